@@ -1,16 +1,22 @@
 use crate::renderer::renderer::RenderedFrame;
 use crate::renderer::{
-    EffectParameters, FramePosition, FrameSize, RenderCmd, RenderMode, RenderResopnse,
-    RendererHandler,
+    Curves, EffectParameters, FramePosition, FrameSize, Lut, RedactRegion, RenderCmd, RenderMode,
+    RenderResopnse, RendererHandler, SpatialParameters, ToneMapping,
 };
 use crate::ui::preview::preview_frame::{PreviewFrameModel, PreviewFrameMsg, PreviewFrameOutput};
-use crate::ui::preview::{CropMode, Orientation};
+use crate::ui::preview::{CropMode, Orientation, RedactionEdit};
 use crate::ui::sidebar::sidebar::{ControlsModel, ControlsMsg, ControlsOutput};
+use crate::ui::sidebar::OutputContainerSettings;
 use crate::ui::video_controls::{VideoControlModel, VideoControlMsg, VideoControlOutput};
+use crate::video::discover::discover_uri;
+use crate::video::export::estimate_export;
+use crate::video::export_queue::{ExportEvent, ExportJob, ExportQueue};
 use crate::video::metadata::VideoInfo;
 use crate::video::player::Player;
+use anyhow::Error;
 use gst::ClockTime;
 use gtk::prelude::{ApplicationExt, WidgetExt};
+use relm4::adw::prelude::{AdwDialogExt, AlertDialogExt};
 use relm4::gtk::prelude::{
     ButtonExt, FileExt, GtkApplicationExt, GtkWindowExt, OrientableExt, RangeExt,
 };
@@ -21,7 +27,7 @@ use relm4::{
 };
 use std::cell::RefCell;
 use std::rc::Rc;
-use std::sync::mpsc;
+use std::sync::Arc;
 
 pub(super) struct App {
     renderer: RendererHandler,
@@ -33,21 +39,20 @@ pub(super) struct App {
     show_spinner: bool,
     video_selected: bool,
     video_is_loaded: bool,
-    video_is_exporting: bool,
     uri: Option<String>,
-    export_sender: Option<mpsc::Sender<RenderedFrame>>,
-    export_video_decode_finished: bool,
-    frames_exported: u32,
-    export_target_frame_count: u32,
+    export_queue: ExportQueue,
+    exports_pending: u32,
+    export_status: Option<String>,
 }
 
 #[derive(Debug)]
 pub(super) enum AppMsg {
     ExportFrame,
     ExportVideo(String),
-    ExportDone,
+    Export(ExportEvent),
     OpenFile,
     SaveFile,
+    ChooseExportFile,
     SetVideo(String),
     Orient(Orientation),
     StraightenBegin,
@@ -55,8 +60,18 @@ pub(super) enum AppMsg {
     StraightenEnd,
     ShowCropBox,
     HideCropBox,
+    ShowRedactions,
+    HideRedactions,
     SetCropMode(CropMode),
     EffectsChanged(EffectParameters),
+    CurvesChanged(Curves),
+    SpatialChanged(SpatialParameters),
+    LutChanged(Option<Arc<Lut>>, f32),
+    RedactionsChanged(Vec<RedactRegion>, Option<usize>),
+    RedactionEdited(RedactionEdit),
+    PreviewSubtitles(Option<u32>),
+    ToneMapping(ToneMapping),
+    VideoStream(u32),
     Seek(ClockTime),
     // fixme: get better names for these 2
     TogglePlayPause,
@@ -72,7 +87,7 @@ pub(super) enum AppMsg {
 #[derive(Debug)]
 pub enum AppCommandMsg {
     InitWithvideo,
-    VideoLoaded,
+    VideoLoaded(Result<VideoInfo, Error>),
     FrameRendered(RenderedFrame),
 }
 
@@ -129,20 +144,76 @@ impl App {
         });
     }
 
-    fn export_frame_position(&self) -> FramePosition {
+    fn export_position(&self, container: &OutputContainerSettings) -> FramePosition {
         let crop_settings = self.preview_frame.model().export_settings();
         let (orientation, angel) = self.sidebar_panel.model().orientation_and_angle();
         let info = self.player.borrow().info();
         let size = FrameSize::new(info.width, info.height);
 
         let mut position = FramePosition::new(size);
-        // todo: apply crop accurately when rotated.
-        position.set_crop_edges_from_percent(crop_settings.bounding_box);
+        if !container.keep_anamorphic {
+            position.pixel_aspect_ratio = info.pixel_aspect();
+        }
         position.orientation = orientation;
+        position.set_crop_edges_from_percent(crop_settings.bounding_box);
         position.straigthen_angle = angel.to_radians();
+        position.tone_mapping = container.tone_mapping;
 
+        if let Some(max_height) = container.max_height {
+            position.limit_output_height(max_height);
+        }
         position
     }
+
+    fn show_export_estimate(&self, sender: &ComponentSender<Self>) {
+        let container = self.sidebar_panel.model().export_settings().container;
+        let timeline_export_settings = self
+            .video_controls
+            .model()
+            .get_export_settings(self.player.clone());
+        let player = self.player.borrow();
+
+        let estimate = estimate_export(
+            timeline_export_settings,
+            player.info.framerate,
+            self.export_position(&container).output_frame_size(),
+            &container,
+            &player.info.container_info,
+        );
+
+        let dialog = adw::AlertDialog::new(Some("Export Video"), Some(&estimate.summary()));
+        dialog.add_responses(&[("cancel", "Cancel"), ("export", "Choose File")]);
+        dialog.set_response_appearance("export", adw::ResponseAppearance::Suggested);
+        dialog.set_default_response(Some("export"));
+        dialog.set_close_response("cancel");
+
+        let sender = sender.clone();
+        dialog.connect_response(None, move |_, response| {
+            if response == "export" {
+                sender.input(AppMsg::ChooseExportFile);
+            }
+        });
+
+        let window = relm4::main_adw_application().active_window();
+        dialog.present(window.as_ref());
+    }
+
+    fn export_job(&self, save_uri: String) -> ExportJob {
+        let settings = self.sidebar_panel.model().export_settings();
+        let timeline = self
+            .video_controls
+            .model()
+            .get_export_settings(self.player.clone());
+
+        ExportJob {
+            source_uri: self.uri.as_ref().unwrap().clone(),
+            save_uri,
+            info: self.player.borrow().info(),
+            position: self.export_position(&settings.container),
+            timeline,
+            settings,
+        }
+    }
 }
 
 #[relm4::component(pub)]
@@ -177,14 +248,14 @@ impl Component for App {
                         pack_start = &gtk::Button {
                             set_label: "Save",
                             #[watch]
-                            set_visible: model.video_selected && !model.video_is_exporting,
+                            set_visible: model.video_selected,
                             add_css_class: "suggested-action",
                             connect_clicked => AppMsg::SaveFile,
                         },
 
                         pack_start : preview_zoom = &gtk::Scale::with_range(gtk::Orientation::Horizontal, 1f64, 4f64, 0.1f64){
                             #[watch]
-                            set_visible: model.video_selected && !model.video_is_exporting,
+                            set_visible: model.video_selected,
                             set_width_request: 120,
 
                             connect_value_changed[sender] => move|scale| {
@@ -192,17 +263,25 @@ impl Component for App {
                             },
                         },
 
+                        pack_end = &gtk::Label {
+                            #[watch]
+                            set_label: model.export_status.as_deref().unwrap_or_default(),
+                            #[watch]
+                            set_visible: model.export_status.is_some(),
+                            add_css_class: "dim-label",
+                        },
+
                         pack_end = &gtk::Button {
                             set_icon_name: "document-open-symbolic",
                             #[watch]
-                            set_visible: model.video_selected && !model.video_is_exporting,
+                            set_visible: model.video_selected,
                             connect_clicked => AppMsg::OpenFile,
                         },
 
                         #[wrap(Some)]
                         set_title_widget: page_switcher = &adw::ViewSwitcherBar{
                             #[watch]
-                            set_visible: model.video_selected && !model.video_is_exporting,
+                            set_visible: model.video_selected,
                             set_reveal: true,
                         },
                     },
@@ -269,6 +348,7 @@ impl Component for App {
             .launch(handler.timer_cmd_sender())
             .forward(sender.input_sender(), |msg| match msg {
                 PreviewFrameOutput::TogglePlayPause => AppMsg::TogglePlayPauseRequested,
+                PreviewFrameOutput::RedactionEdited(edit) => AppMsg::RedactionEdited(edit),
             });
 
         let player = Rc::new(RefCell::new(Player::new(
@@ -290,7 +370,18 @@ impl Component for App {
                 ControlsOutput::HideCropBox => AppMsg::HideCropBox,
                 ControlsOutput::TempResetZoom => AppMsg::ZoomTempReset,
                 ControlsOutput::RestoreZoom => AppMsg::ZoomRestore,
+                ControlsOutput::ShowRedactions => AppMsg::ShowRedactions,
+                ControlsOutput::HideRedactions => AppMsg::HideRedactions,
                 ControlsOutput::EffectsChanged(params) => AppMsg::EffectsChanged(params),
+                ControlsOutput::CurvesChanged(curves) => AppMsg::CurvesChanged(curves),
+                ControlsOutput::SpatialChanged(parameters) => AppMsg::SpatialChanged(parameters),
+                ControlsOutput::LutChanged(lut, intensity) => AppMsg::LutChanged(lut, intensity),
+                ControlsOutput::RedactionsChanged(regions, selected) => {
+                    AppMsg::RedactionsChanged(regions, selected)
+                }
+                ControlsOutput::PreviewSubtitles(stream) => AppMsg::PreviewSubtitles(stream),
+                ControlsOutput::ToneMapping(mode) => AppMsg::ToneMapping(mode),
+                ControlsOutput::VideoStream(stream) => AppMsg::VideoStream(stream),
             });
 
         let timeline: Controller<VideoControlModel> = VideoControlModel::builder()
@@ -321,6 +412,10 @@ impl Component for App {
                 .drop_on_shutdown()
         });
 
+        let export_sender = sender.clone();
+        let export_queue =
+            ExportQueue::new(move |event| export_sender.input(AppMsg::Export(event)));
+
         let model = Self {
             renderer: handler,
             preview_frame,
@@ -330,13 +425,11 @@ impl Component for App {
             show_spinner: false,
             video_selected: false,
             video_is_loaded: false,
-            video_is_exporting: false,
             player,
             uri: path,
-            export_sender: None,
-            export_video_decode_finished: false,
-            frames_exported: 0,
-            export_target_frame_count: 0,
+            export_queue,
+            exports_pending: 0,
+            export_status: None,
         };
 
         let widgets = view_output!();
@@ -392,80 +485,51 @@ impl Component for App {
                 let bus = self.player.borrow_mut().pipeline_bus();
                 sender.oneshot_command(async move {
                     Player::wait_for_pipeline_init(bus);
-                    AppCommandMsg::VideoLoaded
+                    AppCommandMsg::VideoLoaded(discover_uri(&uri))
                 });
             }
             AppMsg::ExportFrame => {}
-            AppMsg::SaveFile => Self::launch_file_save(&sender),
+            AppMsg::SaveFile => self.show_export_estimate(&sender),
+            AppMsg::ChooseExportFile => Self::launch_file_save(&sender),
             AppMsg::ExportVideo(save_uri) => {
-                self.preview_frame.widget().set_visible(false);
-                self.show_video = false;
-                self.show_spinner = true;
-
-                self.player.borrow_mut().set_is_playing(false);
-                self.renderer
-                    .send_render_cmd(RenderCmd::ChangeRenderMode(RenderMode::AllFrames));
-
-                let position = self.export_frame_position();
-                self.renderer
-                    .send_render_cmd(RenderCmd::PositionFrame(position));
-
-                let timeline_export_settings = self
-                    .video_controls
-                    .model()
-                    .get_export_settings(self.player.clone());
-
-                let (tex_sender, receiver) = mpsc::channel();
-                self.export_sender = Some(tex_sender);
-
-                let duration = timeline_export_settings.duration();
-                let framerate = self.player.borrow().info.framerate;
-                let fps = framerate.numer() as f64 / framerate.denom() as f64;
-                let target_frames = (duration.seconds_f64() * fps).ceil() as u32;
-                println!("for duration: {duration} @ {fps}fps. total frames: {target_frames}",);
-                self.export_target_frame_count = target_frames;
-                self.frames_exported = 0;
-
-                self.player.borrow_mut().export_video(
-                    self.uri.as_ref().unwrap().clone(),
-                    save_uri,
-                    timeline_export_settings,
-                    self.sidebar_panel.model().export_settings(),
-                    position.output_frame_size(),
-                    receiver,
-                    sender.clone(),
-                    self.renderer.render_cmd_sender(),
-                    self.renderer.timer_cmd_sender(),
-                );
-                self.video_is_exporting = true;
+                let job = self.export_job(save_uri);
+                self.export_queue.submit(job);
+                self.exports_pending += 1;
+                self.export_status = Some(format!("{} queued", self.exports_pending));
             }
-            AppMsg::ExportDone => {
-                self.video_is_exporting = false;
-                self.export_video_decode_finished = false;
-                self.video_selected = false;
-                self.video_is_loaded = false;
-                self.show_spinner = false;
-                self.show_video = false;
+            AppMsg::Export(event) => match event {
+                ExportEvent::Started(_) => {
+                    self.export_status = Some(format!("Exporting, {} left", self.exports_pending));
+                }
+                ExportEvent::Progress {
+                    frames_done,
+                    frame_count,
+                    ..
+                } => {
+                    let percent = frames_done * 100 / frame_count.max(1);
+                    self.export_status = Some(format!(
+                        "Exporting {percent}%, {} left",
+                        self.exports_pending
+                    ));
+                }
+                ExportEvent::Finished(job_id, result) => {
+                    if let Err(err) = result {
+                        tracing::error!("export {job_id} failed: {err:?}");
+                        show_error("Export failed", &err);
+                    }
 
-                self.player.borrow_mut().reset_pipeline();
-                self.video_controls.emit(VideoControlMsg::Reset);
-                self.export_sender = None;
-                self.renderer
-                    .send_render_cmd(RenderCmd::ChangeRenderMode(RenderMode::MostRecentFrame));
-            }
+                    self.exports_pending -= 1;
+                    self.export_status = Some(format!("{} queued", self.exports_pending))
+                        .filter(|_| self.exports_pending != 0);
+                }
+            },
             AppMsg::TogglePlayPauseRequested => {
                 self.video_controls.emit(VideoControlMsg::TogglePlayPause)
             }
             AppMsg::Seek(timestamp) => self.player.borrow().seek(timestamp),
             AppMsg::TogglePlayPause => self.player.borrow_mut().toggle_play_plause(),
             AppMsg::ToggleMute => self.player.borrow_mut().toggle_mute(),
-            AppMsg::VideoFinished => {
-                if self.video_is_exporting {
-                    self.export_video_decode_finished = true;
-                } else {
-                    self.player.borrow_mut().set_is_finished()
-                }
-            }
+            AppMsg::VideoFinished => self.player.borrow_mut().set_is_finished(),
             AppMsg::Orient(orientation) => {
                 self.renderer
                     .send_render_cmd(RenderCmd::UpdateOrientation(orientation));
@@ -481,6 +545,8 @@ impl Component for App {
             AppMsg::StraightenEnd => self.preview_frame.emit(PreviewFrameMsg::StraightenEnd),
             AppMsg::ShowCropBox => self.preview_frame.emit(PreviewFrameMsg::CropBoxShow),
             AppMsg::HideCropBox => self.preview_frame.emit(PreviewFrameMsg::CropBoxHide),
+            AppMsg::ShowRedactions => self.preview_frame.emit(PreviewFrameMsg::RedactionsShow),
+            AppMsg::HideRedactions => self.preview_frame.emit(PreviewFrameMsg::RedactionsHide),
             AppMsg::SetCropMode(mode) => self.preview_frame.emit(PreviewFrameMsg::CropMode(mode)),
             AppMsg::Zoom(level) => self.preview_frame.emit(PreviewFrameMsg::Zoom(level)),
             AppMsg::ZoomTempReset => {
@@ -499,6 +565,71 @@ impl Component for App {
                     self.renderer.send_render_cmd(RenderCmd::RenderFrame);
                 }
             }
+            AppMsg::CurvesChanged(curves) => {
+                self.renderer
+                    .send_render_cmd(RenderCmd::UpdateCurves(curves));
+
+                if !self.player.borrow().is_playing() {
+                    self.renderer.send_render_cmd(RenderCmd::RenderFrame);
+                }
+            }
+            AppMsg::SpatialChanged(parameters) => {
+                self.renderer
+                    .send_render_cmd(RenderCmd::UpdateSpatial(parameters));
+
+                if !self.player.borrow().is_playing() {
+                    self.renderer.send_render_cmd(RenderCmd::RenderFrame);
+                }
+            }
+            AppMsg::LutChanged(lut, intensity) => {
+                self.renderer
+                    .send_render_cmd(RenderCmd::UpdateLut(lut, intensity));
+
+                if !self.player.borrow().is_playing() {
+                    self.renderer.send_render_cmd(RenderCmd::RenderFrame);
+                }
+            }
+            AppMsg::RedactionsChanged(regions, selected) => {
+                self.renderer
+                    .send_render_cmd(RenderCmd::UpdateRedactions(regions.clone()));
+
+                if !self.player.borrow().is_playing() {
+                    self.renderer.send_render_cmd(RenderCmd::RenderFrame);
+                }
+
+                self.preview_frame
+                    .emit(PreviewFrameMsg::Redactions(regions, selected));
+            }
+            AppMsg::RedactionEdited(edit) => {
+                self.sidebar_panel.emit(ControlsMsg::RedactionEdited(edit))
+            }
+            AppMsg::PreviewSubtitles(stream) => {
+                self.player.borrow_mut().set_subtitle_stream(stream)
+            }
+            AppMsg::ToneMapping(mode) => {
+                self.renderer
+                    .send_render_cmd(RenderCmd::UpdateToneMapping(mode));
+
+                if !self.player.borrow().is_playing() {
+                    self.renderer.send_render_cmd(RenderCmd::RenderFrame);
+                }
+            }
+            AppMsg::VideoStream(stream) => {
+                let mut player = self.player.borrow_mut();
+                if let Err(err) = player.set_video_stream(stream) {
+                    show_error("Could not switch video stream", &err);
+                } else {
+                    let (width, height) = preview_size(&player.info);
+                    self.renderer
+                        .send_render_cmd(RenderCmd::UpdatePixelAspectRatio(
+                            player.info.pixel_aspect(),
+                        ));
+                    self.renderer
+                        .send_render_cmd(RenderCmd::UpdateOutputResolution(width, height));
+                    self.sidebar_panel
+                        .emit(ControlsMsg::SetBaseOrientation(player.info.orientation));
+                }
+            }
         }
 
         self.update_view(widgets, sender);
@@ -512,24 +643,32 @@ impl Component for App {
         _root: &Self::Root,
     ) {
         match message {
-            AppCommandMsg::VideoLoaded => {
+            AppCommandMsg::VideoLoaded(Err(err)) => {
+                self.show_spinner = false;
+                self.video_selected = false;
+                self.player.borrow_mut().reset_pipeline();
+                show_error("Could not open video", &err);
+            }
+            AppCommandMsg::VideoLoaded(Ok(info)) => {
                 self.show_spinner = false;
                 self.show_video = true;
                 self.video_is_loaded = true;
 
                 let mut player = self.player.borrow_mut();
 
-                player.discover_metadata();
+                player.set_info(info);
 
-                self.sidebar_panel.emit(ControlsMsg::VideoLoaded((
-                    player.info.container_info.clone(),
-                    player.info.orientation,
-                )));
+                self.sidebar_panel
+                    .emit(ControlsMsg::VideoLoaded(player.info.clone()));
 
                 player.set_is_playing(true);
 
                 let (width, height) = preview_size(&player.info);
 
+                self.renderer
+                    .send_render_cmd(RenderCmd::UpdatePixelAspectRatio(
+                        player.info.pixel_aspect(),
+                    ));
                 self.renderer
                     .send_render_cmd(RenderCmd::UpdateOutputResolution(width, height));
 
@@ -539,20 +678,10 @@ impl Component for App {
                 self.preview_frame.widget().set_visible(true);
             }
             AppCommandMsg::FrameRendered(frame) => {
-                if self.video_is_exporting {
-                    self.frames_exported += 1;
-                    if let Some(sender) = self.export_sender.as_ref() {
-                        sender.send(frame).unwrap();
-                    };
-
-                    if self.frames_exported == self.export_target_frame_count {
-                        self.export_sender = None;
-                    }
-                } else {
-                    let texture = frame.build_gdk_texture();
-                    self.preview_frame
-                        .emit(PreviewFrameMsg::FrameRendered(texture));
-                }
+                let texture = frame.build_gdk_texture();
+                self.preview_frame
+                    .emit(PreviewFrameMsg::FrameRendered(texture, frame.pts));
+                self.sidebar_panel.emit(ControlsMsg::FrameTime(frame.pts));
             }
             AppCommandMsg::InitWithvideo => {
                 sender.input(AppMsg::SetVideo(self.uri.as_ref().unwrap().clone()));
@@ -563,9 +692,18 @@ impl Component for App {
     }
 }
 
+fn show_error(heading: &str, err: &Error) {
+    let dialog = adw::AlertDialog::new(Some(heading), Some(&err.to_string()));
+    dialog.add_response("close", "Close");
+
+    let window = relm4::main_adw_application().active_window();
+    dialog.present(window.as_ref());
+}
+
 fn preview_size(info: &VideoInfo) -> (u32, u32) {
-    let scale_factor = (info.width as f32 / 1280.0).max(1.0);
-    let scaled_width = (info.width as f32 / scale_factor) as u32;
+    let width = info.display_width();
+    let scale_factor = (width as f32 / 1280.0).max(1.0);
+    let scaled_width = (width as f32 / scale_factor) as u32;
     let scaled_height = (info.height as f32 / scale_factor) as u32;
 
     (scaled_width, scaled_height)
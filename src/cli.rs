@@ -0,0 +1,684 @@
+use crate::renderer::{
+    Curve, CurveChannel, Curves, EffectParameters, FramePosition, FrameSize, HslAdjustments,
+    HslBand, LutSettings, RedactBounds, RedactRegion, SpatialParameters, ToneMapping,
+    MAX_REDACT_REGIONS,
+};
+use crate::ui::sidebar::{ControlsExportSettings, OutputContainerSettings};
+use crate::video::discover::discover_uri;
+use crate::video::export::{validate_container, TimelineExportSettings};
+use crate::video::export_queue::{run_export_job, ExportJob};
+use crate::video::metadata::{AudioCodec, ContainerFormat, VideoCodec, VideoInfo};
+use crate::video::preset::find_preset;
+use anyhow::{anyhow, Error};
+use argh::FromArgs;
+use gst::ClockTime;
+use serde::de::{DeserializeOwned, IntoDeserializer};
+use std::io::Write;
+use std::path::PathBuf;
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+pub enum Command {
+    Export(ExportCommand),
+    Probe(ProbeCommand),
+}
+
+#[derive(FromArgs)]
+/// print what moviola discovers about a file as json
+#[argh(subcommand, name = "probe")]
+pub struct ProbeCommand {
+    #[argh(positional)]
+    /// path of media to probe
+    input: String,
+}
+
+#[derive(FromArgs)]
+/// export a video without opening a window
+#[argh(subcommand, name = "export")]
+pub struct ExportCommand {
+    #[argh(positional)]
+    /// path of media to export
+    input: String,
+
+    #[argh(option, short = 'o')]
+    /// path to write the export to
+    output: String,
+
+    #[argh(option)]
+    /// trim start in seconds
+    start: Option<f64>,
+
+    #[argh(option)]
+    /// trim end in seconds
+    end: Option<f64>,
+
+    #[argh(option, from_str_fn(parse_crop))]
+    /// crop rectangle in source pixels as x,y,width,height, turns with the rotation and flip
+    crop: Option<[u32; 4]>,
+
+    #[argh(option, default = "0")]
+    /// clockwise rotation in degrees, a multiple of 90
+    rotate: u32,
+
+    #[argh(switch)]
+    /// mirror horizontally
+    flip: bool,
+
+    #[argh(option, default = "0.0")]
+    /// straighten angle in degrees
+    straighten: f32,
+
+    #[argh(option, default = "0.0")]
+    /// exposure in stops
+    exposure: f32,
+
+    #[argh(option, default = "1.0")]
+    /// gamma, 1 leaves it unchanged
+    gamma: f32,
+
+    #[argh(option, default = "0.0")]
+    /// highlights from -1 to 1
+    highlights: f32,
+
+    #[argh(option, default = "0.0")]
+    /// shadows from -1 to 1
+    shadows: f32,
+
+    #[argh(option, default = "0.0")]
+    /// whites from -1 to 1
+    whites: f32,
+
+    #[argh(option, default = "0.0")]
+    /// blacks from -1 to 1
+    blacks: f32,
+
+    #[argh(option, default = "1.0")]
+    /// contrast, 1 leaves it unchanged
+    contrast: f32,
+
+    #[argh(option, default = "0.0")]
+    /// brightness, 0 leaves it unchanged
+    brightness: f32,
+
+    #[argh(option, default = "1.0")]
+    /// saturation, 1 leaves it unchanged
+    saturation: f32,
+
+    #[argh(option, default = "0.0")]
+    /// hue rotation in degrees
+    hue: f32,
+
+    #[argh(option, default = "0.0")]
+    /// white balance from -1 (cooler) to 1 (warmer)
+    temperature: f32,
+
+    #[argh(option, default = "0.0")]
+    /// white balance from -1 (green) to 1 (magenta)
+    tint: f32,
+
+    #[argh(option, from_str_fn(parse_hsl))]
+    /// colour mixer band as band:hue,saturation,luminance from -1 to 1, with red, orange,
+    /// yellow, green, aqua, blue, purple or magenta, can be repeated
+    hsl: Vec<(HslBand, [f32; 3])>,
+
+    #[argh(option, from_str_fn(parse_levels))]
+    /// levels as in_black,in_white,gamma,out_black,out_white on display values from 0 to 1
+    levels: Option<[f32; 5]>,
+
+    #[argh(option, from_str_fn(parse_curve))]
+    /// curve as channel:x,y;x,y;... with master, red, green or blue, can be repeated
+    curve: Vec<(CurveChannel, Curve)>,
+
+    #[argh(option)]
+    /// .cube lut to apply
+    lut: Option<PathBuf>,
+
+    #[argh(option, default = "1.0", from_str_fn(parse_unit))]
+    /// lut intensity from 0 to 1
+    lut_intensity: f32,
+
+    #[argh(option, from_str_fn(parse_vignette))]
+    /// vignette as amount,midpoint,roundness,feather, amount from -1 (darker) to 1 (lighter)
+    vignette: Option<[f32; 4]>,
+
+    #[argh(option, from_str_fn(parse_sharpen))]
+    /// sharpening as amount,radius with amount from 0 to 2 and radius in pixels at 1080p
+    sharpen: Option<[f32; 2]>,
+
+    #[argh(option, default = "0.0")]
+    /// blur radius as a fraction of the frame height, up to 0.05
+    blur: f32,
+
+    #[argh(option, from_str_fn(parse_redact))]
+    /// region to hide as shape:style:strength:x,y,width,height, with rectangle or ellipse, blur or
+    /// pixelate, strength as a fraction of the frame height and bounds from 0 to 1 on the
+    /// uncropped frame, moving regions give seconds=x,y,width,height;... keyframes, can be repeated
+    redact: Vec<RedactRegion>,
+
+    #[argh(option)]
+    /// export preset name, see --list-presets
+    preset: Option<String>,
+
+    #[argh(option, from_str_fn(parse_name))]
+    /// output container (mp4, mkv or quicktime)
+    container: Option<ContainerFormat>,
+
+    #[argh(option, from_str_fn(parse_name))]
+    /// video codec (x264, x265, av1, vp9, ...)
+    video_codec: Option<VideoCodec>,
+
+    #[argh(option, from_str_fn(parse_name))]
+    /// audio codec (aac, opus, flac, ...)
+    audio_codec: Option<AudioCodec>,
+
+    #[argh(option, default = "0")]
+    /// index of the video stream to export, as listed by probe
+    video_stream: u32,
+
+    #[argh(switch)]
+    /// leave out audio
+    no_audio: bool,
+
+    #[argh(switch)]
+    /// keep non-square pixels and flag them in the container instead of resampling
+    keep_anamorphic: bool,
+
+    #[argh(option, from_str_fn(parse_name))]
+    /// tone mapping for HDR sources (off, reinhard, hable)
+    tone_mapping: Option<ToneMapping>,
+}
+
+// same lowercase names presets use, without the placeholders for undetected streams
+fn parse_name<T: DeserializeOwned>(value: &str) -> Result<T, String> {
+    if matches!(value, "unknown" | "noaudio") {
+        return Err(format!("{value} can't be chosen"));
+    }
+    T::deserialize(value.into_deserializer())
+        .map_err(|err: serde::de::value::Error| err.to_string())
+}
+
+fn parse_crop(value: &str) -> Result<[u32; 4], String> {
+    let parts = value
+        .split(',')
+        .map(|part| part.trim().parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| err.to_string())?;
+
+    parts
+        .try_into()
+        .map_err(|_| "expected x,y,width,height".to_string())
+}
+
+fn parse_hsl(value: &str) -> Result<(HslBand, [f32; 3]), String> {
+    let (band, shifts) = value
+        .split_once(':')
+        .ok_or_else(|| "expected band:hue,saturation,luminance".to_string())?;
+
+    let band = HslBand::ALL
+        .into_iter()
+        .find(|candidate| candidate.label().eq_ignore_ascii_case(band.trim()))
+        .ok_or_else(|| format!("unknown hsl band {band}"))?;
+
+    let shifts = shifts
+        .split(',')
+        .map(|part| part.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| err.to_string())?;
+
+    let shifts = shifts
+        .try_into()
+        .map_err(|_| "expected band:hue,saturation,luminance".to_string())?;
+    Ok((band, shifts))
+}
+
+fn parse_unit(value: &str) -> Result<f32, String> {
+    let value = value.trim().parse::<f32>().map_err(|err| err.to_string())?;
+    if !(0.0..=1.0).contains(&value) {
+        return Err(format!("{value} is outside 0 to 1"));
+    }
+    Ok(value)
+}
+
+fn parse_floats<const N: usize>(value: &str, expected: &str) -> Result<[f32; N], String> {
+    let parts = value
+        .split(',')
+        .map(|part| part.trim().parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| err.to_string())?;
+
+    parts.try_into().map_err(|_| format!("expected {expected}"))
+}
+
+fn parse_levels(value: &str) -> Result<[f32; 5], String> {
+    parse_floats(value, "in_black,in_white,gamma,out_black,out_white")
+}
+
+fn parse_vignette(value: &str) -> Result<[f32; 4], String> {
+    parse_floats(value, "amount,midpoint,roundness,feather")
+}
+
+fn parse_sharpen(value: &str) -> Result<[f32; 2], String> {
+    parse_floats(value, "amount,radius")
+}
+
+fn parse_redact(value: &str) -> Result<RedactRegion, String> {
+    let [shape, style, strength, bounds]: [&str; 4] = value
+        .splitn(4, ':')
+        .collect::<Vec<_>>()
+        .try_into()
+        .map_err(|_| "expected shape:style:strength:x,y,width,height".to_string())?;
+
+    let parse_bounds = |value: &str| {
+        let [x, y, width, height] = parse_floats(value, "x,y,width,height")?;
+        Ok::<_, String>(RedactBounds::new(x, y, width, height))
+    };
+
+    let keyframes = bounds
+        .split(';')
+        .map(|keyframe| match keyframe.split_once('=') {
+            Some((seconds, bounds)) => {
+                let seconds = seconds
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|err| err.to_string())?;
+                // from_seconds_f64 panics on times it can't hold
+                if !seconds.is_finite() || seconds < 0.0 {
+                    return Err(format!("keyframe time {seconds} is not a time"));
+                }
+                Ok((
+                    Some(ClockTime::from_seconds_f64(seconds)),
+                    parse_bounds(bounds)?,
+                ))
+            }
+            None => Ok((None, parse_bounds(keyframe)?)),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut region = RedactRegion::new(parse_name(shape.trim())?, keyframes[0].1);
+    region.style = parse_name(style.trim())?;
+    region.strength = strength
+        .trim()
+        .parse::<f32>()
+        .map_err(|err| err.to_string())?;
+
+    if let [(None, _)] = keyframes.as_slice() {
+        return Ok(region);
+    }
+    for (time, bounds) in keyframes {
+        let time = time.ok_or_else(|| "expected seconds=x,y,width,height keyframes".to_string())?;
+        region.track(time);
+        region.set_bounds(Some(time), bounds);
+    }
+
+    Ok(region)
+}
+
+fn parse_curve(value: &str) -> Result<(CurveChannel, Curve), String> {
+    let (channel, points) = value
+        .split_once(':')
+        .ok_or_else(|| "expected channel:x,y;x,y".to_string())?;
+
+    let channel = match channel.trim() {
+        "master" => CurveChannel::Master,
+        "red" => CurveChannel::Red,
+        "green" => CurveChannel::Green,
+        "blue" => CurveChannel::Blue,
+        other => return Err(format!("unknown curve channel {other}")),
+    };
+
+    let points = points
+        .split(';')
+        .map(|point| {
+            let (x, y) = point
+                .split_once(',')
+                .ok_or_else(|| format!("expected x,y, got {point}"))?;
+            let parse = |value: &str| value.trim().parse::<f32>().map_err(|err| err.to_string());
+            Ok((parse(x)?, parse(y)?))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let curve = Curve::from_points(points).map_err(|err| err.to_string())?;
+    Ok((channel, curve))
+}
+
+/// Escapes the path, spaces, `#` and `?` are all valid in file names.
+fn file_uri(path: &str) -> Result<String, Error> {
+    let path = PathBuf::from(path).canonicalize()?;
+    Ok(gst::glib::filename_to_uri(&path, None)?.to_string())
+}
+
+pub fn run(command: Command) -> Result<(), Error> {
+    match command {
+        Command::Export(export) => export.run(),
+        Command::Probe(probe) => probe.run(),
+    }
+}
+
+impl ProbeCommand {
+    fn run(self) -> Result<(), Error> {
+        let info = discover_uri(&file_uri(&self.input)?)?;
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        Ok(())
+    }
+}
+
+impl ExportCommand {
+    fn run(self) -> Result<(), Error> {
+        let source_uri = file_uri(&self.input)?;
+        let mut info = discover_uri(&source_uri)?;
+        info.select_video_stream(self.video_stream)?;
+
+        let settings = self.export_settings(&info)?;
+        validate_container(&settings.container)?;
+        let mut position = self.frame_position(&info)?;
+        if let Some(max_height) = settings.container.max_height {
+            position.limit_output_height(max_height);
+        }
+
+        let job = ExportJob {
+            source_uri,
+            save_uri: self.output.clone(),
+            position,
+            timeline: self.timeline(&info)?,
+            settings,
+            info,
+        };
+
+        run_export_job(&job, |frames_done, frame_count| {
+            let percent = frames_done * 100 / frame_count.max(1);
+            eprint!("\rexporting {frames_done}/{frame_count} frames ({percent}%)");
+            let _ = std::io::stderr().flush();
+        })?;
+
+        eprintln!("\nwrote {}", self.output);
+        Ok(())
+    }
+
+    fn timeline(&self, info: &VideoInfo) -> Result<TimelineExportSettings, Error> {
+        if info.duration == ClockTime::ZERO && self.end.is_none() {
+            return Err(anyhow!("the duration is unknown, pass --end"));
+        }
+
+        let time = |flag: &str, seconds: f64| {
+            ClockTime::try_from_seconds_f64(seconds)
+                .map_err(|_| anyhow!("--{flag} {seconds} is not a time"))
+        };
+        let start = time("start", self.start.unwrap_or(0.0))?;
+        let end = match self.end {
+            Some(seconds) => time("end", seconds)?,
+            None => info.duration,
+        };
+
+        if info.duration != ClockTime::ZERO && end > info.duration {
+            return Err(anyhow!(
+                "trim end {end} is past the end of the video ({})",
+                info.duration
+            ));
+        }
+        if start >= end {
+            return Err(anyhow!("trim start must be before the end ({end})"));
+        }
+
+        Ok(TimelineExportSettings { start, end })
+    }
+
+    fn frame_position(&self, info: &VideoInfo) -> Result<FramePosition, Error> {
+        if self.rotate % 90 != 0 {
+            return Err(anyhow!("rotation must be a multiple of 90"));
+        }
+
+        let mut position = FramePosition::new(FrameSize::new(info.width, info.height));
+        if !self.keep_anamorphic {
+            position.pixel_aspect_ratio = info.pixel_aspect();
+        }
+        position.tone_mapping = self.tone_mapping.unwrap_or_default();
+
+        let mut orientation = info.orientation;
+        for _ in 0..(self.rotate / 90) % 4 {
+            orientation.rotate_90_clockwise();
+        }
+        if self.flip {
+            orientation.mirror_horizontally();
+        }
+        position.orientation = orientation;
+        position.straigthen_angle = self.straighten.to_radians();
+
+        if let Some([x, y, width, height]) = self.crop {
+            let fits = |start: u32, length: u32, size: u32| {
+                start.checked_add(length).is_some_and(|end| end <= size)
+            };
+            if !fits(x, width, info.width) || !fits(y, height, info.height) {
+                return Err(anyhow!(
+                    "crop does not fit in the {}x{} frame",
+                    info.width,
+                    info.height
+                ));
+            }
+
+            // crop edges are in display pixels of the oriented frame
+            let to_display = |x: u32| (x as f32 * position.pixel_aspect_ratio).round() as u32;
+            position.crop_edges = orientation.orient_edges([
+                to_display(x),
+                y,
+                to_display(info.width - x - width),
+                info.height - y - height,
+            ]);
+        }
+
+        Ok(position)
+    }
+
+    fn export_settings(&self, info: &VideoInfo) -> Result<ControlsExportSettings, Error> {
+        if self.redact.len() > MAX_REDACT_REGIONS {
+            return Err(anyhow!(
+                "at most {MAX_REDACT_REGIONS} regions can be redacted, got {}",
+                self.redact.len()
+            ));
+        }
+
+        let mut container = match self.preset.as_deref() {
+            Some(name) => find_preset(name)?.container_settings(&info.container_info, 0),
+            None => OutputContainerSettings::from_container_info(&info.container_info),
+        };
+
+        if let Some(format) = self.container {
+            container.container = format;
+        }
+        if let Some(codec) = self.video_codec {
+            container.video_codec = codec;
+        }
+        if let Some(codec) = self.audio_codec {
+            container.audio_codec = codec;
+        }
+        container.no_audio |= self.no_audio || info.container_info.audio_streams.is_empty();
+        container.keep_anamorphic = self.keep_anamorphic && info.is_anamorphic();
+        container.tone_mapping = self.tone_mapping.unwrap_or_default();
+
+        let [levels_input_black, levels_input_white, levels_gamma, levels_output_black, levels_output_white] =
+            self.levels.unwrap_or([0.0, 1.0, 1.0, 0.0, 1.0]);
+
+        let mut hsl = HslAdjustments::default();
+        for &(band, [hue, saturation, luminance]) in &self.hsl {
+            hsl.hue[band as usize] = hue;
+            hsl.saturation[band as usize] = saturation;
+            hsl.luminance[band as usize] = luminance;
+        }
+
+        let mut curves = Curves::default();
+        for (channel, curve) in &self.curve {
+            *curves.channel_mut(*channel) = curve.clone();
+        }
+
+        let mut spatial = SpatialParameters::new();
+        if let Some([amount, midpoint, roundness, feather]) = self.vignette {
+            spatial.set_vignette_amount(amount);
+            spatial.set_vignette_midpoint(midpoint);
+            spatial.set_vignette_roundness(roundness);
+            spatial.set_vignette_feather(feather);
+        }
+        if let Some([amount, radius]) = self.sharpen {
+            spatial.set_sharpen_amount(amount);
+            spatial.set_sharpen_radius(radius);
+        }
+        spatial.set_blur_radius(self.blur);
+
+        Ok(ControlsExportSettings {
+            container,
+            container_is_default: self.preset.is_none() && self.container.is_none(),
+            effect_parameters: EffectParameters {
+                hsl,
+                contrast: self.contrast,
+                brigthness: self.brightness,
+                saturation: self.saturation,
+                hue: self.hue,
+                temperature: self.temperature,
+                tint: self.tint,
+                exposure: self.exposure,
+                gamma: self.gamma,
+                highlights: self.highlights,
+                shadows: self.shadows,
+                whites: self.whites,
+                blacks: self.blacks,
+                levels_input_black,
+                levels_input_white,
+                levels_gamma,
+                levels_output_black,
+                levels_output_white,
+            },
+            curves,
+            spatial,
+            lut: self.lut.clone().map(|path| LutSettings {
+                path,
+                intensity: self.lut_intensity,
+            }),
+            redactions: self.redact.clone(),
+            metadata: Default::default(),
+            subtitles: Default::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::{RedactShape, RedactStyle};
+    use crate::ui::preview::Orientation;
+
+    #[test]
+    fn names_parse_like_presets() {
+        assert_eq!(
+            parse_name::<ContainerFormat>("mkv"),
+            Ok(ContainerFormat::MKV)
+        );
+        assert_eq!(parse_name::<VideoCodec>("x265"), Ok(VideoCodec::X265));
+        assert!(parse_name::<ContainerFormat>("webm").is_err());
+        assert!(parse_name::<ContainerFormat>("unknown").is_err());
+        assert!(parse_name::<AudioCodec>("noaudio").is_err());
+    }
+
+    #[test]
+    fn crop_needs_four_pixel_values() {
+        assert_eq!(parse_crop("10, 20,300,400"), Ok([10, 20, 300, 400]));
+        assert!(parse_crop("10,20,300").is_err());
+        assert!(parse_crop("-10,20,300,400").is_err());
+    }
+
+    #[test]
+    fn floats_need_the_expected_count() {
+        assert_eq!(parse_sharpen("0.5,1.5"), Ok([0.5, 1.5]));
+        assert_eq!(
+            parse_vignette("0.5"),
+            Err("expected amount,midpoint,roundness,feather".to_string())
+        );
+        assert!(parse_levels("0,1,1,0,x").is_err());
+    }
+
+    #[test]
+    fn unit_values_stay_in_range() {
+        assert_eq!(parse_unit("0.25"), Ok(0.25));
+        assert!(parse_unit("1.5").is_err());
+        assert!(parse_unit("-0.1").is_err());
+        assert!(parse_unit("nan").is_err());
+    }
+
+    #[test]
+    fn file_uris_are_escaped() {
+        let dir = std::env::temp_dir().join(format!("moviola cli #{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("50% done?.mkv");
+        std::fs::write(&path, b"").unwrap();
+
+        let uri = file_uri(path.to_str().unwrap()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(uri.starts_with("file:///"));
+        assert!(uri.contains("/moviola%20cli%20%23"));
+        assert!(uri.ends_with("/50%25%20done%3F.mkv"));
+        assert!(file_uri("/does/not/exist.mkv").is_err());
+    }
+
+    fn export_command(args: &[&str]) -> ExportCommand {
+        let args = [&["in.mkv", "-o", "out.mkv"], args].concat();
+        ExportCommand::from_args(&["moviola", "export"], &args).unwrap()
+    }
+
+    #[test]
+    fn trim_must_be_a_time_within_the_video() {
+        let info = VideoInfo {
+            duration: ClockTime::from_seconds(10),
+            ..Default::default()
+        };
+        let timeline = |args: &[&str]| export_command(args).timeline(&info);
+
+        let trimmed = timeline(&["--start", "2", "--end", "4.5"]).unwrap();
+        assert_eq!(trimmed.start, ClockTime::from_seconds(2));
+        assert_eq!(trimmed.end, ClockTime::from_mseconds(4500));
+        assert_eq!(timeline(&[]).unwrap().end, info.duration);
+
+        assert!(timeline(&["--start", "nan"]).is_err());
+        assert!(timeline(&["--end", "inf"]).is_err());
+        assert!(timeline(&["--start", "-1"]).is_err());
+        assert!(timeline(&["--start", "5", "--end", "5"]).is_err());
+        assert!(timeline(&["--end", "12"]).is_err());
+    }
+
+    #[test]
+    fn crop_turns_with_the_source_rotation() {
+        let info = VideoInfo {
+            width: 1920,
+            height: 1080,
+            orientation: Orientation::new_with_base(90.0, false),
+            ..Default::default()
+        };
+        let position = |args: &[&str]| export_command(args).frame_position(&info);
+
+        let rotated = position(&["--crop", "100,50,1600,1000"]).unwrap();
+        assert_eq!(rotated.crop_edges, [30, 100, 50, 220]);
+        let size = rotated.output_frame_size();
+        assert_eq!((size.width, size.height), (1000, 1600));
+
+        // flipped after the tag's rotation, the left and right edges swap
+        let flipped = position(&["--crop", "100,50,1600,1000", "--flip"]).unwrap();
+        assert_eq!(flipped.crop_edges, [50, 100, 30, 220]);
+
+        assert!(position(&["--crop", "4294967295,0,1,1"]).is_err());
+        assert!(position(&["--crop", "0,100,1920,1000"]).is_err());
+    }
+
+    #[test]
+    fn redact_regions_with_keyframes() {
+        let region = parse_redact("ellipse:pixelate:0.02:0.1,0.1,0.2,0.2").unwrap();
+        assert_eq!(region.shape, RedactShape::Ellipse);
+        assert_eq!(region.style, RedactStyle::Pixelate);
+        assert!(!region.is_tracked());
+
+        let region = parse_redact("rectangle:blur:0.02:0=0,0,0.1,0.1;2=0.5,0.5,0.1,0.1").unwrap();
+        assert_eq!(region.keyframes().len(), 2);
+
+        assert!(parse_redact("rectangle:blur:0.02").is_err());
+        assert!(parse_redact("rectangle:blur:0.02:-1=0,0,0.1,0.1").is_err());
+        assert!(parse_redact("rectangle:blur:0.02:nan=0,0,0.1,0.1").is_err());
+        assert!(parse_redact("rectangle:blur:0.02:inf=0,0,0.1,0.1").is_err());
+        assert!(parse_redact("rectangle:blur:0.02:0,0,0.1,0.1;0.5,0.5,0.1,0.1").is_err());
+    }
+}
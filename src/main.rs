@@ -5,6 +5,7 @@ use relm4::{adw, gtk, RelmApp, RELM_THREADS};
 use crate::app::App;
 
 mod app;
+mod cli;
 mod config;
 mod geometry;
 mod range;
@@ -21,6 +22,13 @@ struct Cli {
     #[argh(option, short = 'f')]
     /// path of media to load immediately
     file_path: Option<String>,
+
+    #[argh(switch)]
+    /// print the available export presets and exit
+    list_presets: bool,
+
+    #[argh(subcommand)]
+    command: Option<cli::Command>,
 }
 
 fn initilaize_gresources() {
@@ -39,6 +47,23 @@ fn main() {
         .init();
 
     gst::init().unwrap();
+
+    if cli.list_presets {
+        for preset in video::preset::load_presets() {
+            println!("{}", preset.name);
+        }
+        return;
+    }
+
+    // subcommands run headless, without gtk
+    if let Some(command) = cli.command {
+        if let Err(err) = cli::run(command) {
+            eprintln!("error: {err:?}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     gtk::init().unwrap();
 
     initilaize_gresources();
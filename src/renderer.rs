@@ -1,14 +1,30 @@
+mod backend;
+mod colour;
+mod curves;
 mod effects;
 mod export_texture;
 mod frame_position;
 mod handler;
+mod lut;
+mod overlay;
+mod pass;
 mod presenter;
+mod redact;
 pub mod renderer;
+mod spatial;
 mod texture;
 mod timer;
 
-pub use effects::EffectParameters;
+pub use backend::RenderBackend;
+pub use colour::{input_caps, SourceTransfer, ToneMapping};
+pub use curves::{Curve, CurveChannel, Curves};
+pub use effects::{EffectParameters, HslAdjustments, HslBand};
 pub use frame_position::{FramePosition, FrameSize};
 pub use handler::{RenderCmd, RenderResopnse, TimerCmd};
 pub use handler::{RenderMode, RendererHandler};
+pub use lut::{Lut, LutSettings};
+pub use redact::{
+    RedactBounds, RedactKeyframe, RedactRegion, RedactShape, RedactStyle, MAX_REDACT_REGIONS,
+};
+pub use spatial::SpatialParameters;
 pub use timer::TimerEvent;
@@ -0,0 +1,286 @@
+use ash::vk;
+use std::ffi::CStr;
+use wgpu::hal;
+
+/// Set to render with a software adapter (lavapipe, llvmpipe) even when a gpu is present.
+const SOFTWARE_RENDERER_ENV: &str = "MOVIOLA_SOFTWARE_RENDERER";
+
+const DMABUF_EXTENSIONS: [&CStr; 4] = [
+    vk::EXT_EXTERNAL_MEMORY_DMA_BUF_NAME,
+    vk::KHR_EXTERNAL_MEMORY_FD_NAME,
+    vk::KHR_EXTERNAL_MEMORY_NAME,
+    vk::EXT_IMAGE_DRM_FORMAT_MODIFIER_NAME,
+];
+
+/// How rendered frames leave the gpu, both run the same shaders.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderBackend {
+    /// Vulkan images exported as dmabufs, shared with gtk and the encoders without copying.
+    Dmabuf,
+    /// Frames copied back into memory, works on any adapter including software ones.
+    Readback,
+}
+
+impl RenderBackend {
+    pub fn preferred() -> Self {
+        if std::env::var_os(SOFTWARE_RENDERER_ENV).is_some() {
+            RenderBackend::Readback
+        } else {
+            RenderBackend::Dmabuf
+        }
+    }
+}
+
+pub(crate) struct GpuContext {
+    pub instance: wgpu::Instance,
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+    pub backend: RenderBackend,
+}
+
+/// Falls back to readback when dmabuf export isn't supported, features the adapter lacks are dropped.
+pub(crate) async fn create_context(backend: RenderBackend, features: wgpu::Features) -> GpuContext {
+    if backend == RenderBackend::Dmabuf {
+        if let Some(context) = dmabuf_context(features).await {
+            return context;
+        }
+        tracing::warn!("vulkan dmabuf export unavailable, reading frames back from the gpu");
+    }
+
+    readback_context(features, backend == RenderBackend::Readback).await
+}
+
+async fn dmabuf_context(features: wgpu::Features) -> Option<GpuContext> {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::VULKAN,
+        ..Default::default()
+    });
+
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: None,
+            force_fallback_adapter: false,
+        })
+        .await?;
+
+    if !supports_dmabuf_export(&adapter) {
+        return None;
+    }
+
+    let (device, queue) = create_device_queue(&instance, &adapter, features & adapter.features());
+
+    Some(GpuContext {
+        instance,
+        adapter,
+        device,
+        queue,
+        backend: RenderBackend::Dmabuf,
+    })
+}
+
+async fn readback_context(features: wgpu::Features, software: bool) -> GpuContext {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::PRIMARY | wgpu::Backends::GL,
+        ..Default::default()
+    });
+
+    let request_adapter = |force_fallback_adapter| {
+        instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            compatible_surface: None,
+            force_fallback_adapter,
+        })
+    };
+
+    let mut adapter = request_adapter(software).await;
+    if adapter.is_none() && !software {
+        adapter = request_adapter(true).await;
+    }
+    let adapter = adapter.expect("no gpu or software (lavapipe, llvmpipe) adapter found");
+
+    tracing::info!("rendering with {}", adapter.get_info().name);
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: features & adapter.features(),
+                required_limits: wgpu::Limits::downlevel_defaults()
+                    .using_resolution(adapter.limits()),
+                memory_hints: wgpu::MemoryHints::Performance,
+            },
+            None,
+        )
+        .await
+        .expect("Failed to create device and queue");
+
+    GpuContext {
+        instance,
+        adapter,
+        device,
+        queue,
+        backend: RenderBackend::Readback,
+    }
+}
+
+fn supports_dmabuf_export(adapter: &wgpu::Adapter) -> bool {
+    unsafe {
+        adapter.as_hal::<hal::api::Vulkan, _, _>(|adapter| {
+            adapter.is_some_and(|adapter| {
+                let capabilities = adapter.physical_device_capabilities();
+                DMABUF_EXTENSIONS
+                    .iter()
+                    .all(|extension| capabilities.supports_extension(extension))
+            })
+        })
+    }
+}
+
+fn create_device_queue(
+    instance: &wgpu::Instance,
+    adapter: &wgpu::Adapter,
+    required_features: wgpu::Features,
+) -> (wgpu::Device, wgpu::Queue) {
+    let instance = unsafe {
+        if let Some(instance) = instance.as_hal::<hal::api::Vulkan>() {
+            instance.shared_instance().raw_instance()
+        } else {
+            panic!("Failed to get vulakn hal instance");
+        }
+    };
+
+    let mut open_device = None;
+    let all_features = adapter.features() | required_features;
+    unsafe {
+        adapter.as_hal::<hal::api::Vulkan, _, _>(|adapter| {
+            if let Some(adapter) = adapter {
+                let raw = adapter.raw_physical_device();
+
+                let mut enabled_extensions = adapter.required_device_extensions(all_features);
+                enabled_extensions.extend(DMABUF_EXTENSIONS);
+
+                let mut enabled_phd_features =
+                    adapter.physical_device_features(&enabled_extensions, all_features);
+
+                let queue_create_info = vk::DeviceQueueCreateInfo::default()
+                    .queue_family_index(0)
+                    .queue_priorities(&[1.0]);
+                let queue_family_infos = [queue_create_info];
+
+                let str_pointers = enabled_extensions
+                    .iter()
+                    .map(|&s| s.as_ptr())
+                    .collect::<Vec<_>>();
+
+                let pre_info = vk::DeviceCreateInfo::default()
+                    .queue_create_infos(&queue_family_infos)
+                    .enabled_extension_names(&str_pointers);
+
+                let device_create_info = enabled_phd_features.add_to_device_create(pre_info);
+
+                let raw_device = instance
+                    .create_device(raw, &device_create_info, None)
+                    .expect("Failed to create device");
+
+                open_device = Some(
+                    adapter
+                        .device_from_raw(
+                            raw_device,
+                            None,
+                            &enabled_extensions,
+                            required_features,
+                            &wgpu::MemoryHints::Performance,
+                            0,
+                            0,
+                        )
+                        .expect("Failed to create adapter"),
+                );
+            }
+        })
+    };
+
+    let (device, queue) = unsafe {
+        adapter
+            .create_device_from_hal(
+                open_device.unwrap(),
+                &wgpu::DeviceDescriptor {
+                    required_features,
+                    required_limits: wgpu::Limits::default(),
+                    label: None,
+                    memory_hints: wgpu::MemoryHints::Performance,
+                },
+                None,
+            )
+            .expect("Failed to create device and queue from hal")
+    };
+
+    (device, queue)
+}
+
+/// Mappable copy of a rendered frame, wgpu pads its rows to 256 bytes.
+pub(crate) struct ReadbackBuffer {
+    buffer: wgpu::Buffer,
+    size: wgpu::Extent3d,
+    padded_bytes_per_row: u32,
+}
+
+impl ReadbackBuffer {
+    pub fn new(device: &wgpu::Device, size: wgpu::Extent3d) -> Self {
+        let padded_bytes_per_row =
+            (size.width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback buffer"),
+            size: (padded_bytes_per_row * size.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            buffer,
+            size,
+            padded_bytes_per_row,
+        }
+    }
+
+    pub fn encode_copy(&self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) {
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &self.buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            self.size,
+        );
+    }
+
+    /// Waits for the copy, the returned rows are packed to `width * 4` bytes.
+    pub fn read(&self, device: &wgpu::Device) -> Vec<u8> {
+        let slice = self.buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| ());
+        device.poll(wgpu::Maintain::wait()).panic_on_timeout();
+
+        let bytes_per_row = (self.size.width * 4) as usize;
+        let mut pixels = Vec::with_capacity(bytes_per_row * self.size.height as usize);
+        {
+            let mapped = slice.get_mapped_range();
+            for row in mapped.chunks_exact(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..bytes_per_row]);
+            }
+        }
+        self.buffer.unmap();
+
+        pixels
+    }
+}
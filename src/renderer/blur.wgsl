@@ -0,0 +1,41 @@
+struct Blur {
+    direction: vec2<i32>,
+    // a fraction of the frame height
+    radius: f32,
+}
+
+@group(0) @binding(0) var input: texture_storage_2d<rgba16float, read>;
+@group(0) @binding(1) var output: texture_storage_2d<rgba16float, write>;
+@group(1) @binding(0) var<uniform> blur: Blur;
+
+@compute
+@workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let coords = global_invocation_id.xy;
+    let size = textureDimensions(output);
+
+    if all(coords < size) {
+        let radius = blur.radius * f32(size.y);
+        textureStore(output, coords, gaussian(vec2<i32>(coords), radius));
+    }
+}
+
+// the radius covers three standard deviations, the kernel is clamped to the frame's edges.
+// every pixel under it is read, skipping pixels between taps aliased fine detail on wide blurs
+fn gaussian(coords: vec2<i32>, radius: f32) -> vec4<f32> {
+    let sigma = max(radius / 3.0, 0.01);
+    let taps = i32(ceil(radius));
+    let max_coords = vec2<i32>(textureDimensions(input)) - 1;
+
+    var total = vec4<f32>(0.0);
+    var weights = 0.0;
+    for (var offset = -taps; offset <= taps; offset++) {
+        let weight = exp(-f32(offset * offset) / (2.0 * sigma * sigma));
+        let sample_coords = clamp(coords + blur.direction * offset, vec2<i32>(0), max_coords);
+
+        total += textureLoad(input, sample_coords) * weight;
+        weights += weight;
+    }
+
+    return total / weights;
+}
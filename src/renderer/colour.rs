@@ -0,0 +1,84 @@
+use crate::video::selectable::SelectableOption;
+use gst_video::{VideoColorimetry, VideoTransferFunction};
+use serde::{Deserialize, Serialize};
+
+/// How HDR sources are brought into the 8 bit SDR range the renderer outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToneMapping {
+    /// Keeps the HDR signal as is, clipped to 8 bit and tagged with the source's colorimetry.
+    Off,
+    Reinhard,
+    #[default]
+    Hable,
+}
+
+impl SelectableOption for ToneMapping {
+    const OPTIONS: &'static [Self] = &[ToneMapping::Off, ToneMapping::Reinhard, ToneMapping::Hable];
+    const UNLISTED: Self = ToneMapping::Hable;
+
+    fn display(&self) -> &'static str {
+        match self {
+            ToneMapping::Off => "Off",
+            ToneMapping::Reinhard => "Reinhard",
+            ToneMapping::Hable => "Filmic",
+        }
+    }
+}
+
+impl ToneMapping {
+    // matches position.wgsl
+    pub(crate) fn shader_id(&self) -> u32 {
+        match self {
+            ToneMapping::Off => 0,
+            ToneMapping::Reinhard => 1,
+            ToneMapping::Hable => 2,
+        }
+    }
+}
+
+/// Transfer function of the decoded frames, read from each sample's caps.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum SourceTransfer {
+    #[default]
+    Sdr,
+    Pq,
+    Hlg,
+}
+
+impl SourceTransfer {
+    pub fn from_colorimetry(colorimetry: &VideoColorimetry) -> Self {
+        match colorimetry.transfer() {
+            VideoTransferFunction::Smpte2084 => SourceTransfer::Pq,
+            VideoTransferFunction::AribStdB67 => SourceTransfer::Hlg,
+            _ => SourceTransfer::Sdr,
+        }
+    }
+
+    // matches position.wgsl
+    pub(crate) fn shader_id(&self) -> u32 {
+        match self {
+            SourceTransfer::Sdr => 0,
+            SourceTransfer::Pq => 1,
+            SourceTransfer::Hlg => 2,
+        }
+    }
+}
+
+/// Decoded frames deeper than 8 bit are uploaded as 10 bit so HDR isn't clipped before tone mapping.
+pub fn input_texture_format(format: gst_video::VideoFormat) -> wgpu::TextureFormat {
+    match format {
+        gst_video::VideoFormat::Rgb10a2Le => wgpu::TextureFormat::Rgb10a2Unorm,
+        _ => wgpu::TextureFormat::Rgba8Unorm,
+    }
+}
+
+/// Formats the renderer accepts from decoders, videoconvert picks the one that keeps the depth.
+pub fn input_caps() -> gst::Caps {
+    gst_video::VideoCapsBuilder::new()
+        .format_list([
+            gst_video::VideoFormat::Rgba,
+            gst_video::VideoFormat::Rgb10a2Le,
+        ])
+        .build()
+}
@@ -0,0 +1,290 @@
+use anyhow::{bail, Error};
+
+/// Entries baked per channel, the effects shader interpolates between them.
+pub(crate) const CURVE_TABLE_SIZE: u32 = 1024;
+/// Closest two control points can be horizontally.
+const MIN_POINT_GAP: f32 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CurveChannel {
+    /// Applied to every channel before its own curve.
+    Master,
+    Red,
+    Green,
+    Blue,
+}
+
+impl CurveChannel {
+    pub const ALL: [CurveChannel; 4] = [
+        CurveChannel::Master,
+        CurveChannel::Red,
+        CurveChannel::Green,
+        CurveChannel::Blue,
+    ];
+}
+
+/// A tone curve through control points on display encoded values, both axes run from 0 to 1.
+///
+/// Points are kept sorted by x, outside the first and last point the curve holds their value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    points: Vec<(f32, f32)>,
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Self {
+            points: vec![(0.0, 0.0), (1.0, 1.0)],
+        }
+    }
+}
+
+impl Curve {
+    pub fn from_points(mut points: Vec<(f32, f32)>) -> Result<Self, Error> {
+        if points.len() < 2 {
+            bail!("a curve needs at least two points");
+        }
+        if points
+            .iter()
+            .any(|&(x, y)| !(0.0..=1.0).contains(&x) || !(0.0..=1.0).contains(&y))
+        {
+            bail!("curve points must be between 0 and 1");
+        }
+
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if points
+            .windows(2)
+            .any(|pair| pair[1].0 - pair[0].0 < MIN_POINT_GAP)
+        {
+            bail!("curve points must be at least {MIN_POINT_GAP} apart");
+        }
+
+        Ok(Self { points })
+    }
+
+    pub fn points(&self) -> &[(f32, f32)] {
+        &self.points
+    }
+
+    pub fn is_identity(&self) -> bool {
+        let on_diagonal = |&(x, y): &(f32, f32)| (x - y).abs() < 1e-4;
+        self.points.first() == Some(&(0.0, 0.0))
+            && self.points.last() == Some(&(1.0, 1.0))
+            && self.points.iter().all(on_diagonal)
+    }
+
+    /// Adds a point, returning its index, or `None` when it is too close to an existing point.
+    pub fn insert(&mut self, (x, y): (f32, f32)) -> Option<usize> {
+        let (x, y) = (x.clamp(0.0, 1.0), y.clamp(0.0, 1.0));
+        if self
+            .points
+            .iter()
+            .any(|point| (point.0 - x).abs() < MIN_POINT_GAP)
+        {
+            return None;
+        }
+
+        let idx = self.points.partition_point(|point| point.0 < x);
+        self.points.insert(idx, (x, y));
+        Some(idx)
+    }
+
+    /// Moves a point, keeping it between its neighbours so indices stay stable.
+    pub fn move_point(&mut self, idx: usize, (x, y): (f32, f32)) {
+        let min_x = match idx {
+            0 => 0.0,
+            _ => self.points[idx - 1].0 + MIN_POINT_GAP,
+        };
+        let max_x = match self.points.get(idx + 1) {
+            Some(next) => next.0 - MIN_POINT_GAP,
+            None => 1.0,
+        };
+
+        self.points[idx] = (x.clamp(min_x, max_x), y.clamp(0.0, 1.0));
+    }
+
+    /// The first and last points can't be removed, only moved.
+    pub fn remove(&mut self, idx: usize) {
+        if idx > 0 && idx < self.points.len() - 1 {
+            self.points.remove(idx);
+        }
+    }
+
+    pub fn evaluate(&self, x: f32) -> f32 {
+        self.evaluate_with(&self.tangents(), x)
+    }
+
+    /// Fritsch-Carlson tangents, the curve never overshoots between monotonic points.
+    fn tangents(&self) -> Vec<f32> {
+        let points = &self.points;
+        let secants: Vec<f32> = points
+            .windows(2)
+            .map(|pair| (pair[1].1 - pair[0].1) / (pair[1].0 - pair[0].0))
+            .collect();
+
+        let mut tangents: Vec<f32> = (0..points.len())
+            .map(|idx| match idx {
+                0 => secants[0],
+                _ if idx == points.len() - 1 => secants[idx - 1],
+                _ if secants[idx - 1] * secants[idx] <= 0.0 => 0.0,
+                _ => (secants[idx - 1] + secants[idx]) / 2.0,
+            })
+            .collect();
+
+        for (idx, &secant) in secants.iter().enumerate() {
+            if secant == 0.0 {
+                tangents[idx] = 0.0;
+                tangents[idx + 1] = 0.0;
+                continue;
+            }
+
+            let a = tangents[idx] / secant;
+            let b = tangents[idx + 1] / secant;
+            let magnitude = a * a + b * b;
+            if magnitude > 9.0 {
+                let scale = 3.0 / magnitude.sqrt();
+                tangents[idx] = scale * a * secant;
+                tangents[idx + 1] = scale * b * secant;
+            }
+        }
+
+        tangents
+    }
+
+    fn evaluate_with(&self, tangents: &[f32], x: f32) -> f32 {
+        let points = &self.points;
+        let (first, last) = (points[0], points[points.len() - 1]);
+        if x <= first.0 {
+            return first.1;
+        }
+        if x >= last.0 {
+            return last.1;
+        }
+
+        let idx = points.partition_point(|point| point.0 <= x) - 1;
+        let ((x0, y0), (x1, y1)) = (points[idx], points[idx + 1]);
+        let width = x1 - x0;
+        let t = (x - x0) / width;
+        let (t2, t3) = (t * t, t * t * t);
+
+        let value = (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+            + (t3 - 2.0 * t2 + t) * width * tangents[idx]
+            + (-2.0 * t3 + 3.0 * t2) * y1
+            + (t3 - t2) * width * tangents[idx + 1];
+
+        value.clamp(0.0, 1.0)
+    }
+
+    fn bake(&self) -> Vec<f32> {
+        let tangents = self.tangents();
+        (0..CURVE_TABLE_SIZE)
+            .map(|idx| self.evaluate_with(&tangents, idx as f32 / (CURVE_TABLE_SIZE - 1) as f32))
+            .collect()
+    }
+}
+
+/// The master curve and a curve per channel, applied after the other colour effects.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Curves {
+    pub master: Curve,
+    pub red: Curve,
+    pub green: Curve,
+    pub blue: Curve,
+}
+
+impl Curves {
+    pub fn is_identity(&self) -> bool {
+        CurveChannel::ALL
+            .iter()
+            .all(|&channel| self.channel(channel).is_identity())
+    }
+
+    pub fn channel(&self, channel: CurveChannel) -> &Curve {
+        match channel {
+            CurveChannel::Master => &self.master,
+            CurveChannel::Red => &self.red,
+            CurveChannel::Green => &self.green,
+            CurveChannel::Blue => &self.blue,
+        }
+    }
+
+    pub fn channel_mut(&mut self, channel: CurveChannel) -> &mut Curve {
+        match channel {
+            CurveChannel::Master => &mut self.master,
+            CurveChannel::Red => &mut self.red,
+            CurveChannel::Green => &mut self.green,
+            CurveChannel::Blue => &mut self.blue,
+        }
+    }
+
+    /// One rgb entry per input level, the master curve folded into each channel's curve.
+    pub(crate) fn bake(&self) -> Vec<[f32; 3]> {
+        let master = self.master.bake();
+        let tangents = [&self.red, &self.green, &self.blue].map(Curve::tangents);
+
+        master
+            .iter()
+            .map(|&level| {
+                [
+                    self.red.evaluate_with(&tangents[0], level),
+                    self.green.evaluate_with(&tangents[1], level),
+                    self.blue.evaluate_with(&tangents[2], level),
+                ]
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity_curve_bakes_to_a_ramp() {
+        let curves = Curves::default();
+        assert!(curves.is_identity());
+
+        for (idx, entry) in curves.bake().iter().enumerate() {
+            let level = idx as f32 / (CURVE_TABLE_SIZE - 1) as f32;
+            assert!(entry.iter().all(|value| (value - level).abs() < 1e-5));
+        }
+    }
+
+    #[test]
+    fn monotonic_points_never_overshoot() {
+        let curve =
+            Curve::from_points(vec![(0.0, 0.0), (0.2, 0.6), (0.25, 0.62), (1.0, 1.0)]).unwrap();
+
+        let samples = curve.bake();
+        assert!(samples.windows(2).all(|pair| pair[1] >= pair[0]));
+        assert!((curve.evaluate(0.2) - 0.6).abs() < 1e-5);
+    }
+
+    #[test]
+    fn master_applies_before_channels() {
+        let mut curves = Curves::default();
+        curves.master = Curve::from_points(vec![(0.0, 0.5), (1.0, 0.5)]).unwrap();
+        curves.red = Curve::from_points(vec![(0.0, 0.0), (0.5, 1.0), (1.0, 1.0)]).unwrap();
+
+        let table = curves.bake();
+        assert!(table.iter().all(|entry| *entry == [1.0, 0.5, 0.5]));
+    }
+
+    #[test]
+    fn points_keep_their_order() {
+        let mut curve = Curve::default();
+        let idx = curve.insert((0.5, 0.7)).unwrap();
+        assert_eq!(curve.insert((0.505, 0.2)), None);
+
+        curve.move_point(idx, (1.5, 0.2));
+        assert_eq!(curve.points()[idx], (1.0 - MIN_POINT_GAP, 0.2));
+
+        curve.remove(0);
+        assert_eq!(curve.points().len(), 3);
+        curve.remove(idx);
+        assert!(curve.is_identity());
+
+        assert!(Curve::from_points(vec![(0.5, 0.5)]).is_err());
+        assert!(Curve::from_points(vec![(0.0, 0.0), (0.001, 0.5), (1.0, 1.0)]).is_err());
+    }
+}
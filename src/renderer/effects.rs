@@ -1,20 +1,112 @@
 use crate::range::Range;
+use crate::renderer::curves::{Curves, CURVE_TABLE_SIZE};
+use crate::renderer::lut::create_table;
+use crate::renderer::pass::{
+    sampled_texture_entry, sampler_entry, uniform_entry, with_srgb, EffectPass,
+};
+use crate::renderer::timer::QuerySet;
 use wgpu::util::DeviceExt;
 
+pub const HSL_BANDS: usize = 8;
+
+/// The colour mixer's bands, named after the hue at their centre.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HslBand {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Aqua,
+    Blue,
+    Purple,
+    Magenta,
+}
+
+impl HslBand {
+    pub const ALL: [HslBand; HSL_BANDS] = [
+        HslBand::Red,
+        HslBand::Orange,
+        HslBand::Yellow,
+        HslBand::Green,
+        HslBand::Aqua,
+        HslBand::Blue,
+        HslBand::Purple,
+        HslBand::Magenta,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            HslBand::Red => "Red",
+            HslBand::Orange => "Orange",
+            HslBand::Yellow => "Yellow",
+            HslBand::Green => "Green",
+            HslBand::Aqua => "Aqua",
+            HslBand::Blue => "Blue",
+            HslBand::Purple => "Purple",
+            HslBand::Magenta => "Magenta",
+        }
+    }
+}
+
+/// Shifts from -1 to 1 for each band, indexed by `HslBand`.
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct HslAdjustments {
+    pub hue: [f32; HSL_BANDS],
+    pub saturation: [f32; HSL_BANDS],
+    pub luminance: [f32; HSL_BANDS],
+}
+
 #[repr(C)]
 #[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct EffectParameters {
+    /// First so the arrays keep the 16 byte alignment uniforms need.
+    pub hsl: HslAdjustments,
     pub contrast: f32,
     pub brigthness: f32,
     pub saturation: f32,
+    /// Degrees around the colour wheel.
+    pub hue: f32,
+    /// Negative is cooler (blue), positive warmer (amber).
+    pub temperature: f32,
+    /// Negative is green, positive magenta.
+    pub tint: f32,
+    /// Stops of gain, applied in linear light.
+    pub exposure: f32,
+    pub gamma: f32,
+    pub highlights: f32,
+    pub shadows: f32,
+    pub whites: f32,
+    pub blacks: f32,
+    /// Levels work on display encoded values from 0 to 1.
+    pub levels_input_black: f32,
+    pub levels_input_white: f32,
+    pub levels_gamma: f32,
+    pub levels_output_black: f32,
+    pub levels_output_white: f32,
 }
 
 impl Default for EffectParameters {
     fn default() -> Self {
         Self {
+            hsl: HslAdjustments::default(),
             contrast: 1f32,
             brigthness: 0f32,
             saturation: 1f32,
+            hue: 0f32,
+            temperature: 0f32,
+            tint: 0f32,
+            exposure: 0f32,
+            gamma: 1f32,
+            highlights: 0f32,
+            shadows: 0f32,
+            whites: 0f32,
+            blacks: 0f32,
+            levels_input_black: 0f32,
+            levels_input_white: 1f32,
+            levels_gamma: 1f32,
+            levels_output_black: 0f32,
+            levels_output_white: 1f32,
         }
     }
 }
@@ -25,23 +117,30 @@ impl EffectParameters {
     }
 
     pub fn reset(&mut self) {
+        self.hsl = HslAdjustments::default();
         self.contrast = 1f32;
         self.brigthness = 0f32;
         self.saturation = 1f32;
+        self.hue = 0f32;
+        self.temperature = 0f32;
+        self.tint = 0f32;
+        self.exposure = 0f32;
+        self.gamma = 1f32;
+        self.highlights = 0f32;
+        self.shadows = 0f32;
+        self.whites = 0f32;
+        self.blacks = 0f32;
+        self.levels_input_black = 0f32;
+        self.levels_input_white = 1f32;
+        self.levels_gamma = 1f32;
+        self.levels_output_black = 0f32;
+        self.levels_output_white = 1f32;
     }
 
     pub fn is_default(&self) -> bool {
         self == &Default::default()
     }
 
-    pub fn buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
-        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Effects Buffer"),
-            contents: bytemuck::cast_slice(&[*self]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        })
-    }
-
     pub fn set_contrast(&mut self, value: f32) {
         self.contrast = value;
     }
@@ -65,4 +164,258 @@ impl EffectParameters {
     pub fn saturation_range() -> (Range, Range) {
         (Range::new(0.0, 2.0), Range::new(-100.0, 100.0))
     }
+
+    pub fn set_hue(&mut self, value: f32) {
+        self.hue = value;
+    }
+
+    pub fn hue_range() -> (Range, Range) {
+        (Range::new(-180.0, 180.0), Range::new(-180.0, 180.0))
+    }
+
+    pub fn set_temperature(&mut self, value: f32) {
+        self.temperature = value;
+    }
+
+    /// The shader scales channels in stops, so equal slider steps look like equal shifts.
+    pub fn temperature_range() -> (Range, Range) {
+        (Range::new(-1.0, 1.0), Range::new(-100.0, 100.0))
+    }
+
+    pub fn set_tint(&mut self, value: f32) {
+        self.tint = value;
+    }
+
+    pub fn tint_range() -> (Range, Range) {
+        (Range::new(-1.0, 1.0), Range::new(-100.0, 100.0))
+    }
+
+    pub fn set_exposure(&mut self, value: f32) {
+        self.exposure = value;
+    }
+
+    /// Shown in stops.
+    pub fn exposure_range() -> (Range, Range) {
+        (Range::new(-3.0, 3.0), Range::new(-3.0, 3.0))
+    }
+
+    pub fn set_gamma(&mut self, value: f32) {
+        self.gamma = value;
+    }
+
+    pub fn gamma_range() -> (Range, Range) {
+        (Range::new(0.5, 1.5), Range::new(-100.0, 100.0))
+    }
+
+    pub fn set_highlights(&mut self, value: f32) {
+        self.highlights = value;
+    }
+
+    /// Highlights, shadows, whites and blacks share a range, the shader maps them to stops.
+    pub fn tonal_range() -> (Range, Range) {
+        (Range::new(-1.0, 1.0), Range::new(-100.0, 100.0))
+    }
+
+    pub fn set_shadows(&mut self, value: f32) {
+        self.shadows = value;
+    }
+
+    pub fn set_whites(&mut self, value: f32) {
+        self.whites = value;
+    }
+
+    pub fn set_blacks(&mut self, value: f32) {
+        self.blacks = value;
+    }
+
+    pub fn set_levels_input_black(&mut self, value: f32) {
+        self.levels_input_black = value;
+    }
+
+    pub fn set_levels_input_white(&mut self, value: f32) {
+        self.levels_input_white = value;
+    }
+
+    pub fn set_levels_output_black(&mut self, value: f32) {
+        self.levels_output_black = value;
+    }
+
+    pub fn set_levels_output_white(&mut self, value: f32) {
+        self.levels_output_white = value;
+    }
+
+    /// Input and output black points, shown as 8 bit levels.
+    pub fn levels_black_range() -> (Range, Range) {
+        (
+            Range::new_with_default(0.0, 1.0, 0.0),
+            Range::new_with_default(0.0, 255.0, 0.0),
+        )
+    }
+
+    /// Input and output white points, shown as 8 bit levels.
+    pub fn levels_white_range() -> (Range, Range) {
+        (
+            Range::new_with_default(0.0, 1.0, 1.0),
+            Range::new_with_default(0.0, 255.0, 255.0),
+        )
+    }
+
+    pub fn set_levels_gamma(&mut self, value: f32) {
+        self.levels_gamma = value;
+    }
+
+    pub fn levels_gamma_range() -> (Range, Range) {
+        (Range::new(0.25, 1.75), Range::new(-100.0, 100.0))
+    }
+
+    pub fn set_hsl_hue(&mut self, band: HslBand, value: f32) {
+        self.hsl.hue[band as usize] = value;
+    }
+
+    pub fn set_hsl_saturation(&mut self, band: HslBand, value: f32) {
+        self.hsl.saturation[band as usize] = value;
+    }
+
+    pub fn set_hsl_luminance(&mut self, band: HslBand, value: f32) {
+        self.hsl.luminance[band as usize] = value;
+    }
+
+    /// Shared by the hue, saturation and luminance shifts, the shader scales each.
+    pub fn hsl_range() -> (Range, Range) {
+        (Range::new(-1.0, 1.0), Range::new(-100.0, 100.0))
+    }
+}
+
+/// What the effects shader reads, the parameters plus whether the curve table is used.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ColourUniform {
+    parameters: EffectParameters,
+    curves: u32,
+    _padding: [u32; 2],
+}
+
+/// Applies the colour `EffectParameters` and `Curves`, see `effects.wgsl`.
+pub(crate) struct ColourPass {
+    parameters: EffectParameters,
+    curves: Curves,
+    buffer: wgpu::Buffer,
+    curve_table: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+}
+
+impl ColourPass {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let parameters = EffectParameters::new();
+        let curves = Curves::default();
+
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Effects Buffer"),
+            contents: bytemuck::cast_slice(&[ColourUniform {
+                parameters,
+                curves: 0,
+                _padding: [0; 2],
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("curve sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            curve_table: Self::create_curve_table(device, queue, &curves),
+            parameters,
+            curves,
+            buffer,
+            sampler,
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, parameters: EffectParameters) {
+        self.parameters = parameters;
+        self.write_uniform(queue);
+    }
+
+    /// Bakes the curves into the table the shader samples.
+    pub fn update_curves(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, curves: Curves) {
+        self.curve_table = Self::create_curve_table(device, queue, &curves);
+        self.curves = curves;
+        self.write_uniform(queue);
+    }
+
+    fn write_uniform(&self, queue: &wgpu::Queue) {
+        let uniform = ColourUniform {
+            parameters: self.parameters,
+            curves: !self.curves.is_identity() as u32,
+            _padding: [0; 2],
+        };
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    fn create_curve_table(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        curves: &Curves,
+    ) -> wgpu::TextureView {
+        create_table(
+            device,
+            queue,
+            wgpu::TextureDimension::D2,
+            CURVE_TABLE_SIZE,
+            &curves.bake(),
+        )
+    }
+}
+
+impl EffectPass for ColourPass {
+    fn label(&self) -> &'static str {
+        "colour effects pass"
+    }
+
+    fn shader(&self) -> wgpu::ShaderModuleDescriptor<'static> {
+        with_srgb("effects.wgsl", include_str!("effects.wgsl"))
+    }
+
+    fn layout_entries(&self) -> Vec<wgpu::BindGroupLayoutEntry> {
+        vec![
+            uniform_entry(0),
+            sampled_texture_entry(1, wgpu::TextureViewDimension::D2),
+            sampler_entry(2),
+        ]
+    }
+
+    fn bind_group_entries(&self) -> Vec<wgpu::BindGroupEntry<'_>> {
+        vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: self.buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&self.curve_table),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(&self.sampler),
+            },
+        ]
+    }
+
+    fn is_identity(&self) -> bool {
+        self.parameters.is_default() && self.curves.is_identity()
+    }
+
+    fn workgroup_size(&self) -> (u32, u32) {
+        (256, 1)
+    }
+
+    fn query_set(&self) -> Option<QuerySet> {
+        Some(QuerySet::Effects)
+    }
 }
@@ -1,12 +1,47 @@
 struct Parameters {
+    // eight bands each, red first
+    hsl_hue: array<vec4<f32>, 2>,
+    hsl_saturation: array<vec4<f32>, 2>,
+    hsl_luminance: array<vec4<f32>, 2>,
     contrast: f32,
     brightness: f32,
     saturation: f32,
+    hue: f32,
+    temperature: f32,
+    tint: f32,
+    exposure: f32,
+    gamma: f32,
+    highlights: f32,
+    shadows: f32,
+    whites: f32,
+    blacks: f32,
+    levels_input_black: f32,
+    levels_input_white: f32,
+    levels_gamma: f32,
+    levels_output_black: f32,
+    levels_output_white: f32,
+    // non zero when the curve table isn't an identity
+    curves: u32,
 }
 
-@group(0) @binding(0) var input: texture_storage_2d<rgba8unorm, read>;
-@group(0) @binding(1) var<uniform> params: Parameters;
-@group(0) @binding(2) var output: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(0) var input: texture_storage_2d<rgba16float, read>;
+@group(0) @binding(1) var output: texture_storage_2d<rgba16float, write>;
+@group(1) @binding(0) var<uniform> params: Parameters;
+// r, g and b curves with the master curve folded in
+@group(1) @binding(1) var curve_table: texture_2d<f32>;
+@group(1) @binding(2) var s_curve: sampler;
+
+// stops of gain at the ends of the temperature and tint sliders
+const white_balance_stops = 0.35;
+// stops moved at the ends of the highlights/shadows and whites/blacks sliders
+const tonal_stops = 2.0;
+const extremes_stops = 1.5;
+const mid_grey = 0.18;
+// degrees and stops moved at the ends of the hsl sliders
+const hsl_hue_degrees = 30.0;
+const hsl_luminance_stops = 1.0;
+// centre of each hsl band, red repeated to close the wheel
+const band_centres = array<f32, 9>(0.0, 30.0, 60.0, 120.0, 180.0, 225.0, 270.0, 315.0, 360.0);
 
 @compute
 @workgroup_size(256, 1, 1)
@@ -15,24 +50,181 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
 
     if all(coords < textureDimensions(output)) {
         let colour = textureLoad(input, coords);
-        let final_colour = apply_colour_effects(colour);
+        let final_colour = apply_colour_effects(colour.rgb);
+
+        textureStore(output, coords, vec4<f32>(final_colour, colour.a));
+    }
+}
+
+// the frame is in linear light, positioning decodes it and the output pass encodes it again
+fn apply_colour_effects(linear: vec3<f32>) -> vec3<f32> {
+    let toned = tonal_adjustments(linear * exp2(params.exposure));
+    let gamma_corrected = pow(toned, vec3<f32>(1.0 / params.gamma));
+    let balanced = white_balance(gamma_corrected);
+    let contrast_bright = contrast_brigtness(balanced);
+    let hue_rotated = rotate_hue(contrast_bright, params.hue);
+    let saturated = max(hsl_mixer(saturate(hue_rotated)), vec3<f32>(0.0));
+
+    if levels_is_identity() && params.curves == 0u {
+        return saturated;
+    }
+
+    // levels and curves are edited against display encoded values, like other editors
+    let encoded = linear_to_srgb(saturated);
+    return srgb_to_linear(apply_curves(apply_levels(encoded)));
+}
 
-        textureStore(output, coords, final_colour);
+// bt.709 coefficients for linear rgb
+fn luminance(colour: vec3<f32>) -> f32 {
+    return dot(colour, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
+
+// moves luminance in stops around mid grey with smooth masks, the colour ratios are kept
+fn tonal_adjustments(linear: vec3<f32>) -> vec3<f32> {
+    let y = luminance(linear);
+    // pure black has no stops to move
+    if y <= 1e-5 {
+        return linear;
     }
+
+    // stops relative to mid grey, white is ~2.5
+    let ev = log2(y / mid_grey);
+
+    let shadows_mask = 1.0 - smoothstep(-5.0, 0.0, ev);
+    let highlights_mask = smoothstep(0.0, 2.5, ev);
+    let blacks_mask = 1.0 - smoothstep(-7.0, -3.0, ev);
+    let whites_mask = smoothstep(1.5, 2.5, ev);
+
+    let adjusted_ev = ev
+        + tonal_stops * (params.shadows * shadows_mask + params.highlights * highlights_mask)
+        + extremes_stops * (params.blacks * blacks_mask + params.whites * whites_mask);
+
+    return linear * exp2(adjusted_ev - ev);
 }
 
-fn apply_colour_effects(colour: vec4<f32> ) -> vec4<f32> {
-    let contrast_bright = contrast_brigtness(colour);
+// warms/cools along blue-amber and shifts green-magenta, keeping luminance so brightness doesn't move
+fn white_balance(colour: vec3<f32>) -> vec3<f32> {
+    let stops = vec3<f32>(params.temperature, -params.tint, -params.temperature) * white_balance_stops;
+    let gains = exp2(stops);
 
-    return saturate(contrast_bright);
+    return colour * gains / luminance(gains);
 }
 
-fn contrast_brigtness(colour: vec4<f32>) -> vec4<f32> {
-    //todo: should use midpoint as pow(0.5, 2.2)
-    return ((colour - 0.5 ) * params.contrast) + 0.5 + params.brightness;
+// contrast pivots on mid grey in stops, brightness bends the midtones leaving black and white fixed
+fn contrast_brigtness(colour: vec3<f32>) -> vec3<f32> {
+    let contrasted = mid_grey * pow(max(colour, vec3<f32>(0.0)) / mid_grey, vec3<f32>(params.contrast));
+    return pow(contrasted, vec3<f32>(exp2(-params.brightness * 2.0)));
+}
+
+// rotation around the grey axis, greys stay grey
+fn rotate_hue(colour: vec3<f32>, degrees: f32) -> vec3<f32> {
+    let angle = radians(degrees);
+    let cos_a = cos(angle);
+    let k = (1.0 - cos_a) / 3.0;
+    let s = sin(angle) * sqrt(1.0 / 3.0);
+
+    let rotation = mat3x3<f32>(
+        cos_a + k, k + s, k - s,
+        k - s, cos_a + k, k + s,
+        k + s, k - s, cos_a + k,
+    );
+
+    return rotation * colour;
 }
 
-fn saturate(colour: vec4<f32>) -> vec4<f32> {
-    let luma = dot(colour, vec4<f32>(0.216279, 0.7515122, 0.0721750, 0.0));
+fn saturate(colour: vec3<f32>) -> vec3<f32> {
+    let luma = luminance(colour);
     return luma + params.saturation * (colour - luma);
-}
\ No newline at end of file
+}
+
+// shifts hue, saturation and luminance per band, blending smoothly between neighbouring bands
+fn hsl_mixer(colour: vec3<f32>) -> vec3<f32> {
+    let max_channel = max(colour.r, max(colour.g, colour.b));
+    let chroma = max_channel - min(colour.r, min(colour.g, colour.b));
+    if chroma <= 1e-5 {
+        return colour;
+    }
+
+    let shifts = band_shifts(hexcone_hue(colour, max_channel, chroma));
+    // near greys have no reliable hue, fade the shifts out towards them
+    let amount = smoothstep(0.0, 0.2, chroma / max_channel);
+
+    let rotated = rotate_hue(colour, shifts.x * hsl_hue_degrees * amount);
+    let luma = luminance(rotated);
+    let saturated = luma + (rotated - luma) * (1.0 + shifts.y * amount);
+
+    return saturated * exp2(shifts.z * hsl_luminance_stops * amount);
+}
+
+// degrees from 0 to 360, red at 0
+fn hexcone_hue(colour: vec3<f32>, max_channel: f32, chroma: f32) -> f32 {
+    var sector: f32;
+    if max_channel == colour.r {
+        sector = (colour.g - colour.b) / chroma;
+    } else if max_channel == colour.g {
+        sector = (colour.b - colour.r) / chroma + 2.0;
+    } else {
+        sector = (colour.r - colour.g) / chroma + 4.0;
+    }
+
+    return fract(sector / 6.0) * 360.0;
+}
+
+// the hue, saturation and luminance shifts at `hue`, mixed from the two bands around it
+fn band_shifts(hue: f32) -> vec3<f32> {
+    var centres = band_centres;
+    var band = 0u;
+    for (var i = 1u; i < 8u; i++) {
+        if hue >= centres[i] {
+            band = i;
+        }
+    }
+
+    let t = (hue - centres[band]) / (centres[band + 1u] - centres[band]);
+    return mix(band_values(band), band_values((band + 1u) % 8u), smoothstep(0.0, 1.0, t));
+}
+
+fn band_values(band: u32) -> vec3<f32> {
+    let vector = band / 4u;
+    let component = band % 4u;
+
+    return vec3<f32>(
+        params.hsl_hue[vector][component],
+        params.hsl_saturation[vector][component],
+        params.hsl_luminance[vector][component],
+    );
+}
+
+fn levels_is_identity() -> bool {
+    return params.levels_input_black == 0.0 && params.levels_input_white == 1.0
+        && params.levels_gamma == 1.0
+        && params.levels_output_black == 0.0 && params.levels_output_white == 1.0;
+}
+
+fn apply_levels(encoded: vec3<f32>) -> vec3<f32> {
+    let input_range = max(params.levels_input_white - params.levels_input_black, 1e-3);
+    let normalised = max((encoded - params.levels_input_black) / input_range, vec3<f32>(0.0));
+    let curved = pow(normalised, vec3<f32>(1.0 / params.levels_gamma));
+
+    return mix(vec3<f32>(params.levels_output_black), vec3<f32>(params.levels_output_white), curved);
+}
+
+// values above white keep their headroom past the end of the table
+fn apply_curves(encoded: vec3<f32>) -> vec3<f32> {
+    if params.curves == 0u {
+        return encoded;
+    }
+
+    let size = f32(textureDimensions(curve_table).x);
+    let clamped = clamp(encoded, vec3<f32>(0.0), vec3<f32>(1.0));
+    // map onto texel centres so 0 and 1 land on the first and last entries
+    let table_coords = clamped * ((size - 1.0) / size) + 0.5 / size;
+
+    let curved = vec3<f32>(
+        textureSampleLevel(curve_table, s_curve, vec2<f32>(table_coords.r, 0.5), 0.0).r,
+        textureSampleLevel(curve_table, s_curve, vec2<f32>(table_coords.g, 0.5), 0.0).g,
+        textureSampleLevel(curve_table, s_curve, vec2<f32>(table_coords.b, 0.5), 0.0).b,
+    );
+
+    return curved + max(encoded - 1.0, vec3<f32>(0.0));
+}
@@ -1,3 +1,4 @@
+use crate::renderer::colour::{SourceTransfer, ToneMapping};
 use crate::ui::preview::{BoundingBoxDimensions, Orientation};
 use encase::{ShaderType, UniformBuffer};
 use wgpu::util::DeviceExt;
@@ -5,21 +6,31 @@ use wgpu::util::DeviceExt;
 #[derive(ShaderType)]
 pub struct FramePositionUniform {
     translate: mint::Vector2<i32>,
+    crop_offset: mint::Vector2<f32>,
     scale: f32,
     rotation: f32,
     orientation: f32,
     mirrored: u32,
+    pixel_aspect_ratio: f32,
+    transfer: u32,
+    tone_mapping: u32,
 }
 
 // todo: send a matrix for rotation and translate
 #[derive(Copy, Clone, Debug)]
 pub struct FramePosition {
+    /// Left, top, right and bottom of the oriented frame, in display pixels before scaling.
     pub(crate) crop_edges: [u32; 4],
     pub(crate) translate: [i32; 2],
     pub(crate) scale: f32,
     pub(crate) orientation: Orientation,
     pub(crate) straigthen_angle: f32,
     pub(crate) original_frame_size: FrameSize,
+    /// Width of a source pixel relative to its height, 1 renders stored pixels as is.
+    pub(crate) pixel_aspect_ratio: f32,
+    /// Applied while sampling, before the frame is quantised to 8 bit.
+    pub(crate) transfer: SourceTransfer,
+    pub(crate) tone_mapping: ToneMapping,
 }
 
 impl FramePosition {
@@ -31,21 +42,25 @@ impl FramePosition {
             orientation: Orientation::default(),
             straigthen_angle: 0.0,
             original_frame_size: frame_size,
+            pixel_aspect_ratio: 1.0,
+            transfer: SourceTransfer::default(),
+            tone_mapping: ToneMapping::default(),
         }
     }
 
     pub fn buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
-        let translate = mint::Vector2::from([
-            self.translate[0] - self.crop_edges[0] as i32,
-            self.translate[1] - self.crop_edges[1] as i32,
-        ]);
+        let crop = self.scaled_crop_edges();
 
         let uniform = FramePositionUniform {
             rotation: self.straigthen_angle,
             orientation: self.orientation.absolute_angle(),
             mirrored: if self.orientation.mirrored { 1 } else { 0 },
-            translate,
+            translate: mint::Vector2::from(self.translate),
+            crop_offset: mint::Vector2::from([crop[0], crop[1]]),
             scale: self.scale,
+            pixel_aspect_ratio: self.pixel_aspect_ratio,
+            transfer: self.transfer.shader_id(),
+            tone_mapping: self.tone_mapping.shader_id(),
         };
 
         let mut buffer = UniformBuffer::new(Vec::<u8>::new());
@@ -59,13 +74,26 @@ impl FramePosition {
         })
     }
 
+    // crop, scale and output sizes are all in display pixels
+    fn display_width(&self) -> f32 {
+        self.original_frame_size.width as f32 * self.pixel_aspect_ratio
+    }
+
     pub fn scale_for_output_size(&mut self, output_size: FrameSize) {
-        self.scale = self.original_frame_size.width as f32 / output_size.width as f32;
+        self.scale = self.display_width() / output_size.width as f32;
+    }
+
+    /// Scales down so the cropped output is at most `max_height` tall, never scales up.
+    pub fn limit_output_height(&mut self, max_height: u32) {
+        let (_, height) = self.cropped_size();
+        if height / self.scale > max_height as f32 {
+            self.scale = height / max_height as f32;
+        }
     }
 
+    /// The crop box is drawn on the oriented preview, set the orientation first.
     pub fn set_crop_edges_from_percent(&mut self, bounding_box: BoundingBoxDimensions) {
-        let width = self.original_frame_size.width as f32;
-        let height = self.original_frame_size.height as f32;
+        let (width, height) = self.oriented_size();
 
         self.crop_edges = [
             (width * bounding_box.left_x) as u32,
@@ -75,17 +103,39 @@ impl FramePosition {
         ];
     }
 
-    pub fn output_frame_size(&self) -> FrameSize {
-        let (mut width, mut height) = (
-            (self.original_frame_size.width as f32 / self.scale) as u32,
-            (self.original_frame_size.height as f32 / self.scale) as u32,
-        );
+    fn oriented_size(&self) -> (f32, f32) {
+        let (width, height) = (self.display_width(), self.original_frame_size.height as f32);
 
-        (width, height) = self.orientation.oriented_size(width, height);
-        width = width - (self.crop_edges[0] + self.crop_edges[2]);
-        height = height - (self.crop_edges[1] + self.crop_edges[3]);
+        if self.orientation.is_width_flipped() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Oriented size of the frame left after cropping, in display pixels before scaling.
+    fn cropped_size(&self) -> (f32, f32) {
+        let [left, top, right, bottom] = self.crop_edges.map(|edge| edge as f32);
+        let (width, height) = self.oriented_size();
 
-        FrameSize::new(width, height)
+        (
+            (width - left - right).max(1.0),
+            (height - top - bottom).max(1.0),
+        )
+    }
+
+    /// Crop edges in output pixels, `crop_edges` are in display pixels before scaling.
+    pub fn scaled_crop_edges(&self) -> [f32; 4] {
+        self.crop_edges.map(|edge| edge as f32 / self.scale)
+    }
+
+    pub fn output_frame_size(&self) -> FrameSize {
+        let (width, height) = self.cropped_size();
+
+        FrameSize::new(
+            ((width / self.scale).round() as u32).max(1),
+            ((height / self.scale).round() as u32).max(1),
+        )
     }
 }
 
@@ -118,3 +168,41 @@ impl Into<wgpu::Extent3d> for FrameSize {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anamorphic_output_is_square_pixels() {
+        // dv widescreen, 720x480 stored with 32:27 pixels
+        let mut position = FramePosition::new(FrameSize::new(720, 480));
+        position.pixel_aspect_ratio = 32.0 / 27.0;
+
+        let size = position.output_frame_size();
+        assert_eq!((size.width, size.height), (853, 480));
+
+        position.limit_output_height(240);
+        let size = position.output_frame_size();
+        assert_eq!((size.width, size.height), (427, 240));
+    }
+
+    #[test]
+    fn max_height_applies_to_the_cropped_frame() {
+        let mut position = FramePosition::new(FrameSize::new(3840, 2160));
+        position.crop_edges = [0, 600, 0, 600];
+
+        position.limit_output_height(720);
+        let size = position.output_frame_size();
+        assert_eq!((size.width, size.height), (2880, 720));
+        let crop = position.scaled_crop_edges();
+        assert!((crop[1] - 450.0).abs() < 0.01 && (crop[3] - 450.0).abs() < 0.01);
+
+        // cropped below the limit, nothing is scaled and nothing underflows
+        position.scale = 1.0;
+        position.crop_edges = [0, 1000, 0, 1000];
+        position.limit_output_height(480);
+        let size = position.output_frame_size();
+        assert_eq!((size.width, size.height), (3840, 160));
+    }
+}
@@ -2,7 +2,9 @@ use crate::renderer::frame_position::FramePosition;
 use crate::renderer::handler::RenderResopnse::FrameRendered;
 use crate::renderer::renderer::{RenderedFrame, Renderer};
 use crate::renderer::timer::Timer;
-use crate::renderer::{EffectParameters, TimerEvent};
+use crate::renderer::{
+    Curves, EffectParameters, Lut, RedactRegion, SpatialParameters, TimerEvent, ToneMapping,
+};
 use crate::ui::preview::Orientation;
 use std::cmp::PartialEq;
 use std::collections::VecDeque;
@@ -15,11 +17,22 @@ use tokio::sync::Mutex;
 pub enum RenderCmd {
     ChangeRenderMode(RenderMode),
     PositionFrame(FramePosition),
+    QueueOverlay(gst::Sample),
+    ClearOverlays,
     RenderFrame,
     RenderSample(gst::Sample),
     UpdateEffects(EffectParameters),
+    UpdateCurves(Curves),
+    UpdateSpatial(SpatialParameters),
+    UpdateRedactions(Vec<RedactRegion>),
+    /// The table and its intensity, tables are only uploaded when the `Arc` changes.
+    UpdateLut(Option<Arc<Lut>>, f32),
     UpdateOutputResolution(u32, u32),
     UpdateOrientation(Orientation),
+    UpdatePixelAspectRatio(f32),
+    UpdateToneMapping(ToneMapping),
+    /// Stops the render thread, the loop holds its own sender so it never sees a hang up.
+    Quit,
 }
 
 #[derive(Debug)]
@@ -74,9 +87,14 @@ async fn update_queued(
     effect_parms: &mut Option<EffectParameters>,
     orientation: &mut Option<Orientation>,
     output_res: &mut Option<(u32, u32)>,
+    overlays: &mut Vec<gst::Sample>,
 ) {
     let mut renderer = renderer.lock().await;
 
+    for overlay in overlays.drain(..) {
+        renderer.queue_overlay(overlay);
+    }
+
     if let Some((width, height)) = output_res {
         renderer.update_output_resolution(*width, *height);
     }
@@ -107,6 +125,7 @@ async fn render_loop(
     let mut queued_effect_params: Option<EffectParameters> = None;
     let mut queued_output_resolution: Option<(u32, u32)> = None;
     let mut queued_orientation: Option<Orientation> = None;
+    let mut queued_overlays: Vec<gst::Sample> = Vec::new();
     let mut render_mode = inital_render_mode;
 
     let mut samples: VecDeque<gst::Sample> = VecDeque::with_capacity(1);
@@ -149,6 +168,10 @@ async fn render_loop(
                 samples.push_back(sample);
 
                 if let Ok(mut guarded_renderer) = renderer.try_lock() {
+                    // overlays sent ahead of the frame have to be there when it renders
+                    for overlay in queued_overlays.drain(..) {
+                        guarded_renderer.queue_overlay(overlay);
+                    }
                     guarded_renderer.upload_new_sample(&samples.pop_front().unwrap());
                     drop(guarded_renderer);
 
@@ -177,6 +200,7 @@ async fn render_loop(
                             &mut queued_effect_params,
                             &mut queued_orientation,
                             &mut queued_output_resolution,
+                            &mut queued_overlays,
                         )
                         .await;
 
@@ -219,9 +243,48 @@ async fn render_loop(
                     render_queued.store(true, std::sync::atomic::Ordering::Relaxed);
                 }
             }
+            // only sent when a video loads, before its output resolution
+            RenderCmd::UpdatePixelAspectRatio(pixel_aspect_ratio) => {
+                renderer
+                    .lock()
+                    .await
+                    .set_pixel_aspect_ratio(pixel_aspect_ratio);
+            }
+            RenderCmd::UpdateToneMapping(tone_mapping) => {
+                renderer.lock().await.set_tone_mapping(tone_mapping);
+            }
+            RenderCmd::UpdateCurves(curves) => {
+                renderer.lock().await.update_curves(curves);
+            }
+            RenderCmd::UpdateSpatial(parameters) => {
+                renderer.lock().await.update_spatial(parameters);
+            }
+            RenderCmd::UpdateRedactions(regions) => {
+                renderer.lock().await.update_redactions(regions);
+            }
+            RenderCmd::UpdateLut(lut, intensity) => {
+                renderer.lock().await.update_lut(lut, intensity);
+            }
+            RenderCmd::QueueOverlay(sample) => {
+                queued_overlays.push(sample);
+
+                if let Ok(mut renderer) = renderer.try_lock() {
+                    for overlay in queued_overlays.drain(..) {
+                        renderer.queue_overlay(overlay);
+                    }
+                }
+            }
+            RenderCmd::ClearOverlays => {
+                queued_overlays.clear();
+
+                if let Ok(mut renderer) = renderer.try_lock() {
+                    renderer.clear_overlays();
+                }
+            }
             RenderCmd::ChangeRenderMode(mode) => {
                 render_mode = mode;
             }
+            RenderCmd::Quit => break,
         }
     }
 }
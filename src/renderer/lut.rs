@@ -0,0 +1,442 @@
+use crate::renderer::pass::{
+    sampled_texture_entry, sampler_entry, uniform_entry, with_srgb, EffectPass,
+};
+use anyhow::{anyhow, bail, Context, Error};
+use encase::{ShaderType, UniformBuffer};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use wgpu::util::DeviceExt;
+
+const MAX_3D_SIZE: u32 = 128;
+const MAX_1D_SIZE: u32 = 8192;
+const LUT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgb10a2Unorm;
+/// Output slack for rounding in authored tables, anything further outside 0 to 1 is rejected.
+const OUTPUT_TOLERANCE: f32 = 1e-3;
+
+/// The LUT recorded with the export's effect settings, loaded again when exporting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LutSettings {
+    pub path: PathBuf,
+    /// Mix between the untouched (0) and fully looked up (1) frame.
+    pub intensity: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LutDimension {
+    /// A curve per channel.
+    One,
+    Three,
+}
+
+/// An Adobe/Resolve `.cube` lookup table, authored for display encoded input.
+///
+/// Outputs must be display encoded too, extended range tables with outputs outside 0 to 1
+/// fail to load rather than clipping.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut {
+    pub title: Option<String>,
+    pub dimension: LutDimension,
+    pub size: u32,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+    /// Red changes fastest, then green, then blue.
+    table: Vec<[f32; 3]>,
+}
+
+impl Lut {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        contents
+            .parse()
+            .with_context(|| format!("invalid lut {}", path.display()))
+    }
+
+    fn expected_entries(&self) -> usize {
+        match self.dimension {
+            LutDimension::One => self.size as usize,
+            LutDimension::Three => (self.size as usize).pow(3),
+        }
+    }
+}
+
+fn parse_triplet<'a>(words: impl Iterator<Item = &'a str>) -> Result<[f32; 3], Error> {
+    let values = words
+        .map(f32::from_str)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| anyhow!("expected numbers: {err}"))?;
+
+    values
+        .try_into()
+        .map_err(|values: Vec<f32>| anyhow!("expected 3 values, found {}", values.len()))
+}
+
+fn parse_size(words: &mut std::str::SplitWhitespace, max: u32) -> Result<u32, Error> {
+    let size: u32 = words
+        .next()
+        .ok_or_else(|| anyhow!("missing size"))?
+        .parse()?;
+
+    if !(2..=max).contains(&size) {
+        bail!("size {size} is outside 2 to {max}");
+    }
+    Ok(size)
+}
+
+#[derive(Default)]
+struct CubeParser {
+    title: Option<String>,
+    dimension: Option<(LutDimension, u32)>,
+    domain: Option<([f32; 3], [f32; 3])>,
+    table: Vec<[f32; 3]>,
+}
+
+impl CubeParser {
+    fn parse_line(&mut self, line: &str) -> Result<(), Error> {
+        let mut words = line.split_whitespace();
+        let keyword = words.next().unwrap_or_default();
+
+        match keyword {
+            "TITLE" => {
+                self.title = Some(line["TITLE".len()..].trim().trim_matches('"').to_string());
+            }
+            "LUT_3D_SIZE" => {
+                self.dimension = Some((LutDimension::Three, parse_size(&mut words, MAX_3D_SIZE)?));
+            }
+            "LUT_1D_SIZE" => {
+                self.dimension = Some((LutDimension::One, parse_size(&mut words, MAX_1D_SIZE)?));
+            }
+            "DOMAIN_MIN" => {
+                let (_, max) = self.domain.unwrap_or(([0.0; 3], [1.0; 3]));
+                self.domain = Some((parse_triplet(words)?, max));
+            }
+            "DOMAIN_MAX" => {
+                let (min, _) = self.domain.unwrap_or(([0.0; 3], [1.0; 3]));
+                self.domain = Some((min, parse_triplet(words)?));
+            }
+            // resolve's spelling of the domain, the same range for every channel
+            "LUT_1D_INPUT_RANGE" | "LUT_3D_INPUT_RANGE" => {
+                let range = words
+                    .map(f32::from_str)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|err| anyhow!("expected numbers: {err}"))?;
+                let [min, max] = range[..] else {
+                    bail!("expected a min and max");
+                };
+                self.domain = Some(([min; 3], [max; 3]));
+            }
+            _ if keyword.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.') => {
+                self.table.push(parse_triplet(line.split_whitespace())?);
+            }
+            // other keywords are vendor extensions, the spec says to skip them
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Lut {
+    type Err = Error;
+
+    fn from_str(contents: &str) -> Result<Self, Error> {
+        let mut parser = CubeParser::default();
+
+        for (idx, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            parser
+                .parse_line(line)
+                .with_context(|| format!("line {}", idx + 1))?;
+        }
+
+        let (dimension, size) = parser
+            .dimension
+            .ok_or_else(|| anyhow!("missing LUT_3D_SIZE or LUT_1D_SIZE"))?;
+        let (domain_min, domain_max) = parser.domain.unwrap_or(([0.0; 3], [1.0; 3]));
+        if (0..3).any(|c| domain_max[c] <= domain_min[c]) {
+            bail!("domain max must be greater than domain min");
+        }
+
+        let lut = Lut {
+            title: parser.title,
+            dimension,
+            size,
+            domain_min,
+            domain_max,
+            table: parser.table,
+        };
+
+        if lut.table.len() != lut.expected_entries() {
+            bail!(
+                "expected {} entries, found {}",
+                lut.expected_entries(),
+                lut.table.len()
+            );
+        }
+
+        let in_range = |value: &f32| (-OUTPUT_TOLERANCE..=1.0 + OUTPUT_TOLERANCE).contains(value);
+        if let Some(idx) = lut
+            .table
+            .iter()
+            .position(|entry| !entry.iter().all(in_range))
+        {
+            bail!(
+                "entry {} is outside 0 to 1, extended range luts aren't supported",
+                idx + 1
+            );
+        }
+
+        Ok(lut)
+    }
+}
+
+#[derive(ShaderType)]
+struct LutUniform {
+    domain_min: mint::Vector3<f32>,
+    intensity: f32,
+    domain_max: mint::Vector3<f32>,
+    dimension: u32,
+    size: f32,
+}
+
+/// Looks up the frame in a `Lut`, see `lut.wgsl`.
+pub(crate) struct LutPass {
+    lut: Option<Arc<Lut>>,
+    intensity: f32,
+    cube: wgpu::TextureView,
+    curves: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    buffer: wgpu::Buffer,
+}
+
+impl LutPass {
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("lut sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("lut buffer"),
+            size: LutUniform::min_size().get(),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self {
+            lut: None,
+            intensity: 1.0,
+            cube: create_table(device, queue, wgpu::TextureDimension::D3, 1, &[[0.0; 3]]),
+            curves: create_table(device, queue, wgpu::TextureDimension::D2, 1, &[[0.0; 3]]),
+            sampler,
+            buffer,
+        }
+    }
+
+    /// Tables are only uploaded when `lut` is a different table, intensity changes are cheap.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        lut: Option<Arc<Lut>>,
+        intensity: f32,
+    ) {
+        let changed = match (&self.lut, &lut) {
+            (Some(current), Some(new)) => !Arc::ptr_eq(current, new),
+            (None, None) => false,
+            _ => true,
+        };
+
+        if changed {
+            if let Some(lut) = &lut {
+                let table = create_table(device, queue, lut.dimension.into(), lut.size, &lut.table);
+                match lut.dimension {
+                    LutDimension::One => self.curves = table,
+                    LutDimension::Three => self.cube = table,
+                }
+            }
+            self.lut = lut;
+        }
+        self.intensity = intensity;
+
+        let Some(lut) = &self.lut else {
+            return;
+        };
+
+        let uniform = LutUniform {
+            domain_min: lut.domain_min.into(),
+            intensity: self.intensity,
+            domain_max: lut.domain_max.into(),
+            dimension: match lut.dimension {
+                LutDimension::One => 1,
+                LutDimension::Three => 3,
+            },
+            size: lut.size as f32,
+        };
+
+        let mut buffer = UniformBuffer::new(Vec::<u8>::new());
+        buffer.write(&uniform).unwrap();
+        queue.write_buffer(&self.buffer, 0, &buffer.into_inner());
+    }
+}
+
+impl From<LutDimension> for wgpu::TextureDimension {
+    fn from(dimension: LutDimension) -> Self {
+        match dimension {
+            // curves are stored in a row, 1d textures can't be filtered everywhere
+            LutDimension::One => wgpu::TextureDimension::D2,
+            LutDimension::Three => wgpu::TextureDimension::D3,
+        }
+    }
+}
+
+/// 10 bit per channel is plenty between the interpolated entries and keeps 4 byte texels.
+/// Parsing rejects outputs outside 0 to 1, the clamp only catches rounding slack.
+fn pack_entry([r, g, b]: [f32; 3]) -> u32 {
+    let quantise = |value: f32| (value.clamp(0.0, 1.0) * 1023.0).round() as u32;
+    quantise(r) | (quantise(g) << 10) | (quantise(b) << 20) | (3 << 30)
+}
+
+pub(crate) fn create_table(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    dimension: wgpu::TextureDimension,
+    size: u32,
+    table: &[[f32; 3]],
+) -> wgpu::TextureView {
+    let extent = match dimension {
+        wgpu::TextureDimension::D3 => wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: size,
+        },
+        _ => wgpu::Extent3d {
+            width: size,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+    };
+
+    let packed: Vec<u32> = table.iter().copied().map(pack_entry).collect();
+    let texture = device.create_texture_with_data(
+        queue,
+        &wgpu::TextureDescriptor {
+            label: Some("lookup table"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension,
+            format: LUT_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        },
+        wgpu::util::TextureDataOrder::LayerMajor,
+        bytemuck::cast_slice(&packed),
+    );
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+impl EffectPass for LutPass {
+    fn label(&self) -> &'static str {
+        "lut pass"
+    }
+
+    fn shader(&self) -> wgpu::ShaderModuleDescriptor<'static> {
+        with_srgb("lut.wgsl", include_str!("lut.wgsl"))
+    }
+
+    fn layout_entries(&self) -> Vec<wgpu::BindGroupLayoutEntry> {
+        vec![
+            uniform_entry(0),
+            sampled_texture_entry(1, wgpu::TextureViewDimension::D3),
+            sampled_texture_entry(2, wgpu::TextureViewDimension::D2),
+            sampler_entry(3),
+        ]
+    }
+
+    fn bind_group_entries(&self) -> Vec<wgpu::BindGroupEntry<'_>> {
+        vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: self.buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&self.cube),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&self.curves),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(&self.sampler),
+            },
+        ]
+    }
+
+    fn is_identity(&self) -> bool {
+        self.lut.is_none() || self.intensity <= 0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_3d_cube() {
+        let lut: Lut = "# comment\n\
+            TITLE \"Identity\"\n\
+            LUT_3D_SIZE 2\n\
+            DOMAIN_MIN 0 0 0\n\
+            DOMAIN_MAX 1 1 1\n\
+            0 0 0\n1 0 0\n0 1 0\n1 1 0\n\
+            0 0 1\n1 0 1\n0 1 1\n1 1 1\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(lut.title.as_deref(), Some("Identity"));
+        assert_eq!(lut.dimension, LutDimension::Three);
+        assert_eq!(lut.size, 2);
+        assert_eq!(lut.table[1], [1.0, 0.0, 0.0]);
+        assert_eq!(lut.table[7], [1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn parse_1d_with_resolve_input_range() {
+        let lut: Lut = "LUT_1D_SIZE 3\nLUT_1D_INPUT_RANGE 0.0 2.0\n0 0 0\n0.5 0.5 0.5\n1 1 1\n"
+            .parse()
+            .unwrap();
+
+        assert_eq!(lut.dimension, LutDimension::One);
+        assert_eq!(lut.domain_max, [2.0; 3]);
+    }
+
+    #[test]
+    fn reject_malformed_cubes() {
+        assert!("0 0 0\n".parse::<Lut>().is_err());
+        assert!("LUT_3D_SIZE 2\n0 0 0\n".parse::<Lut>().is_err());
+        assert!("LUT_1D_SIZE 2\n0 0\n1 1 1\n".parse::<Lut>().is_err());
+        assert!("LUT_3D_SIZE 1\n0 0 0\n".parse::<Lut>().is_err());
+    }
+
+    #[test]
+    fn reject_extended_range_outputs() {
+        assert!("LUT_1D_SIZE 2\n0 0 0\n1.0001 1 1\n".parse::<Lut>().is_ok());
+        assert!("LUT_1D_SIZE 2\n0 0 0\n1.5 1 1\n".parse::<Lut>().is_err());
+        assert!("LUT_1D_SIZE 2\n-0.2 0 0\n1 1 1\n".parse::<Lut>().is_err());
+    }
+}
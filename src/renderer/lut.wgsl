@@ -0,0 +1,46 @@
+struct LutUniform {
+    domain_min: vec3f,
+    intensity: f32,
+    domain_max: vec3f,
+    dimension: u32,
+    size: f32,
+}
+
+@group(0) @binding(0) var input: texture_storage_2d<rgba16float, read>;
+@group(0) @binding(1) var output: texture_storage_2d<rgba16float, write>;
+@group(1) @binding(0) var<uniform> lut: LutUniform;
+@group(1) @binding(1) var cube: texture_3d<f32>;
+@group(1) @binding(2) var curves: texture_2d<f32>;
+@group(1) @binding(3) var s_lut: sampler;
+
+const dimension_1d = 1u;
+
+@compute
+@workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let coords = global_invocation_id.xy;
+
+    if all(coords < textureDimensions(output)) {
+        let colour = textureLoad(input, coords);
+
+        // luts are authored for display encoded input
+        let encoded = linear_to_srgb(clamp(colour.rgb, vec3f(0.0), vec3f(1.0)));
+        let normalised = clamp((encoded - lut.domain_min) / (lut.domain_max - lut.domain_min), vec3f(0.0), vec3f(1.0));
+        // map onto texel centres so 0 and 1 land on the first and last entries
+        let table_coords = normalised * ((lut.size - 1.0) / lut.size) + 0.5 / lut.size;
+
+        var looked_up: vec3f;
+        if lut.dimension == dimension_1d {
+            looked_up = vec3f(
+                textureSampleLevel(curves, s_lut, vec2f(table_coords.r, 0.5), 0.0).r,
+                textureSampleLevel(curves, s_lut, vec2f(table_coords.g, 0.5), 0.0).g,
+                textureSampleLevel(curves, s_lut, vec2f(table_coords.b, 0.5), 0.0).b,
+            );
+        } else {
+            looked_up = textureSampleLevel(cube, s_lut, table_coords, 0.0).rgb;
+        }
+
+        let graded = mix(colour.rgb, srgb_to_linear(looked_up), lut.intensity);
+        textureStore(output, coords, vec4f(graded, colour.a));
+    }
+}
@@ -0,0 +1,15 @@
+@group(0) @binding(0) var input: texture_storage_2d<rgba16float, read>;
+@group(0) @binding(1) var output: texture_storage_2d<rgba8unorm, write>;
+
+@compute
+@workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let coords = global_invocation_id.xy;
+
+    if all(coords < textureDimensions(output)) {
+        let colour = textureLoad(input, coords);
+        let encoded = linear_to_srgb(clamp(colour.rgb, vec3f(0.0), vec3f(1.0)));
+
+        textureStore(output, coords, vec4f(encoded, colour.a));
+    }
+}
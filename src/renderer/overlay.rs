@@ -0,0 +1,148 @@
+use crate::renderer::frame_position::FrameSize;
+use crate::renderer::pass::{sampled_texture_entry, sampler_entry, with_srgb, EffectPass};
+use crate::renderer::texture::Texture;
+use gst::ClockTime;
+use std::collections::VecDeque;
+
+/// Timed images (rendered subtitles) blended over the frame they overlap.
+pub struct Overlay {
+    samples: VecDeque<gst::Sample>,
+    active: Option<(ClockTime, ClockTime)>,
+    visible: bool,
+    texture: Texture,
+}
+
+impl Overlay {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            samples: VecDeque::new(),
+            active: None,
+            visible: false,
+            texture: Self::create_texture(device, FrameSize::new(1, 1)),
+        }
+    }
+
+    fn create_texture(device: &wgpu::Device, size: FrameSize) -> Texture {
+        Texture::new(
+            device,
+            size.into(),
+            wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING,
+            Some("overlay texture"),
+        )
+        .unwrap()
+    }
+
+    /// Kept ordered by start time, cues can arrive out of order around seeks.
+    pub fn queue_sample(&mut self, sample: gst::Sample) {
+        let start = sample_times(&sample).map(|(start, _)| start);
+        let idx = self
+            .samples
+            .partition_point(|queued| sample_times(queued).map(|(start, _)| start) <= start);
+        self.samples.insert(idx, sample);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+        self.active = None;
+        self.visible = false;
+    }
+
+    /// Uploads the overlay covering `pts`, hidden for frames without a timestamp (images).
+    pub fn select(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, pts: Option<ClockTime>) {
+        self.visible = pts.is_some_and(|pts| self.select_sample(device, queue, pts));
+    }
+
+    /// Returns false when nothing should be drawn.
+    fn select_sample(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        pts: ClockTime,
+    ) -> bool {
+        // samples are ordered, anything ending before this frame will not be shown again
+        while self
+            .samples
+            .front()
+            .and_then(sample_times)
+            .is_some_and(|(_, end)| end <= pts)
+        {
+            self.samples.pop_front();
+        }
+
+        let Some(sample) = self.samples.front() else {
+            self.active = None;
+            return false;
+        };
+
+        let Some((start, end)) = sample_times(sample) else {
+            return false;
+        };
+
+        if pts < start {
+            self.active = None;
+            return false;
+        }
+
+        if self.active == Some((start, end)) {
+            return true;
+        }
+
+        let Some(info) = sample
+            .caps()
+            .and_then(|caps| gst_video::VideoInfo::from_caps(caps).ok())
+        else {
+            return false;
+        };
+
+        let texture = &self.texture.texture;
+        if texture.width() != info.width() || texture.height() != info.height() {
+            self.texture =
+                Self::create_texture(device, FrameSize::new(info.width(), info.height()));
+        }
+
+        self.texture.write_from_sample(queue, sample);
+        self.active = Some((start, end));
+        true
+    }
+}
+
+impl EffectPass for Overlay {
+    fn label(&self) -> &'static str {
+        "overlay pass"
+    }
+
+    fn shader(&self) -> wgpu::ShaderModuleDescriptor<'static> {
+        with_srgb("overlay.wgsl", include_str!("overlay.wgsl"))
+    }
+
+    fn layout_entries(&self) -> Vec<wgpu::BindGroupLayoutEntry> {
+        vec![
+            sampled_texture_entry(0, wgpu::TextureViewDimension::D2),
+            sampler_entry(1),
+        ]
+    }
+
+    fn bind_group_entries(&self) -> Vec<wgpu::BindGroupEntry<'_>> {
+        vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&self.texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(self.texture.sampler.as_ref().unwrap()),
+            },
+        ]
+    }
+
+    fn is_identity(&self) -> bool {
+        !self.visible
+    }
+}
+
+fn sample_times(sample: &gst::Sample) -> Option<(ClockTime, ClockTime)> {
+    let buffer = sample.buffer()?;
+    let start = buffer.pts()?;
+    let end = start + buffer.duration().unwrap_or(ClockTime::ZERO);
+    Some((start, end))
+}
@@ -0,0 +1,22 @@
+@group(0) @binding(0) var input: texture_storage_2d<rgba16float, read>;
+@group(0) @binding(1) var output: texture_storage_2d<rgba16float, write>;
+@group(1) @binding(0) var overlay: texture_2d<f32>;
+@group(1) @binding(1) var s_overlay: sampler;
+
+@compute
+@workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let coords = global_invocation_id.xy;
+    let dimensions = textureDimensions(output);
+
+    if all(coords < dimensions) {
+        let colour = textureLoad(input, coords);
+
+        // overlay is stretched over the whole frame, it is rendered at the output size
+        let uv = (vec2f(coords) + 0.5) / vec2f(dimensions);
+        let overlay_colour = textureSampleLevel(overlay, s_overlay, uv, 0.0);
+        let overlay_linear = srgb_to_linear(overlay_colour.rgb);
+
+        textureStore(output, coords, vec4(mix(colour.rgb, overlay_linear, overlay_colour.a), colour.a));
+    }
+}
@@ -0,0 +1,379 @@
+use crate::renderer::frame_position::FrameSize;
+use crate::renderer::texture::Texture;
+use crate::renderer::timer::{GpuTimer, QuerySet};
+use std::borrow::Cow;
+
+/// Format of the positioned frame and every texture in the pass chain, holds linear light.
+pub(crate) const INTERMEDIATE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+/// The finished frame, srgb encoded for gtk and the encoders.
+pub(crate) const OUTPUT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Prepends the shared transfer functions in `srgb.wgsl`, so every shader encodes the same way.
+pub(crate) fn with_srgb(
+    label: &'static str,
+    source: &'static str,
+) -> wgpu::ShaderModuleDescriptor<'static> {
+    wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(Cow::Owned(format!(
+            "{}\n{source}",
+            include_str!("srgb.wgsl")
+        ))),
+    }
+}
+
+/// A compute pass run on the positioned frame.
+///
+/// Shaders read `input` at `@group(0) @binding(0)`, write `output` at `@group(0) @binding(1)`
+/// and find the pass's own resources in group 1.
+pub(crate) trait EffectPass {
+    fn label(&self) -> &'static str;
+
+    fn shader(&self) -> wgpu::ShaderModuleDescriptor<'static>;
+
+    /// Layout of the pass's uniforms and textures.
+    fn layout_entries(&self) -> Vec<wgpu::BindGroupLayoutEntry>;
+
+    /// Matches `layout_entries`, rebuilt every frame so passes can swap their textures.
+    fn bind_group_entries(&self) -> Vec<wgpu::BindGroupEntry<'_>>;
+
+    /// Identity passes are skipped, leaving the frame untouched.
+    fn is_identity(&self) -> bool;
+
+    fn workgroup_size(&self) -> (u32, u32) {
+        (16, 16)
+    }
+
+    fn query_set(&self) -> Option<QuerySet> {
+        None
+    }
+}
+
+pub(crate) fn uniform_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+pub(crate) fn sampled_texture_entry(
+    binding: u32,
+    view_dimension: wgpu::TextureViewDimension,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension,
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+        },
+        count: None,
+    }
+}
+
+pub(crate) fn sampler_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+        count: None,
+    }
+}
+
+pub(crate) fn storage_texture_entry(
+    binding: u32,
+    access: wgpu::StorageTextureAccess,
+    format: wgpu::TextureFormat,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::COMPUTE,
+        ty: wgpu::BindingType::StorageTexture {
+            access,
+            format,
+            view_dimension: wgpu::TextureViewDimension::D2,
+        },
+        count: None,
+    }
+}
+
+struct CompiledPass {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+}
+
+/// Runs effect passes in order, ping ponging between two textures sized to the output,
+/// then encodes the result into `OUTPUT_FORMAT`.
+pub(crate) struct PassChain {
+    compiled: Vec<CompiledPass>,
+    io_bind_group_layout: wgpu::BindGroupLayout,
+    output_pipeline: wgpu::ComputePipeline,
+    output_bind_group_layout: wgpu::BindGroupLayout,
+    targets: ChainTargets,
+}
+
+struct ChainTargets {
+    /// Written by the position pass, the first pass reads it.
+    source: Texture,
+    // only read through the bind groups
+    _ping_pong: [Texture; 2],
+    output: Texture,
+    // source -> ping_pong[0], ping_pong[0] -> ping_pong[1], ping_pong[1] -> ping_pong[0]
+    io_bind_groups: [wgpu::BindGroup; 3],
+    // source, ping_pong[0], ping_pong[1] -> output
+    output_bind_groups: [wgpu::BindGroup; 3],
+}
+
+impl PassChain {
+    /// `passes` must be given in the same order to `encode`.
+    pub fn new(device: &wgpu::Device, passes: &[&dyn EffectPass], size: FrameSize) -> Self {
+        let io_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("pass io bind group layout"),
+                entries: &[
+                    storage_texture_entry(
+                        0,
+                        wgpu::StorageTextureAccess::ReadOnly,
+                        INTERMEDIATE_FORMAT,
+                    ),
+                    storage_texture_entry(
+                        1,
+                        wgpu::StorageTextureAccess::WriteOnly,
+                        INTERMEDIATE_FORMAT,
+                    ),
+                ],
+            });
+
+        let output_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("output pass bind group layout"),
+                entries: &[
+                    storage_texture_entry(
+                        0,
+                        wgpu::StorageTextureAccess::ReadOnly,
+                        INTERMEDIATE_FORMAT,
+                    ),
+                    storage_texture_entry(1, wgpu::StorageTextureAccess::WriteOnly, OUTPUT_FORMAT),
+                ],
+            });
+
+        let output_shader =
+            device.create_shader_module(with_srgb("output.wgsl", include_str!("output.wgsl")));
+        let output_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("output pass"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("output pass"),
+                    bind_group_layouts: &[&output_bind_group_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            module: &output_shader,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let compiled = passes
+            .iter()
+            .map(|pass| {
+                let bind_group_layout =
+                    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                        label: Some(pass.label()),
+                        entries: &pass.layout_entries(),
+                    });
+
+                let pipeline_layout =
+                    device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                        label: Some(pass.label()),
+                        bind_group_layouts: &[&io_bind_group_layout, &bind_group_layout],
+                        push_constant_ranges: &[],
+                    });
+
+                let shader = device.create_shader_module(pass.shader());
+                let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: Some(pass.label()),
+                    layout: Some(&pipeline_layout),
+                    module: &shader,
+                    entry_point: Some("main"),
+                    compilation_options: Default::default(),
+                    cache: None,
+                });
+
+                CompiledPass {
+                    pipeline,
+                    bind_group_layout,
+                }
+            })
+            .collect();
+
+        let targets = ChainTargets::new(
+            device,
+            &io_bind_group_layout,
+            &output_bind_group_layout,
+            size,
+        );
+
+        Self {
+            compiled,
+            io_bind_group_layout,
+            output_pipeline,
+            output_bind_group_layout,
+            targets,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, size: FrameSize) {
+        self.targets = ChainTargets::new(
+            device,
+            &self.io_bind_group_layout,
+            &self.output_bind_group_layout,
+            size,
+        );
+    }
+
+    pub fn source(&self) -> &Texture {
+        &self.targets.source
+    }
+
+    /// Encodes the non identity passes and the output pass, returns the finished frame.
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        passes: &[&dyn EffectPass],
+        timer: &GpuTimer,
+    ) -> &Texture {
+        let targets = &self.targets;
+        let size = targets.source.texture.size();
+        // index into ping_pong of the last written texture
+        let mut written: Option<usize> = None;
+
+        for (pass, compiled) in passes.iter().zip(&self.compiled) {
+            if pass.is_identity() {
+                continue;
+            }
+
+            let (io_bind_group, target) = match written {
+                None => (&targets.io_bind_groups[0], 0),
+                Some(0) => (&targets.io_bind_groups[1], 1),
+                Some(_) => (&targets.io_bind_groups[2], 0),
+            };
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(pass.label()),
+                layout: &compiled.bind_group_layout,
+                entries: &pass.bind_group_entries(),
+            });
+
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some(pass.label()),
+                timestamp_writes: pass
+                    .query_set()
+                    .and_then(|query_set| timer.query_timestamp_writes(query_set)),
+            });
+
+            let (workgroup_width, workgroup_height) = pass.workgroup_size();
+            compute_pass.set_pipeline(&compiled.pipeline);
+            compute_pass.set_bind_group(0, io_bind_group, &[]);
+            compute_pass.set_bind_group(1, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(
+                size.width.div_ceil(workgroup_width),
+                size.height.div_ceil(workgroup_height),
+                1,
+            );
+
+            written = Some(target);
+        }
+
+        let output_bind_group = match written {
+            None => &targets.output_bind_groups[0],
+            Some(idx) => &targets.output_bind_groups[idx + 1],
+        };
+
+        let mut output_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("output pass"),
+            timestamp_writes: None,
+        });
+        output_pass.set_pipeline(&self.output_pipeline);
+        output_pass.set_bind_group(0, output_bind_group, &[]);
+        output_pass.dispatch_workgroups(size.width.div_ceil(16), size.height.div_ceil(16), 1);
+
+        &targets.output
+    }
+}
+
+impl ChainTargets {
+    fn new(
+        device: &wgpu::Device,
+        io_bind_group_layout: &wgpu::BindGroupLayout,
+        output_bind_group_layout: &wgpu::BindGroupLayout,
+        size: FrameSize,
+    ) -> Self {
+        let source = Self::create_target(device, size, INTERMEDIATE_FORMAT, "positioned frame");
+        let ping_pong = [
+            Self::create_target(device, size, INTERMEDIATE_FORMAT, "effects ping"),
+            Self::create_target(device, size, INTERMEDIATE_FORMAT, "effects pong"),
+        ];
+        let output = Self::create_target(device, size, OUTPUT_FORMAT, "output frame");
+
+        let bind_group = |layout: &wgpu::BindGroupLayout, input: &Texture, output: &Texture| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("pass io bind group"),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&input.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&output.view),
+                    },
+                ],
+            })
+        };
+
+        let io_bind_groups = [
+            bind_group(io_bind_group_layout, &source, &ping_pong[0]),
+            bind_group(io_bind_group_layout, &ping_pong[0], &ping_pong[1]),
+            bind_group(io_bind_group_layout, &ping_pong[1], &ping_pong[0]),
+        ];
+        let output_bind_groups = [
+            bind_group(output_bind_group_layout, &source, &output),
+            bind_group(output_bind_group_layout, &ping_pong[0], &output),
+            bind_group(output_bind_group_layout, &ping_pong[1], &output),
+        ];
+
+        Self {
+            source,
+            _ping_pong: ping_pong,
+            output,
+            io_bind_groups,
+            output_bind_groups,
+        }
+    }
+
+    fn create_target(
+        device: &wgpu::Device,
+        size: FrameSize,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Texture {
+        Texture::with_format(
+            device,
+            size.into(),
+            format,
+            wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::STORAGE_BINDING,
+            Some(label),
+        )
+        .unwrap()
+    }
+}
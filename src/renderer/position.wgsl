@@ -1,15 +1,20 @@
 struct PositionUniform {
     translate: vec2i,
+    // left and top crop edges in output pixels of the oriented frame
+    crop_offset: vec2f,
     scale: f32,
     rotation: f32,
     orientation: f32,
     mirrored: u32,
+    pixel_aspect_ratio: f32,
+    transfer: u32,
+    tone_mapping: u32,
 }
 
 @group(0) @binding(0) var frame: texture_2d<f32>;
 @group(0) @binding(1) var s_texture: sampler;
 @group(0) @binding(2) var<uniform> position: PositionUniform;
-@group(0) @binding(3) var output: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(3) var output: texture_storage_2d<rgba16float, write>;
 
 const rotate_90: mat2x2f = mat2x2(0, -1, 1, 0);
 const rotate_180: mat2x2f = mat2x2(-1, 0, 0, -1);
@@ -23,6 +28,79 @@ fn rotate(p: vec2f, angle: f32) -> vec2f {
     return (r * p);
 }
 
+// transfer and tone_mapping ids, see renderer/colour.rs
+const transfer_pq = 1u;
+const transfer_hlg = 2u;
+const tone_map_reinhard = 1u;
+
+// sdr reference white, bt.2408
+const sdr_white_nits = 203.0;
+
+fn pq_to_nits(signal: vec3f) -> vec3f {
+    let m1 = 0.1593017578125;
+    let m2 = 78.84375;
+    let c1 = 0.8359375;
+    let c2 = 18.8515625;
+    let c3 = 18.6875;
+
+    let p = pow(max(signal, vec3f(0.0)), vec3f(1.0 / m2));
+    return 10000.0 * pow(max(p - c1, vec3f(0.0)) / (c2 - c3 * p), vec3f(1.0 / m1));
+}
+
+// inverse oetf and the bt.2100 ootf for a 1000 nit display
+fn hlg_to_nits(signal: vec3f) -> vec3f {
+    let a = 0.17883277;
+    let b = 0.28466892;
+    let c = 0.55991073;
+
+    let low = signal * signal / 3.0;
+    let high = (exp((signal - c) / a) + b) / 12.0;
+    let scene = select(high, low, signal <= vec3f(0.5));
+
+    let luma = dot(scene, vec3f(0.2627, 0.6780, 0.0593));
+    return 1000.0 * pow(max(luma, 1e-6), 0.2) * scene;
+}
+
+fn bt2020_to_bt709(rgb: vec3f) -> vec3f {
+    return vec3f(
+        dot(rgb, vec3f(1.6605, -0.5876, -0.0728)),
+        dot(rgb, vec3f(-0.1246, 1.1329, -0.0083)),
+        dot(rgb, vec3f(-0.0182, -0.1006, 1.1187)),
+    );
+}
+
+fn hable_partial(x: vec3f) -> vec3f {
+    let a = 0.15;
+    let b = 0.50;
+    let c = 0.10;
+    let d = 0.20;
+    let e = 0.02;
+    let f = 0.30;
+
+    return ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f;
+}
+
+fn tone_map(signal: vec3f) -> vec3f {
+    var nits: vec3f;
+    if position.transfer == transfer_pq {
+        nits = pq_to_nits(signal);
+    } else {
+        nits = hlg_to_nits(signal);
+    }
+
+    let linear = max(bt2020_to_bt709(nits / sdr_white_nits), vec3f(0.0));
+
+    var mapped: vec3f;
+    if position.tone_mapping == tone_map_reinhard {
+        mapped = linear / (1.0 + linear);
+    } else {
+        let white = 1000.0 / sdr_white_nits;
+        mapped = hable_partial(linear * 2.0) / hable_partial(vec3f(white));
+    }
+
+    return clamp(mapped, vec3f(0.0), vec3f(1.0));
+}
+
 const tile_width = 8u;
 const wg_x = 16u;
 const wg_y = 16u;
@@ -71,16 +149,24 @@ fn main(
         @builtin(num_workgroups) dispatch_size: vec3<u32>
     ) {
     let f_tex_dimensions = vec2f(textureDimensions(frame));
+    // anamorphic sources are stretched to square pixels, everything below works in those
+    let f_frame_dimensions = f_tex_dimensions * vec2f(position.pixel_aspect_ratio, 1.0);
     let f_output_dimensions = vec2f(textureDimensions(output));
     let output_coords = id_to_coord(local_id, workgroup_id, dispatch_size);
-    var tex_coords = vec2f(output_coords);
 
-    if !all(tex_coords < f_output_dimensions) {
+    if !all(vec2f(output_coords) < f_output_dimensions) {
         return;
     }
 
+    // the output is the cropped part of the scaled, oriented frame
+    var tex_coords = vec2f(output_coords) + position.crop_offset;
+
     if position.orientation != 0.0 {
-        let center = f_output_dimensions / 2.0;
+        var f_oriented_dimensions = f_frame_dimensions;
+        if position.orientation == 90.0 || position.orientation == 270.0 {
+            f_oriented_dimensions = f_frame_dimensions.yx;
+        }
+        let center = f_oriented_dimensions / position.scale / 2.0;
 
         switch (u32(position.orientation)) {
             case 90u: {
@@ -101,19 +187,28 @@ fn main(
     tex_coords = tex_coords * position.scale;
 
     if (position.mirrored == 1) {
-        tex_coords = vec2f(abs(tex_coords.x - f_tex_dimensions.x), tex_coords.y);
+        tex_coords = vec2f(abs(tex_coords.x - f_frame_dimensions.x), tex_coords.y);
     }
 
     if position.rotation != 0.0 {
-        let tex_center = f_tex_dimensions / 2.0;
+        let tex_center = f_frame_dimensions / 2.0;
         tex_coords = rotate(tex_coords - tex_center, position.rotation) + tex_center;
     }
     
     tex_coords = tex_coords - vec2f(position.translate);
 
-    if all(vec2f(0,0) <= tex_coords) && all(tex_coords < f_tex_dimensions) {
-        let uv = (tex_coords + 0.5) / f_tex_dimensions;
+    if all(vec2f(0,0) <= tex_coords) && all(tex_coords < f_frame_dimensions) {
+        let uv = (tex_coords + 0.5) / f_frame_dimensions;
         let colour = textureSampleLevel(frame, s_texture, uv, 0.0);
-        textureStore(output, output_coords, colour);
+        // effects work in linear light, the output pass encodes back to srgb
+        var linear: vec3f;
+        if position.transfer != 0u && position.tone_mapping != 0u {
+            linear = tone_map(colour.rgb);
+        } else {
+            // hdr with tone mapping off round trips unchanged, it stays in the source's transfer
+            // bt.709 shares srgb's primaries, decoding with the srgb curve matches the preview
+            linear = srgb_to_linear(colour.rgb);
+        }
+        textureStore(output, output_coords, vec4f(linear, colour.a));
     }
 }
@@ -0,0 +1,416 @@
+use crate::range::Range;
+use crate::renderer::frame_position::FramePosition;
+use crate::renderer::pass::{uniform_entry, EffectPass};
+use crate::renderer::spatial::{
+    create_uniform_buffer, direction_vector, write_uniform, BlurDirection,
+};
+use crate::video::selectable::SelectableOption;
+use encase::ShaderType;
+use gst::ClockTime;
+use serde::{Deserialize, Serialize};
+use wgpu::include_wgsl;
+
+/// The shader's uniform holds a fixed size array, the sidebar and cli refuse more regions.
+pub const MAX_REDACT_REGIONS: usize = 16;
+/// Keyframes closer than this are on the same frame.
+const SAME_FRAME: ClockTime = ClockTime::from_mseconds(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactShape {
+    #[default]
+    Rectangle,
+    Ellipse,
+}
+
+impl SelectableOption for RedactShape {
+    const OPTIONS: &'static [Self] = &[RedactShape::Rectangle, RedactShape::Ellipse];
+    const UNLISTED: Self = RedactShape::Rectangle;
+
+    fn display(&self) -> &'static str {
+        match self {
+            RedactShape::Rectangle => "Rectangle",
+            RedactShape::Ellipse => "Ellipse",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactStyle {
+    #[default]
+    Blur,
+    Pixelate,
+}
+
+impl SelectableOption for RedactStyle {
+    const OPTIONS: &'static [Self] = &[RedactStyle::Blur, RedactStyle::Pixelate];
+    const UNLISTED: Self = RedactStyle::Blur;
+
+    fn display(&self) -> &'static str {
+        match self {
+            RedactStyle::Blur => "Blur",
+            RedactStyle::Pixelate => "Pixelate",
+        }
+    }
+}
+
+/// Normalised to the oriented frame before cropping, the same space the crop box uses.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RedactBounds {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl RedactBounds {
+    pub fn new(x: f32, y: f32, width: f32, height: f32) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    fn lerp(&self, other: &RedactBounds, t: f32) -> RedactBounds {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+        RedactBounds::new(
+            mix(self.x, other.x),
+            mix(self.y, other.y),
+            mix(self.width, other.width),
+            mix(self.height, other.height),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RedactKeyframe {
+    pub time: ClockTime,
+    pub bounds: RedactBounds,
+}
+
+/// An area of the frame hidden by blurring or pixelating it, optionally moving over time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RedactRegion {
+    pub shape: RedactShape,
+    pub style: RedactStyle,
+    /// Blur radius or pixel size as a fraction of the frame height.
+    pub strength: f32,
+    bounds: RedactBounds,
+    /// Sorted by time, when empty the region stays at `bounds`.
+    keyframes: Vec<RedactKeyframe>,
+}
+
+impl RedactRegion {
+    pub fn new(shape: RedactShape, bounds: RedactBounds) -> Self {
+        Self {
+            shape,
+            style: RedactStyle::default(),
+            strength: 0.03,
+            bounds,
+            keyframes: Vec::new(),
+        }
+    }
+
+    pub fn strength_range() -> (Range, Range) {
+        (
+            Range::new_with_default(0.005, 0.1, 0.03),
+            Range::new_with_default(5.0, 100.0, 30.0),
+        )
+    }
+
+    pub fn is_tracked(&self) -> bool {
+        !self.keyframes.is_empty()
+    }
+
+    pub fn keyframes(&self) -> &[RedactKeyframe] {
+        &self.keyframes
+    }
+
+    /// Interpolates between keyframes, holding the first and last outside them.
+    /// Frames without a timestamp (images) use the first keyframe.
+    pub fn bounds_at(&self, time: Option<ClockTime>) -> RedactBounds {
+        let (Some(first), Some(last)) = (self.keyframes.first(), self.keyframes.last()) else {
+            return self.bounds;
+        };
+        let Some(time) = time else {
+            return first.bounds;
+        };
+
+        if time <= first.time {
+            return first.bounds;
+        }
+        if time >= last.time {
+            return last.bounds;
+        }
+
+        let idx = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= time);
+        let (before, after) = (&self.keyframes[idx - 1], &self.keyframes[idx]);
+        let t =
+            (time - before.time).nseconds() as f64 / (after.time - before.time).nseconds() as f64;
+
+        before.bounds.lerp(&after.bounds, t as f32)
+    }
+
+    /// Moves the region, tracked regions get a keyframe at `time` instead.
+    pub fn set_bounds(&mut self, time: Option<ClockTime>, bounds: RedactBounds) {
+        match time.filter(|_| self.is_tracked()) {
+            Some(time) => self.set_keyframe(time, bounds),
+            None => self.bounds = bounds,
+        }
+    }
+
+    /// Starts following the region over time from where it is at `time`.
+    pub fn track(&mut self, time: ClockTime) {
+        if !self.is_tracked() {
+            self.keyframes.push(RedactKeyframe {
+                time,
+                bounds: self.bounds,
+            });
+        }
+    }
+
+    /// Drops the keyframes, leaving the region where it is at `time`.
+    pub fn untrack(&mut self, time: Option<ClockTime>) {
+        self.bounds = self.bounds_at(time);
+        self.keyframes.clear();
+    }
+
+    /// Removes the keyframe on the frame at `time`, returns if there was one.
+    pub fn remove_keyframe(&mut self, time: ClockTime) -> bool {
+        let Some(idx) = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time.absdiff(time) < SAME_FRAME)
+        else {
+            return false;
+        };
+
+        let removed = self.keyframes.remove(idx);
+        // the last keyframe leaves the region where it was
+        if self.keyframes.is_empty() {
+            self.bounds = removed.bounds;
+        }
+        true
+    }
+
+    fn set_keyframe(&mut self, time: ClockTime, bounds: RedactBounds) {
+        let keyframe = RedactKeyframe { time, bounds };
+        match self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time.absdiff(time) < SAME_FRAME)
+        {
+            Some(idx) => self.keyframes[idx] = keyframe,
+            None => {
+                let idx = self
+                    .keyframes
+                    .partition_point(|keyframe| keyframe.time < time);
+                self.keyframes.insert(idx, keyframe);
+            }
+        }
+    }
+}
+
+// matches redact.wgsl
+#[derive(ShaderType)]
+struct RegionUniform {
+    bounds: mint::Vector4<f32>,
+    shape: u32,
+    style: u32,
+    strength: f32,
+}
+
+#[derive(ShaderType)]
+struct RedactUniform {
+    frame_offset: mint::Vector2<f32>,
+    frame_size: mint::Vector2<f32>,
+    direction: mint::Vector2<i32>,
+    rotation: f32,
+    count: u32,
+    regions: [RegionUniform; MAX_REDACT_REGIONS],
+}
+
+/// One axis of the redaction blur or pixelation, run once per `BlurDirection`. See `redact.wgsl`.
+pub(crate) struct RedactPass {
+    direction: BlurDirection,
+    regions: Vec<RedactRegion>,
+    buffer: wgpu::Buffer,
+}
+
+impl RedactPass {
+    pub fn new(device: &wgpu::Device, direction: BlurDirection) -> Self {
+        Self {
+            direction,
+            regions: Vec::new(),
+            buffer: create_uniform_buffer(device, "redact buffer", RedactUniform::min_size()),
+        }
+    }
+
+    pub fn update(&mut self, mut regions: Vec<RedactRegion>) {
+        if regions.len() > MAX_REDACT_REGIONS {
+            tracing::error!(
+                "only {MAX_REDACT_REGIONS} of {} regions can be redacted",
+                regions.len()
+            );
+            regions.truncate(MAX_REDACT_REGIONS);
+        }
+        self.regions = regions;
+    }
+
+    /// Places the regions for the frame at `pts`, call before every render.
+    pub fn select(
+        &self,
+        queue: &wgpu::Queue,
+        frame_position: &FramePosition,
+        pts: Option<ClockTime>,
+    ) {
+        if self.regions.is_empty() {
+            return;
+        }
+
+        // regions are drawn on the uncropped frame, the output only holds the cropped part of it
+        let crop = frame_position.scaled_crop_edges();
+        let output_size = frame_position.output_frame_size();
+        let frame_size = [
+            output_size.width as f32 + crop[0] + crop[2],
+            output_size.height as f32 + crop[1] + crop[3],
+        ];
+
+        let uniform_regions = std::array::from_fn(|idx| {
+            let Some(region) = self.regions.get(idx) else {
+                return RegionUniform {
+                    bounds: [0.0; 4].into(),
+                    shape: 0,
+                    style: 0,
+                    strength: 0.0,
+                };
+            };
+
+            let bounds = region.bounds_at(pts);
+            RegionUniform {
+                bounds: [bounds.x, bounds.y, bounds.width, bounds.height].into(),
+                shape: match region.shape {
+                    RedactShape::Rectangle => 0,
+                    RedactShape::Ellipse => 1,
+                },
+                style: match region.style {
+                    RedactStyle::Blur => 0,
+                    RedactStyle::Pixelate => 1,
+                },
+                strength: region.strength,
+            }
+        });
+
+        write_uniform(
+            queue,
+            &self.buffer,
+            &RedactUniform {
+                frame_offset: [crop[0], crop[1]].into(),
+                frame_size: frame_size.into(),
+                direction: direction_vector(self.direction),
+                rotation: frame_position.straigthen_angle,
+                count: self.regions.len() as u32,
+                regions: uniform_regions,
+            },
+        );
+    }
+}
+
+impl EffectPass for RedactPass {
+    fn label(&self) -> &'static str {
+        match self.direction {
+            BlurDirection::Horizontal => "horizontal redact pass",
+            BlurDirection::Vertical => "vertical redact pass",
+        }
+    }
+
+    fn shader(&self) -> wgpu::ShaderModuleDescriptor<'static> {
+        include_wgsl!("redact.wgsl")
+    }
+
+    fn layout_entries(&self) -> Vec<wgpu::BindGroupLayoutEntry> {
+        vec![uniform_entry(0)]
+    }
+
+    fn bind_group_entries(&self) -> Vec<wgpu::BindGroupEntry<'_>> {
+        vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: self.buffer.as_entire_binding(),
+        }]
+    }
+
+    fn is_identity(&self) -> bool {
+        self.regions.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seconds(seconds: u64) -> Option<ClockTime> {
+        Some(ClockTime::from_seconds(seconds))
+    }
+
+    #[test]
+    fn keyframes_interpolate_and_hold() {
+        let start = RedactBounds::new(0.0, 0.0, 0.25, 0.25);
+        let end = RedactBounds::new(0.5, 0.5, 0.75, 0.25);
+
+        let mut region = RedactRegion::new(RedactShape::Rectangle, start);
+        region.track(ClockTime::from_seconds(2));
+        region.set_bounds(seconds(4), end);
+
+        assert_eq!(region.bounds_at(seconds(0)), start);
+        assert_eq!(
+            region.bounds_at(seconds(3)),
+            RedactBounds::new(0.25, 0.25, 0.5, 0.25)
+        );
+        assert_eq!(region.bounds_at(seconds(9)), end);
+        assert_eq!(region.bounds_at(None), start);
+    }
+
+    #[test]
+    fn moving_on_a_keyframe_replaces_it() {
+        let bounds = RedactBounds::new(0.25, 0.25, 0.25, 0.25);
+        let moved = RedactBounds::new(0.75, 0.25, 0.25, 0.25);
+
+        let mut region = RedactRegion::new(RedactShape::Ellipse, bounds);
+        region.set_bounds(seconds(1), moved);
+        assert!(!region.is_tracked());
+        assert_eq!(region.bounds_at(seconds(5)), moved);
+
+        region.track(ClockTime::from_seconds(1));
+        region.set_bounds(seconds(1), bounds);
+        assert_eq!(region.keyframes().len(), 1);
+
+        region.set_bounds(seconds(3), moved);
+        region.untrack(seconds(2));
+        assert!(!region.is_tracked());
+        assert_eq!(
+            region.bounds_at(None),
+            RedactBounds::new(0.5, 0.25, 0.25, 0.25)
+        );
+    }
+
+    #[test]
+    fn removing_the_last_keyframe_keeps_the_region() {
+        let bounds = RedactBounds::new(0.1, 0.1, 0.2, 0.2);
+        let moved = RedactBounds::new(0.6, 0.6, 0.2, 0.2);
+
+        let mut region = RedactRegion::new(RedactShape::Rectangle, bounds);
+        region.track(ClockTime::from_seconds(0));
+        region.set_bounds(seconds(2), moved);
+
+        assert!(!region.remove_keyframe(ClockTime::from_seconds(1)));
+        assert!(region.remove_keyframe(ClockTime::from_seconds(0)));
+        assert!(region.remove_keyframe(ClockTime::from_seconds(2)));
+        assert!(!region.is_tracked());
+        assert_eq!(region.bounds_at(seconds(0)), moved);
+    }
+}
@@ -0,0 +1,130 @@
+struct Region {
+    // x, y, width and height on the uncropped frame, from 0 to 1
+    bounds: vec4<f32>,
+    shape: u32,
+    style: u32,
+    // blur radius or pixel size, a fraction of the frame height
+    strength: f32,
+}
+
+struct Redact {
+    frame_offset: vec2<f32>,
+    frame_size: vec2<f32>,
+    direction: vec2<i32>,
+    rotation: f32,
+    count: u32,
+    regions: array<Region, 16>,
+}
+
+@group(0) @binding(0) var input: texture_storage_2d<rgba16float, read>;
+@group(0) @binding(1) var output: texture_storage_2d<rgba16float, write>;
+@group(1) @binding(0) var<uniform> redact: Redact;
+
+// shape and style ids, see renderer/redact.rs
+const shape_ellipse = 1u;
+const style_pixelate = 1u;
+
+@compute
+@workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let coords = global_invocation_id.xy;
+    let size = textureDimensions(output);
+
+    if !all(coords < size) {
+        return;
+    }
+
+    // the output only holds the cropped part of the frame the regions were drawn on
+    let frame_coords = vec2<f32>(coords) + 0.5 + redact.frame_offset;
+    let uv = straighten(frame_coords) / redact.frame_size;
+
+    // later regions are drawn over earlier ones
+    var covering = -1;
+    for (var idx = 0u; idx < redact.count; idx++) {
+        if contains(redact.regions[idx], uv) {
+            covering = i32(idx);
+        }
+    }
+
+    var colour = textureLoad(input, coords);
+    if covering >= 0 {
+        let region = redact.regions[covering];
+        let strength = max(region.strength * redact.frame_size.y, 1.0);
+
+        if region.style == style_pixelate {
+            colour = block_average(vec2<i32>(coords), frame_coords, strength);
+        } else {
+            colour = gaussian(vec2<i32>(coords), strength);
+        }
+    }
+
+    textureStore(output, coords, colour);
+}
+
+// undoes the straighten rotation from position.wgsl, regions are drawn on the unrotated frame
+fn straighten(frame_coords: vec2<f32>) -> vec2<f32> {
+    let center = redact.frame_size / 2.0;
+    let cos = cos(redact.rotation);
+    let sin = sin(redact.rotation);
+    let r = mat2x2(cos, -sin, sin, cos);
+
+    return r * (frame_coords - center) + center;
+}
+
+fn contains(region: Region, uv: vec2<f32>) -> bool {
+    let start = region.bounds.xy;
+
+    if region.shape == shape_ellipse {
+        let radius = max(region.bounds.zw / 2.0, vec2<f32>(1e-6));
+        let offset = (uv - (start + radius)) / radius;
+        return dot(offset, offset) <= 1.0;
+    }
+
+    return all(start <= uv) && all(uv <= start + region.bounds.zw);
+}
+
+// averages the pixel's block along the pass's axis, both passes together average the whole block.
+// every pixel in the block is read, so no detail survives between samples
+fn block_average(coords: vec2<i32>, frame_coords: vec2<f32>, block: f32) -> vec4<f32> {
+    let direction = vec2<f32>(redact.direction);
+    let along = dot(coords, redact.direction);
+    let row_start = coords - redact.direction * along;
+
+    // blocks are laid out on the uncropped frame so cropping doesn't shift them
+    let offset = dot(redact.frame_offset, direction);
+    let block_start = floor(dot(frame_coords, direction) / block) * block - offset;
+    let first = i32(ceil(block_start - 0.5));
+    let last = i32(ceil(block_start + block - 0.5)) - 1;
+
+    let max_coords = vec2<i32>(textureDimensions(input)) - 1;
+
+    var total = vec4<f32>(0.0);
+    var samples = 0.0;
+    for (var position = first; position <= last; position++) {
+        let sample_coords = clamp(row_start + redact.direction * position, vec2<i32>(0), max_coords);
+        total += textureLoad(input, sample_coords);
+        samples += 1.0;
+    }
+
+    return total / samples;
+}
+
+// the radius covers three standard deviations, the kernel is clamped to the frame's edges.
+// like blur.wgsl every pixel under it is read, strided taps let detail leak through wide kernels
+fn gaussian(coords: vec2<i32>, radius: f32) -> vec4<f32> {
+    let sigma = max(radius / 3.0, 0.01);
+    let taps = i32(ceil(radius));
+    let max_coords = vec2<i32>(textureDimensions(input)) - 1;
+
+    var total = vec4<f32>(0.0);
+    var weights = 0.0;
+    for (var offset = -taps; offset <= taps; offset++) {
+        let weight = exp(-f32(offset * offset) / (2.0 * sigma * sigma));
+        let sample_coords = clamp(coords + redact.direction * offset, vec2<i32>(0), max_coords);
+
+        total += textureLoad(input, sample_coords) * weight;
+        weights += weight;
+    }
+
+    return total / weights;
+}
@@ -1,29 +1,58 @@
+use crate::renderer::backend::{self, ReadbackBuffer, RenderBackend};
+use crate::renderer::colour::{input_texture_format, SourceTransfer};
+use crate::renderer::effects::ColourPass;
 use crate::renderer::export_texture::ExportTexture;
 use crate::renderer::frame_position::{FramePosition, FrameSize};
 use crate::renderer::handler::TimerCmd;
+use crate::renderer::lut::{Lut, LutPass};
+use crate::renderer::overlay::Overlay;
+use crate::renderer::pass::{
+    sampled_texture_entry, sampler_entry, uniform_entry, with_srgb, EffectPass, PassChain,
+    INTERMEDIATE_FORMAT,
+};
 use crate::renderer::presenter::Presenter;
+use crate::renderer::redact::RedactPass;
+use crate::renderer::spatial::{BlurDirection, BlurPass, Sharpen, VignettePass};
 use crate::renderer::texture::Texture;
 use crate::renderer::timer::{GpuTimer, QuerySet};
-use crate::renderer::{EffectParameters, TimerEvent};
+use crate::renderer::{
+    Curves, EffectParameters, RedactRegion, SpatialParameters, TimerEvent, ToneMapping,
+};
 use crate::ui::preview::Orientation;
-use ash::vk;
-use gst::Sample;
+use gst::{ClockTime, Sample};
 use image::DynamicImage;
-use relm4::gtk::gdk;
+use relm4::gtk::prelude::Cast;
+use relm4::gtk::{gdk, glib};
 use std::cell::RefCell;
 use std::default::Default;
 use std::os::fd::RawFd;
-use std::sync::{mpsc, LazyLock};
+use std::sync::{mpsc, Arc, LazyLock};
 use std::time::Instant;
-use wgpu::{hal, include_wgsl};
 
 static INPUT_TEXTURE_USAGE: LazyLock<wgpu::TextureUsages> =
     LazyLock::new(|| wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::TEXTURE_BINDING);
 
+#[derive(Debug)]
+pub enum FrameMemory {
+    Dmabuf {
+        fd: RawFd,
+        texture: ExportTexture,
+    },
+    /// Tightly packed rgba rows from the readback backend.
+    Cpu(Vec<u8>),
+}
+
+/// Where the last pass is copied to, depends on the backend.
+enum RenderOutput {
+    Dmabuf(ExportTexture),
+    Readback(ReadbackBuffer),
+}
+
 #[derive(Debug)]
 pub struct RenderedFrame {
-    pub fd: RawFd,
-    pub texture: ExportTexture,
+    pub memory: FrameMemory,
+    /// Timestamp of the sample the frame was rendered from, `None` for images.
+    pub pts: Option<ClockTime>,
     fourcc: u32,
     modifer: u64,
     width: u32,
@@ -34,22 +63,40 @@ pub struct RenderedFrame {
 
 impl RenderedFrame {
     fn padded_width(&self) -> u32 {
+        let FrameMemory::Dmabuf { texture, .. } = &self.memory else {
+            return self.width;
+        };
+
         // todo: use fourcc to get bytes per pixel (replace 4)
-        let pixels_per_block = self.texture.alignment / 4;
+        let pixels_per_block = texture.alignment / 4;
         let blocks_needed = (self.width as f32 / pixels_per_block as f32).ceil() as u32;
 
         blocks_needed * (pixels_per_block as u32)
     }
 
     fn row_stride(&self) -> u32 {
-        self.padded_width() * 4
+        self.padded_width() * self.pixel_stride
     }
 
     pub fn build_gdk_texture(&self) -> gdk::Texture {
+        let fd = match &self.memory {
+            FrameMemory::Dmabuf { fd, .. } => *fd,
+            FrameMemory::Cpu(pixels) => {
+                return gdk::MemoryTexture::new(
+                    self.width as i32,
+                    self.height as i32,
+                    gdk::MemoryFormat::R8g8b8a8,
+                    &glib::Bytes::from(pixels.as_slice()),
+                    self.row_stride() as usize,
+                )
+                .upcast();
+            }
+        };
+
         let builder = gdk::DmabufTextureBuilder::new();
 
         builder.set_display(&gdk::Display::default().unwrap());
-        builder.set_fd(0, self.fd as i32);
+        builder.set_fd(0, fd as i32);
         builder.set_fourcc(self.fourcc);
         builder.set_modifier(self.modifer);
         builder.set_width(self.width);
@@ -68,22 +115,25 @@ impl RenderedFrame {
 pub struct Renderer {
     output_size: FrameSize,
     frame_position: FramePosition,
-    effect_parameters: EffectParameters,
     input_texture: RefCell<Texture>,
     frame_position_pipeline: wgpu::ComputePipeline,
     frame_position_bind_group_layout: wgpu::BindGroupLayout,
     frame_position_bind_group: wgpu::BindGroup,
     frame_position_buffer: wgpu::Buffer,
-    positioned_frame: Texture,
-    effects_pipeline: wgpu::ComputePipeline,
-    effects_bind_group_layout: wgpu::BindGroupLayout,
-    effects_bind_group: wgpu::BindGroup,
-    effects_buffer: wgpu::Buffer,
-    post_effects_frame: Texture,
-    presenter: Presenter,
-    current_export_frame: Option<ExportTexture>, // should be only used when exporting.
+    colour: ColourPass,
+    lut: LutPass,
+    blur: [BlurPass; 2],
+    sharpen: Sharpen,
+    vignette: VignettePass,
+    redact: [RedactPass; 2],
+    overlay: Overlay,
+    pass_chain: PassChain,
+    current_pts: Option<ClockTime>,
+    presenter: Option<Presenter>,
+    current_output: Option<RenderOutput>,
     pub(crate) gpu_timer: GpuTimer,
     timer: mpsc::Sender<TimerCmd>,
+    backend: RenderBackend,
     device: wgpu::Device,
     queue: wgpu::Queue,
     _adapter: wgpu::Adapter,
@@ -92,103 +142,43 @@ pub struct Renderer {
 
 impl Renderer {
     pub async fn new(timer_sender: mpsc::Sender<TimerCmd>) -> Renderer {
-        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::PRIMARY,
-            ..Default::default()
-        });
-
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: None,
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
+        Self::with_backend(timer_sender, RenderBackend::preferred()).await
+    }
 
-        let (device, queue) = create_device_queue(
-            &instance,
-            &adapter,
+    /// Dmabuf falls back to readback when the adapter can't export frames.
+    pub async fn with_backend(
+        timer_sender: mpsc::Sender<TimerCmd>,
+        backend: RenderBackend,
+    ) -> Renderer {
+        let backend::GpuContext {
+            instance,
+            adapter,
+            device,
+            queue,
+            backend,
+        } = backend::create_context(
+            backend,
             wgpu::Features::TIMESTAMP_QUERY
                 | wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES,
-        );
+        )
+        .await;
 
-        // assuming timestamp feature available always
+        // no op when timestamps aren't supported, e.g. software adapters
         let timer = GpuTimer::new(&device);
 
         let frame_position_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 label: Some("Frame Position bind group layout"),
                 entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Texture {
-                            multisampled: false,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
+                    sampled_texture_entry(0, wgpu::TextureViewDimension::D2),
+                    sampler_entry(1),
+                    uniform_entry(2),
                     wgpu::BindGroupLayoutEntry {
                         binding: 3,
                         visibility: wgpu::ShaderStages::COMPUTE,
                         ty: wgpu::BindingType::StorageTexture {
                             access: wgpu::StorageTextureAccess::WriteOnly,
-                            format: wgpu::TextureFormat::Rgba8Unorm,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                        },
-                        count: None,
-                    },
-                ],
-            });
-
-        let effects_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("Effects bind group layout"),
-                entries: &[
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 0,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::StorageTexture {
-                            access: wgpu::StorageTextureAccess::ReadOnly,
-                            format: wgpu::TextureFormat::Rgba8Unorm,
-                            view_dimension: wgpu::TextureViewDimension::D2,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 1,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::Buffer {
-                            ty: wgpu::BufferBindingType::Uniform,
-                            has_dynamic_offset: false,
-                            min_binding_size: None,
-                        },
-                        count: None,
-                    },
-                    wgpu::BindGroupLayoutEntry {
-                        binding: 2,
-                        visibility: wgpu::ShaderStages::COMPUTE,
-                        ty: wgpu::BindingType::StorageTexture {
-                            access: wgpu::StorageTextureAccess::WriteOnly,
-                            format: wgpu::TextureFormat::Rgba8Unorm,
+                            format: INTERMEDIATE_FORMAT,
                             view_dimension: wgpu::TextureViewDimension::D2,
                         },
                         count: None,
@@ -196,51 +186,55 @@ impl Renderer {
                 ],
             });
 
-        let effect_parameters = EffectParameters::new();
-        let effects_buffer = effect_parameters.buffer(&device);
-
         let output_size = FrameSize::new(512, 288);
         let input_texture =
             Texture::new(&device, output_size.into(), *INPUT_TEXTURE_USAGE, None).unwrap();
 
         let frame_position = FramePosition::new(output_size);
-        let presenter = Presenter::new(2, &device, &instance, output_size.into());
-
-        let (
-            positioned_frame_buffer,
-            frame_position_buffer,
-            frame_position_bind_group,
-            effects_output_buffer,
-            effects_bind_group,
-        ) = Self::create_render_target(
-            output_size,
-            &effects_buffer,
-            &effects_bind_group_layout,
-            &frame_position_bind_group_layout,
-            &frame_position,
-            &input_texture,
+        let presenter = (backend == RenderBackend::Dmabuf)
+            .then(|| Presenter::new(2, &device, &instance, output_size.into()));
+
+        let colour = ColourPass::new(&device, &queue);
+        let lut = LutPass::new(&device, &queue);
+        let blur = [
+            BlurPass::new(&device, BlurDirection::Horizontal),
+            BlurPass::new(&device, BlurDirection::Vertical),
+        ];
+        let sharpen = Sharpen::new(&device, output_size);
+        let vignette = VignettePass::new(&device);
+        let redact = [
+            RedactPass::new(&device, BlurDirection::Horizontal),
+            RedactPass::new(&device, BlurDirection::Vertical),
+        ];
+        let overlay = Overlay::new(&device);
+        let pass_chain = PassChain::new(
             &device,
+            &[
+                &colour,
+                &lut,
+                &blur[0],
+                &blur[1],
+                &sharpen.blur,
+                &sharpen.combine,
+                &vignette,
+                &redact[0],
+                &redact[1],
+                &overlay,
+            ],
+            output_size,
         );
 
-        let effects_shader = device.create_shader_module(include_wgsl!("effects.wgsl"));
-
-        let effects_pipeline_layout =
-            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-                label: Some("effects pipeline"),
-                bind_group_layouts: &[&effects_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-
-        let effects_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
-            label: Some("effects pipeline"),
-            layout: Some(&effects_pipeline_layout),
-            module: &effects_shader,
-            entry_point: Some("main"),
-            compilation_options: Default::default(),
-            cache: None,
-        });
+        let (frame_position_buffer, frame_position_bind_group) =
+            Self::create_frame_positon_bind_group(
+                &device,
+                &frame_position_bind_group_layout,
+                &frame_position,
+                &input_texture,
+                pass_chain.source(),
+            );
 
-        let position_shader = device.create_shader_module(include_wgsl!("position.wgsl"));
+        let position_shader =
+            device.create_shader_module(with_srgb("position.wgsl", include_str!("position.wgsl")));
 
         let frame_position_pipeline =
             device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
@@ -265,42 +259,57 @@ impl Renderer {
             queue,
             output_size,
             frame_position,
-            effect_parameters,
             input_texture: RefCell::new(input_texture),
             frame_position_pipeline,
             frame_position_bind_group_layout,
             frame_position_bind_group,
             frame_position_buffer,
-            positioned_frame: positioned_frame_buffer,
-            effects_pipeline,
-            effects_bind_group_layout,
-            effects_bind_group,
-            effects_buffer,
-            post_effects_frame: effects_output_buffer,
+            colour,
+            lut,
+            blur,
+            sharpen,
+            vignette,
+            redact,
+            overlay,
+            pass_chain,
+            current_pts: None,
             presenter,
-            current_export_frame: None,
+            current_output: None,
             gpu_timer: timer,
             timer: timer_sender,
+            backend,
         }
     }
 
-    fn create_frame_positon_bind_groups(
+    pub fn backend(&self) -> RenderBackend {
+        self.backend
+    }
+
+    /// Run in order after positioning, must match the order the pass chain was built with.
+    fn effect_passes(&self) -> [&dyn EffectPass; 10] {
+        [
+            &self.colour,
+            &self.lut,
+            &self.blur[0],
+            &self.blur[1],
+            &self.sharpen.blur,
+            &self.sharpen.combine,
+            &self.vignette,
+            &self.redact[0],
+            &self.redact[1],
+            &self.overlay,
+        ]
+    }
+
+    fn create_frame_positon_bind_group(
         device: &wgpu::Device,
-        frame_size: FrameSize,
         frame_position_bind_group_layout: &wgpu::BindGroupLayout,
         frame_position: &FramePosition,
         source_texture: &Texture,
-    ) -> (Texture, wgpu::Buffer, wgpu::BindGroup) {
+        positioned_frame: &Texture,
+    ) -> (wgpu::Buffer, wgpu::BindGroup) {
         let frame_position_buffer = frame_position.buffer(&device);
 
-        let positioned_frame = Texture::new(
-            device,
-            frame_size.into(),
-            wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::STORAGE_BINDING,
-            Some("positioned frame"),
-        )
-        .unwrap();
-
         let frame_position_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Frame Position Bind Group"),
             layout: &frame_position_bind_group_layout,
@@ -326,133 +335,49 @@ impl Renderer {
             ],
         });
 
-        (
-            positioned_frame,
-            frame_position_buffer,
-            frame_position_bind_group,
-        )
+        (frame_position_buffer, frame_position_bind_group)
     }
 
-    fn create_effects_bind_groups(
-        device: &wgpu::Device,
-        frame_size: FrameSize,
-        effects_bind_group_layout: &wgpu::BindGroupLayout,
-        effect_parameters_buffer: &wgpu::Buffer,
-        input_texture: &Texture,
-    ) -> (Texture, wgpu::BindGroup) {
-        let post_effects_frame = Texture::new(
-            device,
-            frame_size.into(),
-            wgpu::TextureUsages::COPY_DST
-                | wgpu::TextureUsages::COPY_SRC
-                | wgpu::TextureUsages::STORAGE_BINDING,
-            Some("post effects frame"),
-        )
-        .unwrap();
-
-        let effects_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("Effects Bind Group"),
-            layout: &effects_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&input_texture.view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: effect_parameters_buffer.as_entire_binding(),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 2,
-                    resource: wgpu::BindingResource::TextureView(&post_effects_frame.view),
-                },
-            ],
-        });
-
-        (post_effects_frame, effects_bind_group)
-    }
+    fn update_render_target(&mut self, output_frame_size: FrameSize) {
+        self.pass_chain.resize(&self.device, output_frame_size);
+        self.sharpen.resize(&self.device, output_frame_size);
 
-    fn create_render_target(
-        output_frame_size: FrameSize,
-        effect_buffer: &wgpu::Buffer,
-        effects_bind_group_layout: &wgpu::BindGroupLayout,
-        frame_position_bind_group_layout: &wgpu::BindGroupLayout,
-        frame_position: &FramePosition,
-        texture: &Texture,
-        device: &wgpu::Device,
-    ) -> (
-        Texture,
-        wgpu::Buffer,
-        wgpu::BindGroup,
-        Texture,
-        wgpu::BindGroup,
-    ) {
-        let (positioned_frame, frame_position_buffer, frame_position_bind_group) =
-            Self::create_frame_positon_bind_groups(
-                &device,
-                output_frame_size,
-                &frame_position_bind_group_layout,
-                &frame_position,
-                &texture,
+        let (frame_position_buffer, frame_postion_bind_group) =
+            Self::create_frame_positon_bind_group(
+                &self.device,
+                &self.frame_position_bind_group_layout,
+                &self.frame_position,
+                &self.input_texture.borrow(),
+                self.pass_chain.source(),
             );
 
-        let (post_effects_frame, effects_bind_group) = Self::create_effects_bind_groups(
-            &device,
-            output_frame_size,
-            &effects_bind_group_layout,
-            &effect_buffer,
-            &positioned_frame,
-        );
-
-        (
-            positioned_frame,
-            frame_position_buffer,
-            frame_position_bind_group,
-            post_effects_frame,
-            effects_bind_group,
-        )
-    }
-
-    fn update_render_target(&mut self, output_frame_size: FrameSize) {
-        let (
-            positioned_frame,
-            frame_position_buffer,
-            frame_postion_bind_group,
-            post_effects_frame,
-            effects_bind_group,
-        ) = Self::create_render_target(
-            output_frame_size,
-            &self.effects_buffer,
-            &self.effects_bind_group_layout,
-            &self.frame_position_bind_group_layout,
-            &self.frame_position,
-            &self.input_texture.borrow(),
-            &self.device,
-        );
-
-        self.presenter
-            .resize_outputs(&self.device, &self.instance, output_frame_size.into());
-        self.positioned_frame = positioned_frame;
+        if let Some(presenter) = self.presenter.as_mut() {
+            presenter.resize_outputs(&self.device, &self.instance, output_frame_size.into());
+        }
         self.frame_position_buffer = frame_position_buffer;
         self.frame_position_bind_group = frame_postion_bind_group;
-        self.post_effects_frame = post_effects_frame;
-        self.effects_bind_group = effects_bind_group;
     }
 
-    pub fn is_size_equal_to_curr_input_size(&self, width: u32, height: u32) -> bool {
+    pub fn is_input_texture_equal(
+        &self,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> bool {
         let texture = &self.input_texture.borrow().texture;
-        width == texture.width() && height == texture.height()
+        width == texture.width() && height == texture.height() && format == texture.format()
     }
 
-    fn update_input_texture_size(&mut self, width: u32, height: u32) {
+    fn update_input_texture(&mut self, width: u32, height: u32, format: wgpu::TextureFormat) {
         self.input_texture.replace(
-            Texture::new(
+            Texture::with_format(
                 &self.device,
                 wgpu::Extent3d {
                     width,
                     height,
                     depth_or_array_layers: 1,
                 },
+                format,
                 *INPUT_TEXTURE_USAGE,
                 Some("video frame texture"),
             )
@@ -498,61 +423,50 @@ impl Renderer {
             );
         }
 
-        let mut rendered_frame = &self.positioned_frame;
-
-        if !self.effect_parameters.is_default() {
-            {
-                let mut effects_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
-                    label: Some("Effects Pass"),
-                    timestamp_writes: self.gpu_timer.query_timestamp_writes(QuerySet::Effects),
-                });
+        self.overlay
+            .select(&self.device, &self.queue, self.current_pts);
+        for pass in &self.redact {
+            pass.select(&self.queue, &self.frame_position, self.current_pts);
+        }
+        let rendered_frame = self.pass_chain.encode(
+            &self.device,
+            &mut encoder,
+            &self.effect_passes(),
+            &self.gpu_timer,
+        );
 
-                effects_pass.set_pipeline(&self.effects_pipeline);
-                effects_pass.set_bind_group(0, &self.effects_bind_group, &[]);
-                effects_pass.dispatch_workgroups(
-                    self.output_size.width.div_ceil(256),
-                    self.output_size.height,
-                    1,
+        let final_output = match self.backend {
+            RenderBackend::Dmabuf => {
+                // let final_output = self.presenter.next_presentation_texture();
+                let final_output =
+                    ExportTexture::new(&self.device, &self.instance, self.output_size.into());
+
+                encoder.copy_texture_to_texture(
+                    wgpu::TexelCopyTextureInfo {
+                        texture: &rendered_frame.texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d::ZERO,
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    wgpu::TexelCopyTextureInfo {
+                        texture: &final_output.texture,
+                        mip_level: 0,
+                        origin: wgpu::Origin3d::ZERO,
+                        aspect: wgpu::TextureAspect::All,
+                    },
+                    final_output.texture.size(),
                 );
+                RenderOutput::Dmabuf(final_output)
             }
+            RenderBackend::Readback => {
+                let readback = ReadbackBuffer::new(&self.device, self.output_size.into());
+                readback.encode_copy(&mut encoder, &rendered_frame.texture);
+                RenderOutput::Readback(readback)
+            }
+        };
+        self.current_output.replace(final_output);
 
-            rendered_frame = &self.post_effects_frame;
-        }
-
-        // let final_output = self.presenter.next_presentation_texture();
-        let final_output =
-            ExportTexture::new(&self.device, &self.instance, self.output_size.into());
-
-        encoder.copy_texture_to_texture(
-            wgpu::TexelCopyTextureInfo {
-                texture: &rendered_frame.texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            wgpu::TexelCopyTextureInfo {
-                texture: &final_output.texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d::ZERO,
-                aspect: wgpu::TextureAspect::All,
-            },
-            final_output.texture.size(),
-        );
-        self.current_export_frame.replace(final_output);
-
-        encoder.resolve_query_set(
-            &self.gpu_timer.query_set,
-            0..self.gpu_timer.queries(),
-            &self.gpu_timer.resolve_buffer,
-            0,
-        );
-        encoder.copy_buffer_to_buffer(
-            &self.gpu_timer.resolve_buffer,
-            0,
-            &self.gpu_timer.result_buffer,
-            0,
-            self.gpu_timer.result_buffer.size(),
-        );
+        self.gpu_timer.encode_resolve(&mut encoder);
 
         encoder.finish()
     }
@@ -569,10 +483,16 @@ impl Renderer {
             .unwrap();
 
         // let output = self.presenter.current_presentation_texture();
-        let output = self.current_export_frame.take().unwrap();
+        let memory = match self.current_output.take().unwrap() {
+            RenderOutput::Dmabuf(texture) => FrameMemory::Dmabuf {
+                fd: texture.fd,
+                texture,
+            },
+            RenderOutput::Readback(readback) => FrameMemory::Cpu(readback.read(&self.device)),
+        };
         let frame = RenderedFrame {
-            fd: output.fd,
-            texture: output,
+            memory,
+            pts: self.current_pts,
             fourcc: 875709016,
             modifer: 0,
             width: self.output_size.width,
@@ -609,21 +529,30 @@ impl Renderer {
 
         let caps = sample.caps().expect("sample without caps");
         let info = gst_video::VideoInfo::from_caps(caps).expect("Failed to parse caps");
+        let format = input_texture_format(info.format());
+        let transfer = SourceTransfer::from_colorimetry(&info.colorimetry());
+        let transfer_changed = transfer != self.frame_position.transfer;
+        self.frame_position.transfer = transfer;
 
-        if !self.is_size_equal_to_curr_input_size(info.width(), info.height()) {
-            self.update_input_texture_size(info.width(), info.height());
+        if !self.is_input_texture_equal(info.width(), info.height(), format) {
+            self.update_input_texture(info.width(), info.height(), format);
             self.gpu_timer.reset();
+        } else if transfer_changed {
+            self.update_render_target(self.output_size);
         }
         self.sample_to_texture(sample);
+        self.current_pts = sample.buffer().and_then(|buffer| buffer.pts());
         self.timer
             .send(TimerCmd::Stop(TimerEvent::SampleImport, Instant::now()))
             .unwrap();
     }
 
     pub fn upload_new_image(&mut self, img: &DynamicImage) {
-        if !self.is_size_equal_to_curr_input_size(img.width(), img.height()) {
-            self.update_input_texture_size(img.width(), img.height());
+        let format = wgpu::TextureFormat::Rgba8Unorm;
+        if !self.is_input_texture_equal(img.width(), img.height(), format) {
+            self.update_input_texture(img.width(), img.height(), format);
         }
+        self.current_pts = None;
         self.input_texture
             .borrow()
             .write_from_image(&self.queue, img);
@@ -631,25 +560,51 @@ impl Renderer {
     }
 
     pub fn update_effects(&mut self, parameters: EffectParameters) {
-        if parameters.is_default() {
+        self.colour.update(&self.queue, parameters);
+        self.toggle_effects_timer();
+    }
+
+    pub fn update_curves(&mut self, curves: Curves) {
+        self.colour.update_curves(&self.device, &self.queue, curves);
+        self.toggle_effects_timer();
+    }
+
+    fn toggle_effects_timer(&mut self) {
+        if self.colour.is_identity() {
             self.gpu_timer.disable_query_set(QuerySet::Effects);
         } else {
             self.gpu_timer.enable_query_set(QuerySet::Effects);
         }
+    }
+
+    pub fn update_spatial(&mut self, parameters: SpatialParameters) {
+        for pass in &mut self.blur {
+            pass.update(&self.queue, parameters.blur_radius);
+        }
+        self.sharpen.update(
+            &self.queue,
+            parameters.sharpen_amount,
+            parameters.sharpen_radius,
+        );
+        self.vignette.update(&self.queue, &parameters);
+    }
+
+    pub fn update_redactions(&mut self, regions: Vec<RedactRegion>) {
+        for pass in &mut self.redact {
+            pass.update(regions.clone());
+        }
+    }
 
-        self.effect_parameters = parameters;
+    pub fn update_lut(&mut self, lut: Option<Arc<Lut>>, intensity: f32) {
+        self.lut.update(&self.device, &self.queue, lut, intensity);
+    }
 
-        let mut view = self
-            .queue
-            .write_buffer_with(
-                &self.effects_buffer,
-                0,
-                wgpu::BufferSize::new(self.effects_buffer.size()).unwrap(),
-            )
-            .unwrap();
+    pub fn queue_overlay(&mut self, sample: gst::Sample) {
+        self.overlay.queue_sample(sample);
+    }
 
-        let buffer: &mut [f32] = bytemuck::cast_slice_mut(&mut view);
-        buffer.clone_from_slice(bytemuck::cast_slice(&[self.effect_parameters]));
+    pub fn clear_overlays(&mut self) {
+        self.overlay.clear();
     }
 
     pub async fn render_frame(&mut self) -> RenderedFrame {
@@ -666,6 +621,16 @@ impl Renderer {
         self.update_buffers_for_output_size(size);
     }
 
+    /// Call before updating the output resolution, which is in display pixels.
+    pub fn set_pixel_aspect_ratio(&mut self, pixel_aspect_ratio: f32) {
+        self.frame_position.pixel_aspect_ratio = pixel_aspect_ratio;
+    }
+
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.frame_position.tone_mapping = tone_mapping;
+        self.update_render_target(self.output_size);
+    }
+
     pub fn orient(&mut self, orientation: Orientation) {
         self.frame_position.orientation = orientation;
         let size = self.frame_position.output_frame_size();
@@ -679,101 +644,29 @@ impl Renderer {
     }
 }
 
-fn create_device_queue(
-    instance: &wgpu::Instance,
-    adapter: &wgpu::Adapter,
-    required_features: wgpu::Features,
-) -> (wgpu::Device, wgpu::Queue) {
-    let instance = unsafe {
-        if let Some(instance) = instance.as_hal::<hal::api::Vulkan>() {
-            instance.shared_instance().raw_instance()
-        } else {
-            panic!("Failed to get vulakn hal instance");
-        }
-    };
-
-    let mut open_device = None;
-    let all_features = adapter.features() | required_features;
-    unsafe {
-        adapter.as_hal::<hal::api::Vulkan, _, _>(|adapter| {
-            if let Some(adapter) = adapter {
-                let raw = adapter.raw_physical_device();
-
-                let mut enabled_extensions = adapter.required_device_extensions(all_features);
-                enabled_extensions.push(vk::EXT_EXTERNAL_MEMORY_DMA_BUF_NAME);
-                enabled_extensions.push(vk::KHR_EXTERNAL_MEMORY_FD_NAME);
-                enabled_extensions.push(vk::KHR_EXTERNAL_MEMORY_NAME);
-                enabled_extensions.push(vk::EXT_IMAGE_DRM_FORMAT_MODIFIER_NAME);
-
-                let mut enabled_phd_features =
-                    adapter.physical_device_features(&enabled_extensions, all_features);
-
-                let queue_create_info = vk::DeviceQueueCreateInfo::default()
-                    .queue_family_index(0)
-                    .queue_priorities(&[1.0]);
-                let queue_family_infos = [queue_create_info];
-
-                let str_pointers = enabled_extensions
-                    .iter()
-                    .map(|&s| s.as_ptr())
-                    .collect::<Vec<_>>();
-
-                let pre_info = vk::DeviceCreateInfo::default()
-                    .queue_create_infos(&queue_family_infos)
-                    .enabled_extension_names(&str_pointers);
-
-                let device_create_info = enabled_phd_features.add_to_device_create(pre_info);
-
-                let raw_device = instance
-                    .create_device(raw, &device_create_info, None)
-                    .expect("Failed to create device");
-
-                open_device = Some(
-                    adapter
-                        .device_from_raw(
-                            raw_device,
-                            None,
-                            &enabled_extensions,
-                            required_features,
-                            &wgpu::MemoryHints::Performance,
-                            0,
-                            0,
-                        )
-                        .expect("Failed to create adapter"),
-                );
-            }
-        })
-    };
-
-    let (device, queue) = unsafe {
-        adapter
-            .create_device_from_hal(
-                open_device.unwrap(),
-                &wgpu::DeviceDescriptor {
-                    required_features,
-                    required_limits: wgpu::Limits::default(),
-                    label: None,
-                    memory_hints: wgpu::MemoryHints::Performance,
-                },
-                None,
-            )
-            .expect("Failed to create device and queue from hal")
-    };
-
-    (device, queue)
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::*;
+    use crate::renderer::{RedactBounds, RedactShape, RedactStyle};
     use std::os::fd::{FromRawFd, OwnedFd};
     use std::path::Path;
 
     fn save_frame(frame: RenderedFrame, save_path: &Path) {
         let start = Instant::now();
+        let fd = match frame.memory {
+            FrameMemory::Dmabuf { fd, .. } => fd,
+            FrameMemory::Cpu(pixels) => {
+                image::RgbaImage::from_raw(frame.width, frame.height, pixels)
+                    .unwrap()
+                    .save(save_path)
+                    .unwrap();
+                println!("saved to file in: {:?}", start.elapsed());
+                return;
+            }
+        };
         {
-            let owned_fd = unsafe { OwnedFd::from_raw_fd(frame.fd) };
+            let owned_fd = unsafe { OwnedFd::from_raw_fd(fd) };
             let dma_buf = dma_buf::DmaBuf::from(owned_fd);
             let mapped_buf = dma_buf.memory_map().unwrap();
 
@@ -814,6 +707,7 @@ mod tests {
         frame_position.orientation = Orientation {
             angle: 0.0,
             base_angle: 0.0,
+            base_mirrored: false,
             mirrored: false,
         };
         // frame_position.straigthen_angle = 31f32.to_radians();
@@ -829,4 +723,559 @@ mod tests {
         println!("time to render: {}", r.gpu_timer.frame_time_msg());
         save_frame(frame, "test_render_output.png".as_ref());
     }
+
+    #[tokio::test]
+    async fn readback_matches_input() {
+        let (sender, _recv) = mpsc::channel();
+        let mut r = Renderer::with_backend(sender, RenderBackend::Readback).await;
+
+        let img = image::open(IMG_TEST_LANDSCAPE).unwrap();
+        r.upload_new_image(&img);
+        r.position_frame(FramePosition::new(FrameSize::new(
+            img.width(),
+            img.height(),
+        )));
+
+        let frame = r.render_frame().await;
+        let FrameMemory::Cpu(pixels) = frame.memory else {
+            panic!("readback backend returned a dmabuf");
+        };
+
+        let expected = img.to_rgba8();
+        assert_eq!(pixels.len(), expected.as_raw().len());
+        let max_diff = pixels
+            .iter()
+            .zip(expected.as_raw())
+            .map(|(a, b)| a.abs_diff(*b))
+            .max()
+            .unwrap();
+        assert!(max_diff <= 1, "pixels differ by up to {max_diff}");
+    }
+
+    fn srgb_to_linear(value: f32) -> f32 {
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    }
+
+    fn linear_to_srgb(value: f32) -> f32 {
+        let value = value.clamp(0.0, 1.0);
+        if value <= 0.0031308 {
+            value * 12.92
+        } else {
+            1.055 * value.powf(1.0 / 2.4) - 0.055
+        }
+    }
+
+    /// Grey ramp over a hue sweep, covers shadows to highlights and every primary.
+    fn test_chart() -> image::RgbaImage {
+        image::RgbaImage::from_fn(64, 32, |x, y| {
+            if y < 16 {
+                let level = (x * 4) as u8;
+                image::Rgba([level, level, level, 255])
+            } else {
+                let hue = x as f32 / 64.0 * 6.0;
+                let channel = |offset: f32| {
+                    let distance = ((hue - offset).rem_euclid(6.0) - 3.0).abs();
+                    ((distance - 1.0).clamp(0.0, 1.0) * 200.0) as u8
+                };
+                image::Rgba([channel(0.0), channel(4.0), channel(2.0), 255])
+            }
+        })
+    }
+
+    /// Reads back `image` rendered unpositioned, after `configure` has set up the effects.
+    async fn render_image(
+        image: &image::RgbaImage,
+        configure: impl FnOnce(&mut Renderer),
+    ) -> Vec<u8> {
+        let (sender, _recv) = mpsc::channel();
+        let mut r = Renderer::with_backend(sender, RenderBackend::Readback).await;
+
+        r.upload_new_image(&DynamicImage::ImageRgba8(image.clone()));
+        r.position_frame(FramePosition::new(FrameSize::new(
+            image.width(),
+            image.height(),
+        )));
+        configure(&mut r);
+
+        let FrameMemory::Cpu(pixels) = r.render_frame().await.memory else {
+            panic!("readback backend returned a dmabuf");
+        };
+        pixels
+    }
+
+    async fn render_chart(parameters: EffectParameters) -> (image::RgbaImage, Vec<u8>) {
+        let chart = test_chart();
+        let pixels = render_image(&chart, |r| r.update_effects(parameters)).await;
+        (chart, pixels)
+    }
+
+    /// Red channel of the pixel at `x`, `y` of a readback.
+    fn red_at(image: &image::RgbaImage, pixels: &[u8], x: u32, y: u32) -> u8 {
+        pixels[((y * image.width() + x) * 4) as usize]
+    }
+
+    /// Compares against golden values from `expected`, which works on linear rgb.
+    fn assert_matches_golden(
+        chart: &image::RgbaImage,
+        pixels: &[u8],
+        expected: impl Fn([f32; 3]) -> [f32; 3],
+    ) {
+        for (idx, (input, output)) in chart.pixels().zip(pixels.chunks_exact(4)).enumerate() {
+            let linear = [0, 1, 2].map(|c| srgb_to_linear(input[c] as f32 / 255.0));
+            let golden = expected(linear).map(|c| (linear_to_srgb(c) * 255.0).round());
+
+            for c in 0..3 {
+                let diff = (output[c] as f32 - golden[c]).abs();
+                assert!(
+                    diff <= 1.0,
+                    "pixel {idx} channel {c}: got {} expected {}",
+                    output[c],
+                    golden[c]
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn golden_linear_round_trip() {
+        let (chart, pixels) = render_chart(EffectParameters::new()).await;
+        assert_matches_golden(&chart, &pixels, |linear| linear);
+    }
+
+    #[tokio::test]
+    async fn golden_desaturate_keeps_bt709_luminance() {
+        let mut parameters = EffectParameters::new();
+        parameters.saturation = 0.0;
+
+        let (chart, pixels) = render_chart(parameters).await;
+        assert_matches_golden(&chart, &pixels, |[r, g, b]| {
+            let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            [luma; 3]
+        });
+    }
+
+    #[tokio::test]
+    async fn golden_exposure_scales_linear_light() {
+        let mut parameters = EffectParameters::new();
+        parameters.exposure = 1.0;
+
+        let (chart, pixels) = render_chart(parameters).await;
+        assert_matches_golden(&chart, &pixels, |linear| linear.map(|c| c * 2.0));
+    }
+
+    #[tokio::test]
+    async fn golden_hsl_saturation_covers_every_band() {
+        let mut parameters = EffectParameters::new();
+        parameters.hsl.saturation = [-1.0; 8];
+
+        // every hue on the chart is fully saturated, so each band desaturates it completely
+        let (chart, pixels) = render_chart(parameters).await;
+        assert_matches_golden(&chart, &pixels, |[r, g, b]| {
+            let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            [luma; 3]
+        });
+    }
+
+    #[tokio::test]
+    async fn golden_levels_work_on_display_values() {
+        let mut parameters = EffectParameters::new();
+        parameters.levels_input_white = 0.5;
+
+        let (chart, pixels) = render_chart(parameters).await;
+        assert_matches_golden(&chart, &pixels, |linear| {
+            linear.map(|c| srgb_to_linear((linear_to_srgb(c) * 2.0).min(1.0)))
+        });
+    }
+
+    fn luminance([r, g, b]: [f32; 3]) -> f32 {
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    #[tokio::test]
+    async fn golden_hue_rotates_around_the_grey_axis() {
+        let mut parameters = EffectParameters::new();
+        parameters.hue = 90.0;
+
+        let (chart, pixels) = render_chart(parameters).await;
+        let (sin, cos) = 90f32.to_radians().sin_cos();
+        let k = (1.0 - cos) / 3.0;
+        let s = sin * (1.0f32 / 3.0).sqrt();
+        assert_matches_golden(&chart, &pixels, |[r, g, b]| {
+            [
+                (cos + k) * r + (k - s) * g + (k + s) * b,
+                (k + s) * r + (cos + k) * g + (k - s) * b,
+                (k - s) * r + (k + s) * g + (cos + k) * b,
+            ]
+            .map(|c| c.max(0.0))
+        });
+
+        // the grey ramp stays grey
+        for x in 0..chart.width() {
+            let idx = (x * 4) as usize;
+            let [r, g, b] = [pixels[idx], pixels[idx + 1], pixels[idx + 2]];
+            assert!(
+                r.abs_diff(g) <= 1 && g.abs_diff(b) <= 1,
+                "grey {x} is {r},{g},{b}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn golden_white_balance_keeps_grey_luminance() {
+        let mut parameters = EffectParameters::new();
+        parameters.temperature = 0.6;
+        parameters.tint = -0.4;
+
+        let gains = [0.6f32 * 0.35, 0.4 * 0.35, -0.6 * 0.35].map(f32::exp2);
+        let (chart, pixels) = render_chart(parameters).await;
+        assert_matches_golden(&chart, &pixels, |linear| {
+            let golden = [0, 1, 2].map(|c| linear[c] * gains[c] / luminance(gains));
+            // greys are tinted without getting brighter or darker
+            if linear[0] == linear[1] && linear[1] == linear[2] {
+                assert!((luminance(golden) - linear[0]).abs() < 1e-5);
+            }
+            golden
+        });
+    }
+
+    #[tokio::test]
+    async fn golden_gamma_bends_linear_light() {
+        let mut parameters = EffectParameters::new();
+        parameters.gamma = 1.8;
+
+        let (chart, pixels) = render_chart(parameters).await;
+        assert_matches_golden(&chart, &pixels, |linear| linear.map(|c| c.powf(1.0 / 1.8)));
+    }
+
+    fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+        let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    /// Moves each pixel by the stops the tonal sliders give its luminance, as the shader does.
+    fn tonal_golden(parameters: EffectParameters) -> impl Fn([f32; 3]) -> [f32; 3] {
+        move |linear| {
+            let y = luminance(linear);
+            if y <= 1e-5 {
+                return linear;
+            }
+
+            let ev = (y / 0.18).log2();
+            let stops = 2.0
+                * (parameters.shadows * (1.0 - smoothstep(-5.0, 0.0, ev))
+                    + parameters.highlights * smoothstep(0.0, 2.5, ev))
+                + 1.5
+                    * (parameters.blacks * (1.0 - smoothstep(-7.0, -3.0, ev))
+                        + parameters.whites * smoothstep(1.5, 2.5, ev));
+            linear.map(|c| c * stops.exp2())
+        }
+    }
+
+    #[tokio::test]
+    async fn golden_highlights_and_shadows() {
+        let mut parameters = EffectParameters::new();
+        parameters.highlights = -0.5;
+        parameters.shadows = 0.5;
+
+        let (chart, pixels) = render_chart(parameters).await;
+        assert_matches_golden(&chart, &pixels, tonal_golden(parameters));
+    }
+
+    #[tokio::test]
+    async fn golden_whites_and_blacks() {
+        let mut parameters = EffectParameters::new();
+        parameters.whites = -0.5;
+        parameters.blacks = 0.5;
+
+        let (chart, pixels) = render_chart(parameters).await;
+        assert_matches_golden(&chart, &pixels, tonal_golden(parameters));
+    }
+
+    #[tokio::test]
+    async fn blur_and_sharpen_keep_flat_frames_flat() {
+        let flat = image::RgbaImage::from_pixel(64, 32, image::Rgba([90, 140, 200, 255]));
+
+        let mut parameters = SpatialParameters::new();
+        parameters.blur_radius = 0.05;
+        parameters.sharpen_amount = 2.0;
+        parameters.sharpen_radius = 3.0;
+
+        // edges are clamped, so a kernel reaching past them must not darken the border
+        let pixels = render_image(&flat, |r| r.update_spatial(parameters)).await;
+        assert_matches_golden(&flat, &pixels, |linear| linear);
+    }
+
+    #[tokio::test]
+    async fn wide_blur_averages_fine_detail() {
+        // two pixel stripes, a 51 pixel radius reaches far past where taps used to skip pixels
+        let stripes = image::RgbaImage::from_fn(16, 1024, |_, y| {
+            let level = if y / 2 % 2 == 0 { 0 } else { 255 };
+            image::Rgba([level, level, level, 255])
+        });
+
+        let mut parameters = SpatialParameters::new();
+        parameters.blur_radius = 0.05;
+        let pixels = render_image(&stripes, |r| r.update_spatial(parameters)).await;
+
+        // half the light, away from the clamped edges
+        let grey = (linear_to_srgb(0.5) * 255.0).round() as u8;
+        for y in 64..960 {
+            let red = red_at(&stripes, &pixels, 8, y);
+            assert!(red.abs_diff(grey) <= 2, "row {y} is {red}, expected {grey}");
+        }
+    }
+
+    #[tokio::test]
+    async fn blur_spreads_an_impulse_evenly() {
+        let mut impulse = image::RgbaImage::from_pixel(64, 64, image::Rgba([0, 0, 0, 255]));
+        impulse.put_pixel(32, 32, image::Rgba([255, 255, 255, 255]));
+
+        let mut parameters = SpatialParameters::new();
+        parameters.blur_radius = 0.05;
+        let pixels = render_image(&impulse, |r| r.update_spatial(parameters)).await;
+
+        let centre = red_at(&impulse, &pixels, 32, 32);
+        assert!(centre > 0 && centre < 255);
+        for offset in 1..3 {
+            let spread = [
+                red_at(&impulse, &pixels, 32 - offset, 32),
+                red_at(&impulse, &pixels, 32 + offset, 32),
+                red_at(&impulse, &pixels, 32, 32 - offset),
+                red_at(&impulse, &pixels, 32, 32 + offset),
+            ];
+            assert!(spread.iter().all(|red| red.abs_diff(spread[0]) <= 1));
+            assert!(spread[0] > 0 && spread[0] < centre);
+        }
+        assert_eq!(red_at(&impulse, &pixels, 32, 40), 0);
+    }
+
+    #[tokio::test]
+    async fn sharpen_overshoots_at_edges_only() {
+        // tall enough that the radius is in pixels of the frame
+        let edge = image::RgbaImage::from_fn(64, 1080, |x, _| {
+            let level = if x < 32 { 64 } else { 192 };
+            image::Rgba([level, level, level, 255])
+        });
+
+        let mut parameters = SpatialParameters::new();
+        parameters.sharpen_amount = 1.0;
+        parameters.sharpen_radius = 1.0;
+        let pixels = render_image(&edge, |r| r.update_spatial(parameters)).await;
+
+        assert!(red_at(&edge, &pixels, 31, 540) < 63);
+        assert!(red_at(&edge, &pixels, 32, 540) > 193);
+        for x in [0, 20, 44, 63] {
+            let expected = edge.get_pixel(x, 540)[0];
+            assert!(red_at(&edge, &pixels, x, 540).abs_diff(expected) <= 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn sharpen_radius_follows_the_frame_height() {
+        let edge = |height| {
+            image::RgbaImage::from_fn(64, height, |x, _| {
+                let level = if x < 32 { 64 } else { 192 };
+                image::Rgba([level, level, level, 255])
+            })
+        };
+
+        let mut parameters = SpatialParameters::new();
+        parameters.sharpen_amount = 1.0;
+        parameters.sharpen_radius = 2.0;
+
+        // half the height is a preview of the same frame, the halo should be half as wide
+        let full = edge(1080);
+        let half = edge(540);
+        let full_pixels = render_image(&full, |r| r.update_spatial(parameters)).await;
+        let half_pixels = render_image(&half, |r| r.update_spatial(parameters)).await;
+
+        let halo_width = |image: &image::RgbaImage, pixels: &[u8]| {
+            (0..32)
+                .filter(|x| red_at(image, pixels, *x, image.height() / 2) < 63)
+                .count()
+        };
+        let full_halo = halo_width(&full, &full_pixels);
+        let half_halo = halo_width(&half, &half_pixels);
+        assert!(full_halo > half_halo, "{full_halo} vs {half_halo} pixels");
+    }
+
+    #[tokio::test]
+    async fn vignette_darkens_corners_evenly() {
+        let grey = image::RgbaImage::from_pixel(64, 32, image::Rgba([160, 160, 160, 255]));
+
+        let mut parameters = SpatialParameters::new();
+        parameters.vignette_amount = -1.0;
+        let pixels = render_image(&grey, |r| r.update_spatial(parameters)).await;
+
+        assert!(red_at(&grey, &pixels, 32, 16).abs_diff(160) <= 1);
+        let corners =
+            [(0, 0), (63, 0), (0, 31), (63, 31)].map(|(x, y)| red_at(&grey, &pixels, x, y));
+        assert!(corners[0] < 120, "corner is {}", corners[0]);
+        assert!(corners.iter().all(|red| red.abs_diff(corners[0]) <= 1));
+    }
+
+    #[tokio::test]
+    async fn skipped_passes_keep_the_chain_intact() {
+        let grey = image::RgbaImage::from_pixel(64, 32, image::Rgba([100, 100, 100, 255]));
+
+        // colour and vignette run, the lut, blur and sharpen between them are skipped
+        let mut effects = EffectParameters::new();
+        effects.exposure = 1.0;
+        let mut spatial = SpatialParameters::new();
+        spatial.vignette_amount = -1.0;
+        let pixels = render_image(&grey, |r| {
+            r.update_effects(effects);
+            r.update_spatial(spatial);
+        })
+        .await;
+
+        let exposed = (linear_to_srgb(srgb_to_linear(100.0 / 255.0) * 2.0) * 255.0).round() as u8;
+        let centre = red_at(&grey, &pixels, 32, 16);
+        assert!(
+            centre.abs_diff(exposed) <= 1,
+            "centre is {centre}, expected {exposed}"
+        );
+        let corner = red_at(&grey, &pixels, 0, 0);
+        assert!(corner < exposed - 20, "corner is {corner}");
+    }
+
+    #[tokio::test]
+    async fn redaction_only_changes_pixels_inside_regions() {
+        let gradient = image::RgbaImage::from_fn(64, 32, |x, y| {
+            image::Rgba([(x * 4) as u8, (y * 8) as u8, 128, 255])
+        });
+
+        // the left half in 8 pixel blocks
+        let bounds = RedactBounds::new(0.0, 0.0, 0.5, 1.0);
+        let mut region = RedactRegion::new(RedactShape::Rectangle, bounds);
+        region.style = RedactStyle::Pixelate;
+        region.strength = 0.25;
+
+        let pixels = render_image(&gradient, |r| r.update_redactions(vec![region])).await;
+        let pixel_at = |x: u32, y: u32| {
+            let idx = ((y * gradient.width() + x) * 4) as usize;
+            &pixels[idx..idx + 3]
+        };
+        let close = |a: &[u8], b: &[u8]| a.iter().zip(b).all(|(a, b)| a.abs_diff(*b) <= 1);
+
+        for (x, y, input) in gradient.enumerate_pixels() {
+            if x < 32 {
+                let block_start = pixel_at(x / 8 * 8, y / 8 * 8);
+                assert!(
+                    close(pixel_at(x, y), block_start),
+                    "pixel {x},{y} not pixelated"
+                );
+            } else {
+                assert!(
+                    close(pixel_at(x, y), &input.0[..3]),
+                    "pixel {x},{y} changed"
+                );
+            }
+        }
+        assert!(!close(pixel_at(0, 0), &gradient.get_pixel(0, 0).0[..3]));
+    }
+
+    #[tokio::test]
+    async fn strong_redaction_hides_fine_detail() {
+        // two pixel stripes under kernels much wider than the old 32 tap limit
+        let stripes = image::RgbaImage::from_fn(16, 1024, |_, y| {
+            let level = if y / 2 % 2 == 0 { 0 } else { 255 };
+            image::Rgba([level, level, level, 255])
+        });
+        let grey = (linear_to_srgb(0.5) * 255.0).round() as u8;
+
+        for style in [RedactStyle::Blur, RedactStyle::Pixelate] {
+            let mut region = RedactRegion::new(
+                RedactShape::Rectangle,
+                RedactBounds::new(0.0, 0.0, 1.0, 1.0),
+            );
+            region.style = style;
+            region.strength = 0.1;
+            let pixels = render_image(&stripes, |r| r.update_redactions(vec![region])).await;
+
+            // away from the clamped edges, a block can hold one stripe more of either level
+            for y in 128..896 {
+                let red = red_at(&stripes, &pixels, 8, y);
+                assert!(red.abs_diff(grey) <= 4, "{style:?} row {y} is {red}");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn crop_applies_to_the_rotated_frame() {
+        let blocks = image::RgbaImage::from_fn(64, 32, |x, y| {
+            image::Rgba([(x / 16 * 64) as u8, (y / 16 * 128) as u8, 0, 255])
+        });
+
+        // turned clockwise to 32x64, then the top 16 rows of that are cropped off
+        let mut position = FramePosition::new(FrameSize::new(64, 32));
+        position.orientation.rotate_90_clockwise();
+        position.crop_edges = [0, 16, 0, 0];
+
+        let pixels = render_image(&blocks, |r| r.position_frame(position)).await;
+        assert_eq!(pixels.len(), 32 * 48 * 4);
+
+        for (x, y) in [(8, 8), (24, 8), (8, 24), (24, 24), (8, 40), (24, 40)] {
+            let idx = ((y * 32 + x) * 4) as usize;
+            let source = blocks.get_pixel(y + 16, 31 - x);
+            let close = pixels[idx..idx + 3]
+                .iter()
+                .zip(&source.0)
+                .all(|(a, b)| a.abs_diff(*b) <= 1);
+            assert!(
+                close,
+                "pixel {x},{y} is {:?}, expected {source:?}",
+                &pixels[idx..idx + 3]
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn redaction_follows_a_cropped_and_scaled_frame() {
+        let gradient = image::RgbaImage::from_fn(128, 64, |x, y| {
+            image::Rgba([(x * 2) as u8, (y * 4) as u8, 128, 255])
+        });
+
+        // 32 source pixels cropped off the left, then halved to 48x32
+        let mut position = FramePosition::new(FrameSize::new(128, 64));
+        position.crop_edges = [32, 0, 0, 0];
+        position.limit_output_height(32);
+
+        // the right half of the uncropped frame starts 16 output pixels in, in 8 pixel blocks
+        let bounds = RedactBounds::new(0.5, 0.0, 0.5, 1.0);
+        let mut region = RedactRegion::new(RedactShape::Rectangle, bounds);
+        region.style = RedactStyle::Pixelate;
+        region.strength = 0.25;
+
+        let plain = render_image(&gradient, |r| r.position_frame(position)).await;
+        let pixels = render_image(&gradient, |r| {
+            r.position_frame(position);
+            r.update_redactions(vec![region]);
+        })
+        .await;
+        assert_eq!(pixels.len(), 48 * 32 * 4);
+
+        let at = |pixels: &[u8], x: u32, y: u32| {
+            let idx = ((y * 48 + x) * 4) as usize;
+            pixels[idx..idx + 3].to_vec()
+        };
+        let close = |a: &[u8], b: &[u8]| a.iter().zip(b).all(|(a, b)| a.abs_diff(*b) <= 1);
+
+        for y in 0..32 {
+            for x in 0..48 {
+                let expected = if x < 16 {
+                    at(&plain, x, y)
+                } else {
+                    at(&pixels, x / 8 * 8, y / 8 * 8)
+                };
+                assert!(close(&at(&pixels, x, y), &expected), "pixel {x},{y}");
+            }
+        }
+        // the block average differs from its corner on the gradient
+        assert!(!close(&at(&pixels, 23, 7), &at(&plain, 23, 7)));
+    }
 }
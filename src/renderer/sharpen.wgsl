@@ -0,0 +1,52 @@
+struct Sharpen {
+    amount: f32,
+    // standard deviation in pixels of a reference_height frame
+    radius: f32,
+}
+
+// the radius scales with the frame, so the preview matches the export
+const reference_height = 1080.0;
+
+@group(0) @binding(0) var input: texture_storage_2d<rgba16float, read>;
+@group(0) @binding(1) var output: texture_storage_2d<rgba16float, write>;
+@group(1) @binding(0) var<uniform> sharpen: Sharpen;
+// the frame blurred horizontally by the sharpen blur pass
+@group(1) @binding(1) var scratch: texture_2d<f32>;
+
+@compute
+@workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let coords = global_invocation_id.xy;
+
+    if all(coords < textureDimensions(output)) {
+        let scale = f32(textureDimensions(output).y) / reference_height;
+        let sigma = max(sharpen.radius * scale, 0.01);
+        let taps = i32(ceil(sigma * 3.0));
+        let max_y = i32(textureDimensions(scratch).y) - 1;
+
+        var total = vec3<f32>(0.0);
+        var weights = 0.0;
+        for (var offset = -taps; offset <= taps; offset++) {
+            let weight = exp(-f32(offset * offset) / (2.0 * sigma * sigma));
+            let y = clamp(i32(coords.y) + offset, 0, max_y);
+
+            total += textureLoad(scratch, vec2<i32>(i32(coords.x), y), 0).rgb * weight;
+            weights += weight;
+        }
+
+        let colour = textureLoad(input, coords);
+        let blurred = total / weights;
+
+        // sharpening luminance only keeps colour noise and fringes from being boosted
+        let luma = luminance(colour.rgb);
+        let detail = luma - luminance(blurred);
+        let sharpened = max(colour.rgb + (sharpen.amount * detail), vec3<f32>(0.0));
+
+        textureStore(output, coords, vec4<f32>(sharpened, colour.a));
+    }
+}
+
+// bt.709 coefficients for linear rgb
+fn luminance(colour: vec3<f32>) -> f32 {
+    return dot(colour, vec3<f32>(0.2126, 0.7152, 0.0722));
+}
@@ -0,0 +1,40 @@
+struct Sharpen {
+    amount: f32,
+    // standard deviation in pixels of a reference_height frame
+    radius: f32,
+}
+
+// the radius scales with the frame, so the preview matches the export
+const reference_height = 1080.0;
+
+@group(0) @binding(0) var input: texture_storage_2d<rgba16float, read>;
+@group(0) @binding(1) var output: texture_storage_2d<rgba16float, write>;
+@group(1) @binding(0) var<uniform> sharpen: Sharpen;
+@group(1) @binding(1) var scratch: texture_storage_2d<rgba16float, write>;
+
+// blurs horizontally into scratch, the frame is passed through for the combine pass
+@compute
+@workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let coords = global_invocation_id.xy;
+
+    if all(coords < textureDimensions(output)) {
+        let scale = f32(textureDimensions(output).y) / reference_height;
+        let sigma = max(sharpen.radius * scale, 0.01);
+        let taps = i32(ceil(sigma * 3.0));
+        let max_x = i32(textureDimensions(input).x) - 1;
+
+        var total = vec4<f32>(0.0);
+        var weights = 0.0;
+        for (var offset = -taps; offset <= taps; offset++) {
+            let weight = exp(-f32(offset * offset) / (2.0 * sigma * sigma));
+            let x = clamp(i32(coords.x) + offset, 0, max_x);
+
+            total += textureLoad(input, vec2<i32>(x, i32(coords.y))) * weight;
+            weights += weight;
+        }
+
+        textureStore(scratch, coords, total / weights);
+        textureStore(output, coords, textureLoad(input, coords));
+    }
+}
@@ -0,0 +1,429 @@
+use crate::range::Range;
+use crate::renderer::frame_position::FrameSize;
+use crate::renderer::pass::{
+    sampled_texture_entry, storage_texture_entry, uniform_entry, EffectPass, INTERMEDIATE_FORMAT,
+};
+use crate::renderer::texture::Texture;
+use encase::internal::WriteInto;
+use encase::{ShaderType, UniformBuffer};
+use wgpu::include_wgsl;
+
+/// Effects that sample a pixel's neighbourhood, run after the colour effects.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpatialParameters {
+    /// Negative darkens the edges, positive lightens them.
+    pub vignette_amount: f32,
+    /// How far out the vignette starts, 0.5 meets the middle of the frame's edges.
+    pub vignette_midpoint: f32,
+    /// 0 follows the frame's shape, 1 is a circle and -1 is close to a rectangle.
+    pub vignette_roundness: f32,
+    pub vignette_feather: f32,
+    pub sharpen_amount: f32,
+    /// Standard deviation of the unsharp mask in pixels of a 1080 line frame, scaled with the
+    /// frame height like the blur.
+    pub sharpen_radius: f32,
+    /// A fraction of the frame height, so the preview matches the export.
+    pub blur_radius: f32,
+}
+
+impl Default for SpatialParameters {
+    fn default() -> Self {
+        Self {
+            vignette_amount: 0f32,
+            vignette_midpoint: 0.5f32,
+            vignette_roundness: 0f32,
+            vignette_feather: 0.5f32,
+            sharpen_amount: 0f32,
+            sharpen_radius: 1f32,
+            blur_radius: 0f32,
+        }
+    }
+}
+
+impl SpatialParameters {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn is_default(&self) -> bool {
+        self == &Default::default()
+    }
+
+    pub fn set_vignette_amount(&mut self, value: f32) {
+        self.vignette_amount = value;
+    }
+
+    pub fn vignette_amount_range() -> (Range, Range) {
+        (Range::new(-1.0, 1.0), Range::new(-100.0, 100.0))
+    }
+
+    pub fn set_vignette_midpoint(&mut self, value: f32) {
+        self.vignette_midpoint = value;
+    }
+
+    pub fn set_vignette_feather(&mut self, value: f32) {
+        self.vignette_feather = value;
+    }
+
+    /// Shared by the midpoint and feather.
+    pub fn vignette_shape_range() -> (Range, Range) {
+        (Range::new(0.0, 1.0), Range::new(0.0, 100.0))
+    }
+
+    pub fn set_vignette_roundness(&mut self, value: f32) {
+        self.vignette_roundness = value;
+    }
+
+    pub fn vignette_roundness_range() -> (Range, Range) {
+        (Range::new(-1.0, 1.0), Range::new(-100.0, 100.0))
+    }
+
+    pub fn set_sharpen_amount(&mut self, value: f32) {
+        self.sharpen_amount = value;
+    }
+
+    pub fn sharpen_amount_range() -> (Range, Range) {
+        (
+            Range::new_with_default(0.0, 2.0, 0.0),
+            Range::new_with_default(0.0, 200.0, 0.0),
+        )
+    }
+
+    pub fn set_sharpen_radius(&mut self, value: f32) {
+        self.sharpen_radius = value;
+    }
+
+    /// Shown in tenths of a pixel at 1080p.
+    pub fn sharpen_radius_range() -> (Range, Range) {
+        (
+            Range::new_with_default(0.5, 3.0, 1.0),
+            Range::new_with_default(5.0, 30.0, 10.0),
+        )
+    }
+
+    pub fn set_blur_radius(&mut self, value: f32) {
+        self.blur_radius = value;
+    }
+
+    /// Up to 5% of the frame height.
+    pub fn blur_radius_range() -> (Range, Range) {
+        (
+            Range::new_with_default(0.0, 0.05, 0.0),
+            Range::new_with_default(0.0, 100.0, 0.0),
+        )
+    }
+}
+
+#[derive(ShaderType)]
+struct VignetteUniform {
+    amount: f32,
+    midpoint: f32,
+    roundness: f32,
+    feather: f32,
+}
+
+/// Darkens or lightens towards the edges of the frame, see `vignette.wgsl`.
+pub(crate) struct VignettePass {
+    amount: f32,
+    buffer: wgpu::Buffer,
+}
+
+impl VignettePass {
+    pub fn new(device: &wgpu::Device) -> Self {
+        Self {
+            amount: 0.0,
+            buffer: create_uniform_buffer(device, "vignette buffer", VignetteUniform::min_size()),
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, parameters: &SpatialParameters) {
+        self.amount = parameters.vignette_amount;
+        write_uniform(
+            queue,
+            &self.buffer,
+            &VignetteUniform {
+                amount: parameters.vignette_amount,
+                midpoint: parameters.vignette_midpoint,
+                roundness: parameters.vignette_roundness,
+                feather: parameters.vignette_feather,
+            },
+        );
+    }
+}
+
+impl EffectPass for VignettePass {
+    fn label(&self) -> &'static str {
+        "vignette pass"
+    }
+
+    fn shader(&self) -> wgpu::ShaderModuleDescriptor<'static> {
+        include_wgsl!("vignette.wgsl")
+    }
+
+    fn layout_entries(&self) -> Vec<wgpu::BindGroupLayoutEntry> {
+        vec![uniform_entry(0)]
+    }
+
+    fn bind_group_entries(&self) -> Vec<wgpu::BindGroupEntry<'_>> {
+        vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: self.buffer.as_entire_binding(),
+        }]
+    }
+
+    fn is_identity(&self) -> bool {
+        self.amount == 0.0
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum BlurDirection {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(ShaderType)]
+struct BlurUniform {
+    direction: mint::Vector2<i32>,
+    radius: f32,
+}
+
+/// One axis of a separable gaussian blur, run once per `BlurDirection`. See `blur.wgsl`.
+pub(crate) struct BlurPass {
+    direction: BlurDirection,
+    radius: f32,
+    buffer: wgpu::Buffer,
+}
+
+impl BlurPass {
+    pub fn new(device: &wgpu::Device, direction: BlurDirection) -> Self {
+        Self {
+            direction,
+            radius: 0.0,
+            buffer: create_uniform_buffer(device, "blur buffer", BlurUniform::min_size()),
+        }
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, radius: f32) {
+        self.radius = radius;
+        write_uniform(
+            queue,
+            &self.buffer,
+            &BlurUniform {
+                direction: direction_vector(self.direction),
+                radius,
+            },
+        );
+    }
+}
+
+impl EffectPass for BlurPass {
+    fn label(&self) -> &'static str {
+        match self.direction {
+            BlurDirection::Horizontal => "horizontal blur pass",
+            BlurDirection::Vertical => "vertical blur pass",
+        }
+    }
+
+    fn shader(&self) -> wgpu::ShaderModuleDescriptor<'static> {
+        include_wgsl!("blur.wgsl")
+    }
+
+    fn layout_entries(&self) -> Vec<wgpu::BindGroupLayoutEntry> {
+        vec![uniform_entry(0)]
+    }
+
+    fn bind_group_entries(&self) -> Vec<wgpu::BindGroupEntry<'_>> {
+        vec![wgpu::BindGroupEntry {
+            binding: 0,
+            resource: self.buffer.as_entire_binding(),
+        }]
+    }
+
+    fn is_identity(&self) -> bool {
+        self.radius <= 0.0
+    }
+}
+
+#[derive(ShaderType)]
+struct SharpenUniform {
+    amount: f32,
+    radius: f32,
+}
+
+/// An unsharp mask in two passes.
+///
+/// The first blurs the frame horizontally into `scratch` and passes the frame through untouched,
+/// the second finishes the blur vertically and pushes the frame away from it.
+pub(crate) struct Sharpen {
+    pub blur: SharpenBlurPass,
+    pub combine: SharpenPass,
+    // only read through the passes' views
+    _scratch: Texture,
+}
+
+pub(crate) struct SharpenBlurPass {
+    active: bool,
+    scratch: wgpu::TextureView,
+    buffer: wgpu::Buffer,
+}
+
+pub(crate) struct SharpenPass {
+    active: bool,
+    scratch: wgpu::TextureView,
+    buffer: wgpu::Buffer,
+}
+
+impl Sharpen {
+    pub fn new(device: &wgpu::Device, size: FrameSize) -> Self {
+        let scratch = Self::create_scratch(device, size);
+
+        Self {
+            blur: SharpenBlurPass {
+                active: false,
+                scratch: scratch.texture.create_view(&Default::default()),
+                buffer: create_uniform_buffer(
+                    device,
+                    "sharpen blur buffer",
+                    SharpenUniform::min_size(),
+                ),
+            },
+            combine: SharpenPass {
+                active: false,
+                scratch: scratch.texture.create_view(&Default::default()),
+                buffer: create_uniform_buffer(device, "sharpen buffer", SharpenUniform::min_size()),
+            },
+            _scratch: scratch,
+        }
+    }
+
+    /// The scratch texture has to match the pass chain's textures.
+    pub fn resize(&mut self, device: &wgpu::Device, size: FrameSize) {
+        let scratch = Self::create_scratch(device, size);
+        self.blur.scratch = scratch.texture.create_view(&Default::default());
+        self.combine.scratch = scratch.texture.create_view(&Default::default());
+        self._scratch = scratch;
+    }
+
+    pub fn update(&mut self, queue: &wgpu::Queue, amount: f32, radius: f32) {
+        self.blur.active = amount > 0.0;
+        self.combine.active = amount > 0.0;
+
+        let uniform = SharpenUniform { amount, radius };
+        write_uniform(queue, &self.blur.buffer, &uniform);
+        write_uniform(queue, &self.combine.buffer, &uniform);
+    }
+
+    fn create_scratch(device: &wgpu::Device, size: FrameSize) -> Texture {
+        Texture::with_format(
+            device,
+            size.into(),
+            INTERMEDIATE_FORMAT,
+            wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+            Some("sharpen scratch"),
+        )
+        .unwrap()
+    }
+}
+
+impl EffectPass for SharpenBlurPass {
+    fn label(&self) -> &'static str {
+        "sharpen blur pass"
+    }
+
+    fn shader(&self) -> wgpu::ShaderModuleDescriptor<'static> {
+        include_wgsl!("sharpen_blur.wgsl")
+    }
+
+    fn layout_entries(&self) -> Vec<wgpu::BindGroupLayoutEntry> {
+        vec![
+            uniform_entry(0),
+            storage_texture_entry(
+                1,
+                wgpu::StorageTextureAccess::WriteOnly,
+                INTERMEDIATE_FORMAT,
+            ),
+        ]
+    }
+
+    fn bind_group_entries(&self) -> Vec<wgpu::BindGroupEntry<'_>> {
+        vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: self.buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&self.scratch),
+            },
+        ]
+    }
+
+    fn is_identity(&self) -> bool {
+        !self.active
+    }
+}
+
+impl EffectPass for SharpenPass {
+    fn label(&self) -> &'static str {
+        "sharpen pass"
+    }
+
+    fn shader(&self) -> wgpu::ShaderModuleDescriptor<'static> {
+        include_wgsl!("sharpen.wgsl")
+    }
+
+    fn layout_entries(&self) -> Vec<wgpu::BindGroupLayoutEntry> {
+        vec![
+            uniform_entry(0),
+            sampled_texture_entry(1, wgpu::TextureViewDimension::D2),
+        ]
+    }
+
+    fn bind_group_entries(&self) -> Vec<wgpu::BindGroupEntry<'_>> {
+        vec![
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: self.buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&self.scratch),
+            },
+        ]
+    }
+
+    fn is_identity(&self) -> bool {
+        !self.active
+    }
+}
+
+pub(crate) fn direction_vector(direction: BlurDirection) -> mint::Vector2<i32> {
+    match direction {
+        BlurDirection::Horizontal => [1, 0].into(),
+        BlurDirection::Vertical => [0, 1].into(),
+    }
+}
+
+pub(crate) fn create_uniform_buffer(
+    device: &wgpu::Device,
+    label: &str,
+    size: std::num::NonZeroU64,
+) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some(label),
+        size: size.get(),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+pub(crate) fn write_uniform(
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+    uniform: &(impl ShaderType + WriteInto),
+) {
+    let mut bytes = UniformBuffer::new(Vec::<u8>::new());
+    bytes.write(uniform).unwrap();
+    queue.write_buffer(buffer, 0, &bytes.into_inner());
+}
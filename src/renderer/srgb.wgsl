@@ -0,0 +1,14 @@
+// srgb transfer functions, prepended to every shader that converts to or from linear light.
+// see `with_srgb` in renderer/pass.rs
+
+fn srgb_to_linear(colour: vec3f) -> vec3f {
+    let low = colour / 12.92;
+    let high = pow((colour + 0.055) / 1.055, vec3f(2.4));
+    return select(high, low, colour <= vec3f(0.04045));
+}
+
+fn linear_to_srgb(colour: vec3f) -> vec3f {
+    let low = colour * 12.92;
+    let high = 1.055 * pow(colour, vec3f(1.0 / 2.4)) - 0.055;
+    return select(high, low, colour <= vec3f(0.0031308));
+}
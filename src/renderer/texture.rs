@@ -12,13 +12,24 @@ impl Texture {
         size: wgpu::Extent3d,
         usage: wgpu::TextureUsages,
         label: Option<&str>,
+    ) -> Result<Self> {
+        Self::with_format(device, size, wgpu::TextureFormat::Rgba8Unorm, usage, label)
+    }
+
+    /// Only 4 byte per pixel formats, writes assume it.
+    pub fn with_format(
+        device: &wgpu::Device,
+        size: wgpu::Extent3d,
+        format: wgpu::TextureFormat,
+        usage: wgpu::TextureUsages,
+        label: Option<&str>,
     ) -> Result<Self> {
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             size,
             mip_level_count: 1,
             sample_count: 1,
             dimension: wgpu::TextureDimension::D2,
-            format: wgpu::TextureFormat::Rgba8Unorm,
+            format,
             usage,
             label,
             view_formats: &[],
@@ -104,18 +104,23 @@ pub enum QuerySet {
     Effects,
 }
 
+/// Times the compute passes, a no op on adapters without timestamp queries.
 pub(crate) struct GpuTimer {
-    pub(crate) query_set: wgpu::QuerySet,
-    pub(crate) resolve_buffer: wgpu::Buffer,
-    pub(crate) result_buffer: wgpu::Buffer,
+    timestamps: Option<TimestampQueries>,
     position_times: RollingAverage,
     effects_times: RollingAverage,
     total_frames_recorded: u32,
     active_query_sets: Vec<QuerySet>,
 }
 
-impl GpuTimer {
-    pub fn new(device: &wgpu::Device) -> Self {
+struct TimestampQueries {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    result_buffer: wgpu::Buffer,
+}
+
+impl TimestampQueries {
+    fn new(device: &wgpu::Device) -> Self {
         let max_query_count = 4;
 
         let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
@@ -142,6 +147,19 @@ impl GpuTimer {
             query_set,
             resolve_buffer,
             result_buffer,
+        }
+    }
+}
+
+impl GpuTimer {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let timestamps = device
+            .features()
+            .contains(wgpu::Features::TIMESTAMP_QUERY)
+            .then(|| TimestampQueries::new(device));
+
+        Self {
+            timestamps,
             position_times: RollingAverage::new(SAMPLES_FOR_AVG),
             effects_times: RollingAverage::new(SAMPLES_FOR_AVG),
             total_frames_recorded: 0,
@@ -154,19 +172,44 @@ impl GpuTimer {
         self.effects_times.clear();
     }
 
+    pub fn encode_resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        let Some(queries) = &self.timestamps else {
+            return;
+        };
+
+        encoder.resolve_query_set(
+            &queries.query_set,
+            0..self.queries(),
+            &queries.resolve_buffer,
+            0,
+        );
+        encoder.copy_buffer_to_buffer(
+            &queries.resolve_buffer,
+            0,
+            &queries.result_buffer,
+            0,
+            queries.result_buffer.size(),
+        );
+    }
+
     pub fn collect_query_results(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let Some(queries) = &self.timestamps else {
+            return;
+        };
+
         self.total_frames_recorded += 1;
-        self.result_buffer
+        queries
+            .result_buffer
             .slice(..)
             .map_async(wgpu::MapMode::Read, |_| ());
         device.poll(wgpu::Maintain::wait()).panic_on_timeout();
 
         let timestamps: Vec<u64> = {
-            let timestamp_view = self.result_buffer.slice(..).get_mapped_range();
+            let timestamp_view = queries.result_buffer.slice(..).get_mapped_range();
             bytemuck::cast_slice(&timestamp_view).to_vec()
         };
 
-        self.result_buffer.unmap();
+        queries.result_buffer.unmap();
 
         let period = queue.get_timestamp_period();
         let elapsed_micro_seconds =
@@ -190,6 +233,10 @@ impl GpuTimer {
     }
 
     pub fn frame_time_msg(&self) -> String {
+        if self.timestamps.is_none() {
+            return "GPU: no timestamp queries".to_string();
+        }
+
         let positioning_time = self.position_times.avg();
         let effects_time = self.effects_times.avg();
         let total_time = positioning_time + effects_time;
@@ -237,9 +284,11 @@ impl GpuTimer {
         &self,
         query_set: QuerySet,
     ) -> Option<wgpu::ComputePassTimestampWrites> {
+        let queries = self.timestamps.as_ref()?;
+
         if let Some(query_start) = self.query_set_start_index(query_set) {
             Some(wgpu::ComputePassTimestampWrites {
-                query_set: &self.query_set,
+                query_set: &queries.query_set,
                 beginning_of_pass_write_index: Some(query_start),
                 end_of_pass_write_index: Some(query_start + 1),
             })
@@ -0,0 +1,52 @@
+struct Vignette {
+    amount: f32,
+    midpoint: f32,
+    roundness: f32,
+    feather: f32,
+}
+
+@group(0) @binding(0) var input: texture_storage_2d<rgba16float, read>;
+@group(0) @binding(1) var output: texture_storage_2d<rgba16float, write>;
+@group(1) @binding(0) var<uniform> vignette: Vignette;
+
+@compute
+@workgroup_size(16, 16, 1)
+fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let coords = global_invocation_id.xy;
+    let size = textureDimensions(output);
+
+    if all(coords < size) {
+        let colour = textureLoad(input, coords);
+        let mask = vignette_mask(coords, vec2<f32>(size));
+
+        var shaded: vec3<f32>;
+        if vignette.amount < 0.0 {
+            shaded = colour.rgb * (1.0 + vignette.amount * mask);
+        } else {
+            shaded = mix(colour.rgb, vec3<f32>(1.0), vignette.amount * mask);
+        }
+
+        textureStore(output, coords, vec4<f32>(shaded, colour.a));
+    }
+}
+
+// 0 inside the vignette, rising to 1 towards the edges
+fn vignette_mask(coords: vec2<u32>, size: vec2<f32>) -> f32 {
+    // -1 to 1 across the frame, an ellipse fitted to the frame at a distance of 1
+    let centred = (vec2<f32>(coords) + 0.5) / size * 2.0 - 1.0;
+
+    // positive roundness stretches the longer axis towards a circle
+    let circle_scale = size / min(size.x, size.y);
+    let scaled = centred * mix(vec2<f32>(1.0), circle_scale, max(vignette.roundness, 0.0));
+    // pow is undefined at zero
+    let position = max(abs(scaled), vec2<f32>(1e-6));
+
+    // negative roundness raises the norm, squaring the shape off towards a rectangle
+    let norm = mix(2.0, 8.0, max(-vignette.roundness, 0.0));
+    let distance = pow(pow(position.x, norm) + pow(position.y, norm), 1.0 / norm);
+
+    let radius = 0.5 + vignette.midpoint;
+    let inner = radius * (1.0 - max(vignette.feather, 0.01));
+
+    return smoothstep(inner, radius, distance);
+}
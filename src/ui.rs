@@ -1,8 +1,18 @@
+use relm4::adw;
 use relm4::gtk::gdk;
 
+mod curves;
 pub mod preview;
 pub(crate) mod sidebar;
 mod slider;
 pub mod video_controls;
 
 pub(crate) static IGNORE_OVERLAY_COLOUR: gdk::RGBA = gdk::RGBA::new(0.0, 0.0, 0.0, 0.7);
+// todo: find better colours
+/// Background behind the custom drawn sliders and curve editors.
+pub(crate) static WIDGET_BACKGROUND_COLOUR: gdk::RGBA = gdk::RGBA::new(0.23, 0.23, 0.23, 1.0);
+
+// todo: get actual accent colour
+pub(crate) fn accent_colour() -> gdk::RGBA {
+    adw::AccentColor::Blue.to_rgba()
+}
@@ -0,0 +1,9 @@
+use relm4::gtk::glib;
+
+mod curve_editor;
+pub mod curves_panel;
+
+glib::wrapper! {
+    pub struct CurveEditor(ObjectSubclass<curve_editor::CurveEditor>)
+        @extends relm4::gtk::Widget;
+}
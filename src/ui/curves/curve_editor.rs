@@ -0,0 +1,253 @@
+use crate::renderer::Curve;
+use crate::ui::{accent_colour, WIDGET_BACKGROUND_COLOUR};
+use gst::glib;
+use gst::subclass::prelude::{ObjectImpl, ObjectSubclass};
+use relm4::gtk::prelude::{SnapshotExt, WidgetExt};
+use relm4::gtk::subclass::prelude::{ObjectSubclassExt, ObjectSubclassIsExt};
+use relm4::gtk::subclass::widget::WidgetImpl;
+use relm4::gtk::{gdk, graphene, gsk, Orientation, Snapshot};
+use std::cell::{Cell, RefCell};
+
+static GRID_COLOUR: gdk::RGBA = gdk::RGBA::new(1.0, 1.0, 1.0, 0.15);
+static HANDLE_RADIUS: f32 = 5.0;
+/// How far from a point a press still grabs it.
+static GRAB_RADIUS: f32 = 12.0;
+/// Line segments drawn along the curve.
+static CURVE_STEPS: u32 = 128;
+
+pub struct CurveEditor {
+    curve: RefCell<Curve>,
+    colour: Cell<gdk::RGBA>,
+    active_point: Cell<Option<usize>>,
+}
+
+impl Default for CurveEditor {
+    fn default() -> Self {
+        Self {
+            curve: RefCell::new(Curve::default()),
+            colour: Cell::new(gdk::RGBA::WHITE),
+            active_point: Cell::new(None),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for CurveEditor {
+    const NAME: &'static str = "CurveEditor";
+    type Type = super::CurveEditor;
+    type ParentType = relm4::gtk::Widget;
+}
+
+impl ObjectImpl for CurveEditor {
+    fn constructed(&self) {}
+}
+
+impl WidgetImpl for CurveEditor {
+    fn measure(&self, _orientation: Orientation, _for_size: i32) -> (i32, i32, i32, i32) {
+        (0, 240, -1, -1)
+    }
+
+    fn snapshot(&self, snapshot: &Snapshot) {
+        let widget = self.obj();
+        snapshot.save();
+
+        let widget_rect =
+            graphene::Rect::new(0f32, 0f32, widget.width() as f32, widget.height() as f32);
+
+        let rounded_size = graphene::Size::new(4.0, 6.0);
+        snapshot.push_rounded_clip(&gsk::RoundedRect::new(
+            widget_rect,
+            rounded_size,
+            rounded_size,
+            rounded_size,
+            rounded_size,
+        ));
+
+        snapshot.append_color(&WIDGET_BACKGROUND_COLOUR, &widget_rect);
+        self.draw_grid(snapshot);
+        self.draw_curve(snapshot);
+        self.draw_handles(snapshot);
+
+        snapshot.pop(); // popping clip
+
+        snapshot.restore();
+    }
+}
+
+impl CurveEditor {
+    fn draw_grid(&self, snapshot: &Snapshot) {
+        let path_builder = gsk::PathBuilder::new();
+
+        for quarter in 1..4 {
+            let (x, y) = self.to_widget((quarter as f32 / 4.0, quarter as f32 / 4.0));
+            let (left, top) = self.to_widget((0.0, 1.0));
+            let (right, bottom) = self.to_widget((1.0, 0.0));
+
+            path_builder.move_to(x, top);
+            path_builder.line_to(x, bottom);
+            path_builder.move_to(left, y);
+            path_builder.line_to(right, y);
+        }
+
+        // the identity curve, for reference
+        let (left, bottom) = self.to_widget((0.0, 0.0));
+        let (right, top) = self.to_widget((1.0, 1.0));
+        path_builder.move_to(left, bottom);
+        path_builder.line_to(right, top);
+
+        let stroke = gsk::Stroke::builder(1.).build();
+        snapshot.append_stroke(&path_builder.to_path(), &stroke, &GRID_COLOUR);
+    }
+
+    fn draw_curve(&self, snapshot: &Snapshot) {
+        let curve = self.curve.borrow();
+        let path_builder = gsk::PathBuilder::new();
+
+        for step in 0..=CURVE_STEPS {
+            let x = step as f32 / CURVE_STEPS as f32;
+            let (widget_x, widget_y) = self.to_widget((x, curve.evaluate(x)));
+
+            if step == 0 {
+                path_builder.move_to(widget_x, widget_y);
+            } else {
+                path_builder.line_to(widget_x, widget_y);
+            }
+        }
+
+        let stroke = gsk::Stroke::builder(2.).build();
+        snapshot.append_stroke(&path_builder.to_path(), &stroke, &self.colour.get());
+    }
+
+    fn draw_handles(&self, snapshot: &Snapshot) {
+        let curve = self.curve.borrow();
+
+        for (idx, &point) in curve.points().iter().enumerate() {
+            let (x, y) = self.to_widget(point);
+            let path_builder = gsk::PathBuilder::new();
+            path_builder.add_circle(&graphene::Point::new(x, y), HANDLE_RADIUS);
+
+            let colour = if self.active_point.get() == Some(idx) {
+                accent_colour()
+            } else {
+                self.colour.get()
+            };
+            snapshot.append_fill(&path_builder.to_path(), gsk::FillRule::Winding, &colour);
+        }
+    }
+
+    /// The curve is inset so handles on the edges aren't clipped.
+    fn to_widget(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let widget = self.obj();
+        let width = widget.width() as f32 - HANDLE_RADIUS * 2.0;
+        let height = widget.height() as f32 - HANDLE_RADIUS * 2.0;
+
+        (
+            HANDLE_RADIUS + x * width,
+            HANDLE_RADIUS + (1.0 - y) * height,
+        )
+    }
+
+    fn from_widget(&self, x: f64, y: f64) -> (f32, f32) {
+        let widget = self.obj();
+        let width = widget.width() as f32 - HANDLE_RADIUS * 2.0;
+        let height = widget.height() as f32 - HANDLE_RADIUS * 2.0;
+
+        (
+            ((x as f32 - HANDLE_RADIUS) / width).clamp(0.0, 1.0),
+            (1.0 - (y as f32 - HANDLE_RADIUS) / height).clamp(0.0, 1.0),
+        )
+    }
+
+    fn point_near(&self, x: f64, y: f64) -> Option<usize> {
+        let curve = self.curve.borrow();
+        curve
+            .points()
+            .iter()
+            .map(|&point| {
+                let (point_x, point_y) = self.to_widget(point);
+                (point_x - x as f32).hypot(point_y - y as f32)
+            })
+            .enumerate()
+            .filter(|(_, distance)| *distance <= GRAB_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(idx, _)| idx)
+    }
+}
+
+impl crate::ui::curves::CurveEditor {
+    pub(crate) fn new() -> Self {
+        glib::Object::builder().build()
+    }
+
+    pub(crate) fn set_curve(&self, curve: Curve) {
+        self.imp().curve.replace(curve);
+        self.imp().active_point.set(None);
+        self.queue_draw();
+    }
+
+    pub(crate) fn curve(&self) -> Curve {
+        self.imp().curve.borrow().clone()
+    }
+
+    pub(crate) fn set_colour(&self, colour: gdk::RGBA) {
+        self.imp().colour.set(colour);
+        self.queue_draw();
+    }
+
+    /// Grabs the point under the press or adds one there, returns if the curve changed.
+    pub(crate) fn drag_begin(&self, x: f64, y: f64) -> bool {
+        let imp = self.imp();
+        if let Some(idx) = imp.point_near(x, y) {
+            imp.active_point.set(Some(idx));
+            self.queue_draw();
+            return false;
+        }
+
+        let point = imp.from_widget(x, y);
+        let inserted = imp.curve.borrow_mut().insert(point);
+        imp.active_point.set(inserted);
+        self.queue_draw();
+        inserted.is_some()
+    }
+
+    /// Moves the grabbed point, returns if the curve changed.
+    pub(crate) fn drag_update(&self, x: f64, y: f64) -> bool {
+        let imp = self.imp();
+        let Some(idx) = imp.active_point.get() else {
+            return false;
+        };
+
+        let point = imp.from_widget(x, y);
+        let mut curve = imp.curve.borrow_mut();
+        let old_point = curve.points()[idx];
+        curve.move_point(idx, point);
+        let changed = curve.points()[idx] != old_point;
+        drop(curve);
+
+        self.queue_draw();
+        changed
+    }
+
+    pub(crate) fn drag_end(&self) {
+        self.imp().active_point.set(None);
+        self.queue_draw();
+    }
+
+    /// Removes the point under the press, returns if the curve changed.
+    pub(crate) fn remove_point_at(&self, x: f64, y: f64) -> bool {
+        let imp = self.imp();
+        let Some(idx) = imp.point_near(x, y) else {
+            return false;
+        };
+
+        let mut curve = imp.curve.borrow_mut();
+        let points = curve.points().len();
+        curve.remove(idx);
+        let changed = curve.points().len() != points;
+        drop(curve);
+
+        imp.active_point.set(None);
+        self.queue_draw();
+        changed
+    }
+}
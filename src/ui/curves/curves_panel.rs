@@ -0,0 +1,193 @@
+use crate::renderer::{CurveChannel, Curves};
+use crate::ui::curves::CurveEditor;
+use relm4::gtk::gdk;
+use relm4::gtk::prelude::{
+    BoxExt, ButtonExt, GestureDragExt, OrientableExt, ToggleButtonExt, WidgetExt,
+};
+use relm4::{gtk, Component, ComponentParts, ComponentSender};
+
+#[derive(Debug)]
+pub struct CurvesPanelModel {
+    curves: Curves,
+    channel: CurveChannel,
+}
+
+#[derive(Debug)]
+pub enum CurvesPanelMsg {
+    SelectChannel(CurveChannel),
+    DragBegin(f64, f64),
+    DragUpdate(f64, f64),
+    DragEnd,
+    RemovePoint(f64, f64),
+    ResetChannel,
+    SilentReset,
+}
+
+#[derive(Debug)]
+pub enum CurvesPanelOutput {
+    CurvesChanged(Curves),
+}
+
+#[relm4::component(pub)]
+impl Component for CurvesPanelModel {
+    type Input = CurvesPanelMsg;
+    type Output = CurvesPanelOutput;
+    type Init = ();
+    type CommandOutput = ();
+
+    view! {
+        gtk::Box {
+            set_hexpand: true,
+            set_orientation: gtk::Orientation::Vertical,
+            set_spacing: 10,
+
+            gtk::Box {
+                set_spacing: 10,
+
+                gtk::Box {
+                    set_hexpand: true,
+                    set_homogeneous: true,
+                    add_css_class: "linked",
+
+                    #[name = "master_toggle"]
+                    gtk::ToggleButton {
+                        set_label: "Master",
+                        set_active: true,
+                        connect_toggled[sender] => move |button| {
+                            if button.is_active() {
+                                sender.input(CurvesPanelMsg::SelectChannel(CurveChannel::Master));
+                            }
+                        },
+                    },
+                    gtk::ToggleButton {
+                        set_label: "Red",
+                        set_group: Some(&master_toggle),
+                        connect_toggled[sender] => move |button| {
+                            if button.is_active() {
+                                sender.input(CurvesPanelMsg::SelectChannel(CurveChannel::Red));
+                            }
+                        },
+                    },
+                    gtk::ToggleButton {
+                        set_label: "Green",
+                        set_group: Some(&master_toggle),
+                        connect_toggled[sender] => move |button| {
+                            if button.is_active() {
+                                sender.input(CurvesPanelMsg::SelectChannel(CurveChannel::Green));
+                            }
+                        },
+                    },
+                    gtk::ToggleButton {
+                        set_label: "Blue",
+                        set_group: Some(&master_toggle),
+                        connect_toggled[sender] => move |button| {
+                            if button.is_active() {
+                                sender.input(CurvesPanelMsg::SelectChannel(CurveChannel::Blue));
+                            }
+                        },
+                    },
+                },
+
+                gtk::Button {
+                    set_icon_name: "edit-undo-symbolic",
+                    set_tooltip_text: Some("Reset Channel"),
+                    add_css_class: "flat",
+                    connect_clicked => CurvesPanelMsg::ResetChannel,
+                },
+            },
+
+            #[name = "editor"]
+            CurveEditor::new() {
+                set_hexpand: true,
+                set_tooltip_text: Some("Drag to add or move points, double click a point to remove it"),
+
+                add_controller = gtk::GestureDrag {
+                    connect_drag_begin[sender] => move |_, x, y| {
+                        sender.input(CurvesPanelMsg::DragBegin(x, y));
+                    },
+                    connect_drag_update[sender] => move |drag, x_offset, y_offset| {
+                        let (start_x, start_y) = drag.start_point().unwrap();
+                        sender.input(CurvesPanelMsg::DragUpdate(start_x + x_offset, start_y + y_offset))
+                    },
+                    connect_drag_end[sender] => move |_, _, _| {
+                        sender.input(CurvesPanelMsg::DragEnd);
+                    },
+                },
+                add_controller = gtk::GestureClick {
+                    connect_released[sender] => move |_, presses, x, y| {
+                        if presses == 2 {
+                            sender.input(CurvesPanelMsg::RemovePoint(x, y));
+                        }
+                    },
+                },
+            },
+        }
+    }
+
+    fn init(
+        _init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = CurvesPanelModel {
+            curves: Curves::default(),
+            channel: CurveChannel::Master,
+        };
+
+        let widgets = view_output!();
+        widgets.editor.set_colour(channel_colour(model.channel));
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update_with_view(
+        &mut self,
+        widgets: &mut Self::Widgets,
+        message: Self::Input,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        let changed = match message {
+            CurvesPanelMsg::SelectChannel(channel) => {
+                self.channel = channel;
+                widgets
+                    .editor
+                    .set_curve(self.curves.channel(channel).clone());
+                widgets.editor.set_colour(channel_colour(channel));
+                false
+            }
+            CurvesPanelMsg::DragBegin(x, y) => widgets.editor.drag_begin(x, y),
+            CurvesPanelMsg::DragUpdate(x, y) => widgets.editor.drag_update(x, y),
+            CurvesPanelMsg::DragEnd => {
+                widgets.editor.drag_end();
+                false
+            }
+            CurvesPanelMsg::RemovePoint(x, y) => widgets.editor.remove_point_at(x, y),
+            CurvesPanelMsg::ResetChannel => {
+                widgets.editor.set_curve(Default::default());
+                true
+            }
+            CurvesPanelMsg::SilentReset => {
+                self.curves = Curves::default();
+                widgets.editor.set_curve(Default::default());
+                false
+            }
+        };
+
+        if changed {
+            *self.curves.channel_mut(self.channel) = widgets.editor.curve();
+            sender
+                .output(CurvesPanelOutput::CurvesChanged(self.curves.clone()))
+                .unwrap();
+        }
+    }
+}
+
+fn channel_colour(channel: CurveChannel) -> gdk::RGBA {
+    match channel {
+        CurveChannel::Master => gdk::RGBA::new(0.9, 0.9, 0.9, 1.0),
+        CurveChannel::Red => gdk::RGBA::new(0.88, 0.27, 0.24, 1.0),
+        CurveChannel::Green => gdk::RGBA::new(0.2, 0.76, 0.42, 1.0),
+        CurveChannel::Blue => gdk::RGBA::new(0.24, 0.52, 0.9, 1.0),
+    }
+}
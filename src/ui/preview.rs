@@ -4,12 +4,14 @@ mod orient;
 mod pan;
 mod preview;
 pub mod preview_frame;
+mod redact;
 mod zoom;
 
 use relm4::gtk::glib;
 
 pub use crate::ui::preview::bounding_box::{BoundingBoxDimensions, CropMode};
 pub use crate::ui::preview::orient::Orientation;
+pub use crate::ui::preview::redact::RedactionEdit;
 
 glib::wrapper! {
     pub struct Preview(ObjectSubclass<preview::Preview>)
@@ -69,9 +69,12 @@ struct RectangleConstraints {
 
 impl Preview {
     pub(crate) fn draw_bounding_box(&self, snapshot: &Snapshot) {
-        let rect = self.bounding_box_rect();
+        self.draw_box(snapshot, &self.bounding_box_rect());
+    }
 
-        let border = gsk::RoundedRect::from_rect(rect, 0.);
+    /// Border and handles, with a grid while dragging. Redaction regions are drawn the same way.
+    pub(crate) fn draw_box(&self, snapshot: &Snapshot, rect: &Rect) {
+        let border = gsk::RoundedRect::from_rect(*rect, 0.);
         let border_widths = [1.; 4];
         let border_colours = [BOX_COLOUR; 4];
 
@@ -83,33 +86,21 @@ impl Preview {
                 DragType::Straighten => 10,
                 _ => 0,
             };
-            self.draw_box_grid(snapshot, &rect, grid_size, grid_size);
+            self.draw_box_grid(snapshot, rect, grid_size, grid_size);
         }
-        self.draw_box_handles(snapshot, &rect);
+        self.draw_box_handles(snapshot, rect);
     }
 
     pub(crate) fn box_handle_drag_begin(&self, x: f32, y: f32) {
         let target_point = graphene::Point::new(x, y);
         let box_rect = self.bounding_box_rect();
 
-        let handle_paths = self.box_handle_paths(&box_rect);
-
         self.active_drag_type.set(DragType::None);
         self.active_handle.set(HandleType::None);
 
-        for (idx, handle_path) in handle_paths.iter().enumerate() {
-            if handle_path.in_fill(&target_point, HANDLE_FILL_RULE) {
-                let handle = match idx {
-                    0 => HandleType::TopLeft,
-                    1 => HandleType::BottomLeft,
-                    2 => HandleType::TopRight,
-                    3 => HandleType::BottomRight,
-                    _ => panic!("too many handle indicies"),
-                };
-                self.active_handle.set(handle);
-                self.active_drag_type.set(DragType::Handle);
-                break;
-            }
+        if let Some(handle) = self.handle_at(&box_rect, &target_point) {
+            self.active_handle.set(handle);
+            self.active_drag_type.set(DragType::Handle);
         }
 
         if self.active_drag_type.get().is_none() && box_rect.contains_point(&target_point) {
@@ -117,6 +108,21 @@ impl Preview {
         }
     }
 
+    pub(crate) fn handle_at(&self, rect: &Rect, point: &Point) -> Option<HandleType> {
+        let handle_paths = self.box_handle_paths(rect);
+
+        handle_paths
+            .iter()
+            .position(|handle_path| handle_path.in_fill(point, HANDLE_FILL_RULE))
+            .map(|idx| match idx {
+                0 => HandleType::TopLeft,
+                1 => HandleType::BottomLeft,
+                2 => HandleType::TopRight,
+                3 => HandleType::BottomRight,
+                _ => panic!("too many handle indicies"),
+            })
+    }
+
     pub(crate) fn bounding_box_rect(&self) -> Rect {
         let preview = self.preview_rect();
         let width = preview.width() * (self.right_x.get() - self.left_x.get());
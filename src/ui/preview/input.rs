@@ -49,7 +49,11 @@ impl Preview {
                 obj.imp()
                     .prev_drag
                     .set(graphene::Point::new(x as f32, y as f32));
-                obj.imp().box_handle_drag_begin(x as f32, y as f32);
+                if obj.imp().show_redactions.get() {
+                    obj.imp().redaction_drag_begin(x as f32, y as f32);
+                } else {
+                    obj.imp().box_handle_drag_begin(x as f32, y as f32);
+                }
             }
         ));
 
@@ -79,6 +83,9 @@ impl Preview {
                         _ => {}
                     }
 
+                    obj.queue_draw();
+                } else if preview.show_redactions.get() {
+                    preview.update_redaction(offset_as_point);
                     obj.queue_draw();
                 } else if preview.zoom.get() != 1f64 {
                     preview.pan_preview(offset_from_prev_x, offset_from_prev_y);
@@ -97,7 +104,10 @@ impl Preview {
             move |_, _, _| {
                 let preview = obj.imp();
 
-                if preview.active_drag_type.get().is_handle() {
+                if preview.show_redactions.get() {
+                    preview.active_handle.set(HandleType::None);
+                    obj.queue_draw();
+                } else if preview.active_drag_type.get().is_handle() {
                     preview.is_cropped.set(
                         preview.right_x.get() != 1.0
                             || preview.left_x.get() != 0.0
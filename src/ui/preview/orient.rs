@@ -1,21 +1,26 @@
-#[derive(Debug, Clone, Copy)]
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, Serialize)]
 pub struct Orientation {
     pub(crate) base_angle: f32,
+    /// Mirrored by the source's orientation tag, before the base rotation.
+    pub(crate) base_mirrored: bool,
     pub(crate) angle: f32,
     pub(crate) mirrored: bool,
 }
 
 impl Orientation {
-    pub fn new_with_base(base_angle: f32) -> Self {
+    pub fn new_with_base(base_angle: f32, base_mirrored: bool) -> Self {
         Self {
             base_angle,
+            base_mirrored,
             angle: 0.0f32,
-            mirrored: false,
+            mirrored: base_mirrored,
         }
     }
 
     pub fn reset(&mut self) {
-        self.mirrored = false;
+        self.mirrored = self.base_mirrored;
         self.angle = 0f32;
     }
 
@@ -43,6 +48,24 @@ impl Orientation {
         }
     }
 
+    /// Moves left, top, right and bottom edges of the stored frame to where they end up once
+    /// it's mirrored and rotated.
+    pub fn orient_edges(&self, edges: [u32; 4]) -> [u32; 4] {
+        let [left, top, right, bottom] = edges;
+        let mut edges = if self.mirrored {
+            [right, top, left, bottom]
+        } else {
+            edges
+        };
+
+        for _ in 0..(self.absolute_angle() as u32 / 90) % 4 {
+            let [left, top, right, bottom] = edges;
+            // clockwise, the bottom edge becomes the left one
+            edges = [bottom, left, top, right];
+        }
+        edges
+    }
+
     pub fn rotate_90_clockwise(&mut self) {
         self.angle = (self.angle + 90.0) % 360.0;
     }
@@ -77,6 +100,7 @@ impl Default for Orientation {
     fn default() -> Self {
         Self {
             base_angle: 0.0,
+            base_mirrored: false,
             angle: 0.0,
             mirrored: false,
         }
@@ -1,11 +1,13 @@
 use crate::geometry::Rectangle;
+use crate::renderer::RedactRegion;
 use crate::ui::preview::bounding_box::{HandleType, BOX_HANDLE_WIDTH};
 use crate::ui::preview::input::DragType;
-use crate::ui::preview::{BoundingBoxDimensions, CropMode};
+use crate::ui::preview::{BoundingBoxDimensions, CropMode, RedactionEdit};
 use crate::ui::sidebar::CropExportSettings;
 use gst::glib;
 use gst::subclass::prelude::ObjectSubclassIsExt;
 use gst::subclass::prelude::{ObjectImpl, ObjectSubclass};
+use gst::ClockTime;
 use relm4::gtk::graphene::Point;
 use relm4::gtk::prelude::TextureExt;
 use relm4::gtk::prelude::{PaintableExt, SnapshotExt, WidgetExt};
@@ -35,6 +37,12 @@ pub struct Preview {
     pub(crate) is_cropped: Cell<bool>,
     pub(crate) is_new_drag: Cell<bool>,
     pub(crate) original_aspect_ratio: Cell<f32>,
+    pub(crate) show_redactions: Cell<bool>,
+    pub(crate) redactions: RefCell<Vec<RedactRegion>>,
+    pub(crate) selected_redaction: Cell<Option<usize>>,
+    /// Timestamp of the frame in `texture`, tracked regions are drawn where they are at it.
+    pub(crate) frame_time: Cell<Option<ClockTime>>,
+    pub(crate) redaction_edited: RefCell<Option<Box<dyn Fn(RedactionEdit)>>>,
 }
 
 impl Default for Preview {
@@ -57,6 +65,11 @@ impl Default for Preview {
             is_cropped: Cell::new(false),
             is_new_drag: Cell::new(true),
             original_aspect_ratio: Cell::new(1.77f32),
+            show_redactions: Cell::new(false),
+            redactions: RefCell::new(Vec::new()),
+            selected_redaction: Cell::new(None),
+            frame_time: Cell::new(None),
+            redaction_edited: RefCell::new(None),
         }
     }
 }
@@ -122,6 +135,10 @@ impl WidgetImpl for Preview {
 
             texture.snapshot(snapshot, preview.width() as f64, preview.height() as f64);
 
+            if self.show_redactions.get() {
+                self.draw_redactions(snapshot, preview.width(), preview.height());
+            }
+
             if self.is_straightened() {
                 snapshot.restore();
             }
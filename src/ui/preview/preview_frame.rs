@@ -1,6 +1,7 @@
-use crate::renderer::{TimerCmd, TimerEvent};
-use crate::ui::preview::{CropMode, Preview};
+use crate::renderer::{RedactRegion, TimerCmd, TimerEvent};
+use crate::ui::preview::{CropMode, Preview, RedactionEdit};
 use crate::ui::sidebar::CropExportSettings;
+use gst::ClockTime;
 use relm4::gtk::gdk;
 use relm4::gtk::prelude::WidgetExt;
 use relm4::*;
@@ -18,7 +19,7 @@ pub struct PreviewFrameModel {
 #[derive(Debug)]
 pub enum PreviewFrameMsg {
     VideoLoaded,
-    FrameRendered(gdk::Texture),
+    FrameRendered(gdk::Texture, Option<ClockTime>),
     StraightenStart,
     Straighten(f64),
     StraightenEnd,
@@ -28,11 +29,15 @@ pub enum PreviewFrameMsg {
     Zoom(f64),
     ZoomHide,
     ZoomShow,
+    Redactions(Vec<RedactRegion>, Option<usize>),
+    RedactionsShow,
+    RedactionsHide,
 }
 
 #[derive(Debug)]
 pub enum PreviewFrameOutput {
     TogglePlayPause,
+    RedactionEdited(RedactionEdit),
 }
 
 #[derive(Debug)]
@@ -67,6 +72,12 @@ impl Component for PreviewFrameModel {
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
         let preview = Preview::new();
+        let edit_sender = sender.clone();
+        preview.connect_redaction_edited(move |edit| {
+            edit_sender
+                .output(PreviewFrameOutput::RedactionEdited(edit))
+                .unwrap()
+        });
 
         let offload = relm4::gtk::GraphicsOffload::new(Some(&preview));
         offload.set_enabled(gtk::GraphicsOffloadEnabled::Enabled);
@@ -100,7 +111,8 @@ impl Component for PreviewFrameModel {
                 self.is_playing = true;
                 root.last_child().unwrap().set_visible(true);
             }
-            PreviewFrameMsg::FrameRendered(texture) => {
+            PreviewFrameMsg::FrameRendered(texture, pts) => {
+                self.preview.set_frame_time(pts);
                 self.preview.update_texture(texture);
                 let now = Instant::now();
                 self.timer_sender
@@ -121,6 +133,11 @@ impl Component for PreviewFrameModel {
             PreviewFrameMsg::Zoom(level) => self.preview.set_zoom(level),
             PreviewFrameMsg::ZoomHide => self.preview.hide_zoom(),
             PreviewFrameMsg::ZoomShow => self.preview.show_zoom(),
+            PreviewFrameMsg::Redactions(regions, selected) => {
+                self.preview.set_redactions(regions, selected)
+            }
+            PreviewFrameMsg::RedactionsShow => self.preview.show_redactions(),
+            PreviewFrameMsg::RedactionsHide => self.preview.hide_redactions(),
         }
 
         self.update_view(widgets, sender);
@@ -0,0 +1,214 @@
+use crate::renderer::{RedactBounds, RedactRegion, RedactShape};
+use crate::ui::preview::bounding_box::HandleType;
+use crate::ui::preview::input::DragType;
+use crate::ui::preview::preview::Preview;
+use gst::subclass::prelude::ObjectSubclassExt;
+use gst::ClockTime;
+use relm4::gtk::graphene::{Point, Rect, Size};
+use relm4::gtk::prelude::{SnapshotExt, WidgetExt};
+use relm4::gtk::subclass::prelude::ObjectSubclassIsExt;
+use relm4::gtk::{gdk, gsk, Snapshot};
+
+/// Smallest a region can be resized to, as a fraction of the frame.
+static MIN_REGION_SIZE: f32 = 0.02;
+static REGION_COLOUR: gdk::RGBA = gdk::RGBA::new(1.0, 1.0, 1.0, 0.6);
+
+#[derive(Debug, Clone, Copy)]
+pub enum RedactionEdit {
+    Selected(usize),
+    /// `time` is the frame the region was moved on, tracked regions get a keyframe there.
+    Moved {
+        idx: usize,
+        time: Option<ClockTime>,
+        bounds: RedactBounds,
+    },
+}
+
+impl Preview {
+    /// Drawn inside the texture's transform, regions sit on the unrotated frame.
+    pub(crate) fn draw_redactions(&self, snapshot: &Snapshot, width: f32, height: f32) {
+        let time = self.frame_time.get();
+        let stroke = gsk::Stroke::builder(1.).build();
+
+        for (idx, region) in self.redactions.borrow().iter().enumerate() {
+            let rect = region_rect(region.bounds_at(time), width, height);
+
+            if Some(idx) == self.selected_redaction.get() {
+                self.draw_box(snapshot, &rect);
+            }
+
+            let path_builder = gsk::PathBuilder::new();
+            match region.shape {
+                RedactShape::Rectangle => path_builder.add_rect(&rect),
+                RedactShape::Ellipse => {
+                    let radius = Size::new(rect.width() / 2., rect.height() / 2.);
+                    let ellipse = gsk::RoundedRect::new(rect, radius, radius, radius, radius);
+                    path_builder.add_rounded_rect(&ellipse);
+                }
+            }
+            snapshot.append_stroke(&path_builder.to_path(), &stroke, &REGION_COLOUR);
+        }
+    }
+
+    pub(crate) fn redaction_drag_begin(&self, x: f32, y: f32) {
+        self.active_drag_type.set(DragType::None);
+        self.active_handle.set(HandleType::None);
+
+        let point = self.to_frame_point(x, y);
+        let preview = self.display_preview_rect();
+        let time = self.frame_time.get();
+        let rect_of = |region: &RedactRegion| {
+            region_rect(region.bounds_at(time), preview.width(), preview.height())
+        };
+
+        let regions = self.redactions.borrow();
+
+        // handles stick out of the region, so the selected one gets first pick
+        let selected = self.selected_redaction.get();
+        if let Some(region) = selected.and_then(|idx| regions.get(idx)) {
+            if let Some(handle) = self.handle_at(&rect_of(region), &point) {
+                self.active_handle.set(handle);
+                self.active_drag_type.set(DragType::Handle);
+                return;
+            }
+        }
+
+        // later regions are drawn over earlier ones
+        let Some(idx) = regions
+            .iter()
+            .rposition(|region| rect_of(region).contains_point(&point))
+        else {
+            return;
+        };
+        drop(regions);
+
+        self.active_drag_type.set(DragType::BoxTranslate);
+        if selected != Some(idx) {
+            self.selected_redaction.set(Some(idx));
+            self.emit_redaction_edit(RedactionEdit::Selected(idx));
+            self.obj().queue_draw();
+        }
+    }
+
+    pub(crate) fn update_redaction(&self, offset: Point) {
+        let Some(idx) = self.selected_redaction.get() else {
+            return;
+        };
+
+        let (x, y) = self.unrotate(offset.x(), offset.y());
+        let (x, y) = self.size_as_percent(x, y);
+        let time = self.frame_time.get();
+
+        let mut regions = self.redactions.borrow_mut();
+        let Some(region) = regions.get_mut(idx) else {
+            return;
+        };
+
+        let bounds = region.bounds_at(time);
+        let bounds = match self.active_drag_type.get() {
+            DragType::Handle => resize_bounds(bounds, self.active_handle.get(), x, y),
+            DragType::BoxTranslate => translate_bounds(bounds, x, y),
+            _ => return,
+        };
+        region.set_bounds(time, bounds);
+        drop(regions);
+
+        self.emit_redaction_edit(RedactionEdit::Moved { idx, time, bounds });
+    }
+
+    /// Widget coordinates to pixels from the top left of the unrotated frame.
+    fn to_frame_point(&self, x: f32, y: f32) -> Point {
+        let preview = self.display_preview_rect();
+        let (center_x, center_y) = (preview.width() / 2., preview.height() / 2.);
+
+        let (x, y) = self.unrotate(x - preview.x() - center_x, y - preview.y() - center_y);
+        Point::new(x + center_x, y + center_y)
+    }
+
+    fn unrotate(&self, x: f32, y: f32) -> (f32, f32) {
+        let (sin, cos) = (-self.straighten_angle.get() as f32).to_radians().sin_cos();
+        (x * cos - y * sin, x * sin + y * cos)
+    }
+
+    fn emit_redaction_edit(&self, edit: RedactionEdit) {
+        if let Some(callback) = self.redaction_edited.borrow().as_ref() {
+            callback(edit);
+        }
+    }
+}
+
+impl crate::ui::preview::Preview {
+    pub fn show_redactions(&self) {
+        self.imp().show_redactions.set(true);
+        self.queue_draw();
+    }
+
+    pub fn hide_redactions(&self) {
+        self.imp().show_redactions.set(false);
+        self.queue_draw();
+    }
+
+    pub fn set_redactions(&self, regions: Vec<RedactRegion>, selected: Option<usize>) {
+        // the preview already has the region being dragged, and is ahead of the sidebar
+        if self.imp().active_drag_type.get().is_active() {
+            return;
+        }
+
+        self.imp().redactions.replace(regions);
+        self.imp().selected_redaction.set(selected);
+        self.queue_draw();
+    }
+
+    pub fn set_frame_time(&self, time: Option<ClockTime>) {
+        self.imp().frame_time.set(time);
+    }
+
+    pub fn connect_redaction_edited(&self, callback: impl Fn(RedactionEdit) + 'static) {
+        self.imp()
+            .redaction_edited
+            .replace(Some(Box::new(callback)));
+    }
+}
+
+fn region_rect(bounds: RedactBounds, width: f32, height: f32) -> Rect {
+    Rect::new(
+        bounds.x * width,
+        bounds.y * height,
+        bounds.width * width,
+        bounds.height * height,
+    )
+}
+
+fn translate_bounds(bounds: RedactBounds, x: f32, y: f32) -> RedactBounds {
+    RedactBounds {
+        x: (bounds.x + x).clamp(0.0, 1.0 - bounds.width),
+        y: (bounds.y + y).clamp(0.0, 1.0 - bounds.height),
+        ..bounds
+    }
+}
+
+fn resize_bounds(bounds: RedactBounds, handle: HandleType, x: f32, y: f32) -> RedactBounds {
+    let (mut left, mut top) = (bounds.x, bounds.y);
+    let (mut right, mut bottom) = (bounds.x + bounds.width, bounds.y + bounds.height);
+
+    match handle {
+        HandleType::TopLeft | HandleType::BottomLeft => {
+            left = (left + x).clamp(0.0, right - MIN_REGION_SIZE)
+        }
+        HandleType::TopRight | HandleType::BottomRight => {
+            right = (right + x).clamp(left + MIN_REGION_SIZE, 1.0)
+        }
+        HandleType::None => {}
+    }
+    match handle {
+        HandleType::TopLeft | HandleType::TopRight => {
+            top = (top + y).clamp(0.0, bottom - MIN_REGION_SIZE)
+        }
+        HandleType::BottomLeft | HandleType::BottomRight => {
+            bottom = (bottom + y).clamp(top + MIN_REGION_SIZE, 1.0)
+        }
+        HandleType::None => {}
+    }
+
+    RedactBounds::new(left, top, right - left, bottom - top)
+}
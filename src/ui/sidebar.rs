@@ -1,10 +1,17 @@
-use crate::renderer::EffectParameters;
+use crate::renderer::{
+    Curves, EffectParameters, LutSettings, RedactRegion, SpatialParameters, ToneMapping,
+};
 use crate::ui::preview::BoundingBoxDimensions;
-use crate::video::metadata::{AudioCodec, ContainerFormat, VideoCodec};
+use crate::video::export::validate_video_encoding;
+use crate::video::metadata::{
+    AudioCodec, BitDepth, ChromaSubsampling, ContainerFormat, VideoCodec, VideoContainerInfo,
+    AUDIO_BITRATE_DEFAULT,
+};
 
 mod adjust;
 mod crop;
 mod output;
+mod redact;
 pub(crate) mod sidebar;
 
 // fixme: too similar to videoContainerInfo
@@ -17,14 +24,78 @@ pub struct OutputContainerSettings {
     pub(crate) container: ContainerFormat,
     pub(crate) video_codec: VideoCodec,
     pub(crate) video_bitrate: u32,
+    pub(crate) video_profile: Option<&'static str>,
+    pub(crate) video_level: Option<&'static str>,
+    pub(crate) chroma_subsampling: ChromaSubsampling,
+    pub(crate) bit_depth: BitDepth,
+    pub(crate) max_height: Option<u32>,
+    /// Bytes, when set the video bitrate is derived from it at export.
+    pub(crate) target_size: Option<u64>,
+    /// Exports stored pixels with the source's pixel aspect ratio instead of resampling to square.
+    pub(crate) keep_anamorphic: bool,
+    /// Only used for HDR sources.
+    pub(crate) tone_mapping: ToneMapping,
+}
+
+impl OutputContainerSettings {
+    /// Re-encodes with the source's codecs, bitrates and bit depth.
+    pub fn from_container_info(info: &VideoContainerInfo) -> Self {
+        let first_audio_stream = info.audio_streams.first();
+
+        let mut settings = Self {
+            no_audio: false,
+            audio_stream_idx: 0,
+            video_bitrate: info.video_bitrate,
+            video_codec: info.video_codec.encodable(),
+            video_profile: None,
+            video_level: None,
+            chroma_subsampling: ChromaSubsampling::YUV420,
+            bit_depth: BitDepth::from_bits(info.video_bit_depth),
+            container: info.container,
+            max_height: None,
+            target_size: None,
+            keep_anamorphic: false,
+            tone_mapping: ToneMapping::default(),
+            audio_bitrate: first_audio_stream
+                .map_or(AUDIO_BITRATE_DEFAULT, |stream| stream.bitrate),
+            audio_codec: first_audio_stream
+                .map_or(AudioCodec::NoAudio, |stream| stream.codec.encodable()),
+        };
+
+        // not every encoder build takes 10 bit input, 8 bit beats failing the export
+        if settings.bit_depth != BitDepth::Eight && validate_video_encoding(&settings).is_err() {
+            settings.bit_depth = BitDepth::Eight;
+        }
+        settings
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MetadataExportSettings {
+    pub(crate) strip_location: bool,
+    pub(crate) title: Option<String>,
+    pub(crate) comment: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SubtitleExportSettings {
+    pub(crate) passthrough_streams: Vec<u32>,
+    pub(crate) burn_in_stream: Option<u32>,
 }
 
 pub struct CropExportSettings {
     pub(crate) bounding_box: BoundingBoxDimensions,
 }
 
+#[derive(Debug, Clone)]
 pub struct ControlsExportSettings {
     pub container: OutputContainerSettings,
     pub container_is_default: bool,
     pub effect_parameters: EffectParameters,
+    pub curves: Curves,
+    pub spatial: SpatialParameters,
+    pub lut: Option<LutSettings>,
+    pub redactions: Vec<RedactRegion>,
+    pub metadata: MetadataExportSettings,
+    pub subtitles: SubtitleExportSettings,
 }
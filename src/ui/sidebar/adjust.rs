@@ -1,26 +1,103 @@
-use crate::renderer::EffectParameters;
+use crate::range::Range;
+use crate::renderer::{Curves, EffectParameters, HslBand, Lut, LutSettings, SpatialParameters};
+use crate::ui::curves::curves_panel::{CurvesPanelModel, CurvesPanelMsg, CurvesPanelOutput};
 use crate::ui::sidebar::adjust::AdjustPageMsg::{
-    BrightnessChange, ContrastChange, SaturationChange, NOP,
+    BlacksChange, BlurRadiusChange, BrightnessChange, ContrastChange, ExposureChange, GammaChange,
+    HighlightsChange, HslHueChange, HslLuminanceChange, HslSaturationChange, HueChange,
+    LevelsGammaChange, LevelsInputBlackChange, LevelsInputWhiteChange, LevelsOutputBlackChange,
+    LevelsOutputWhiteChange, LutIntensityChange, SaturationChange, ShadowsChange,
+    SharpenAmountChange, SharpenRadiusChange, TemperatureChange, TintChange, VignetteAmountChange,
+    VignetteFeatherChange, VignetteMidpointChange, VignetteRoundnessChange, WhitesChange, NOP,
+};
+use crate::ui::slider::adjust_row::{AdjustRowInit, AdjustRowModel, AdjustRowMsg, AdjustRowOutput};
+use crate::ui::slider::slider::SliderFillMode;
+use relm4::adw::prelude::{ActionRowExt, ComboRowExt, PreferencesGroupExt, PreferencesRowExt};
+use relm4::gtk::gio;
+use relm4::gtk::prelude::{
+    BoxExt, ButtonExt, FileExt, GtkApplicationExt, OrientableExt, WidgetExt,
 };
-use crate::ui::slider::adjust_row::{AdjustRowModel, AdjustRowMsg, AdjustRowOutput};
-use relm4::adw::prelude::PreferencesRowExt;
-use relm4::gtk::prelude::{BoxExt, OrientableExt, WidgetExt};
 use relm4::{
-    adw, gtk, ComponentController, ComponentParts, ComponentSender, Controller, SimpleComponent,
+    adw, gtk, Component, ComponentController, ComponentParts, ComponentSender, Controller,
+    SimpleComponent,
 };
+use std::path::PathBuf;
+use std::sync::Arc;
 
 pub struct AdjustPageModel {
     parameters: EffectParameters,
+    exposure_slider: Controller<AdjustRowModel>,
+    gamma_slider: Controller<AdjustRowModel>,
+    highlights_slider: Controller<AdjustRowModel>,
+    shadows_slider: Controller<AdjustRowModel>,
+    whites_slider: Controller<AdjustRowModel>,
+    blacks_slider: Controller<AdjustRowModel>,
     contrast_slider: Controller<AdjustRowModel>,
     brigtness_slider: Controller<AdjustRowModel>,
     saturation_slider: Controller<AdjustRowModel>,
+    hue_slider: Controller<AdjustRowModel>,
+    temperature_slider: Controller<AdjustRowModel>,
+    tint_slider: Controller<AdjustRowModel>,
+    hsl_band: HslBand,
+    hsl_hue_slider: Controller<AdjustRowModel>,
+    hsl_saturation_slider: Controller<AdjustRowModel>,
+    hsl_luminance_slider: Controller<AdjustRowModel>,
+    levels_input_black_slider: Controller<AdjustRowModel>,
+    levels_input_white_slider: Controller<AdjustRowModel>,
+    levels_gamma_slider: Controller<AdjustRowModel>,
+    levels_output_black_slider: Controller<AdjustRowModel>,
+    levels_output_white_slider: Controller<AdjustRowModel>,
+    curves: Curves,
+    curves_panel: Controller<CurvesPanelModel>,
+    lut: Option<Arc<Lut>>,
+    lut_path: Option<PathBuf>,
+    lut_error: Option<String>,
+    lut_intensity: f32,
+    lut_intensity_slider: Controller<AdjustRowModel>,
+    spatial: SpatialParameters,
+    vignette_amount_slider: Controller<AdjustRowModel>,
+    vignette_midpoint_slider: Controller<AdjustRowModel>,
+    vignette_roundness_slider: Controller<AdjustRowModel>,
+    vignette_feather_slider: Controller<AdjustRowModel>,
+    sharpen_amount_slider: Controller<AdjustRowModel>,
+    sharpen_radius_slider: Controller<AdjustRowModel>,
+    blur_radius_slider: Controller<AdjustRowModel>,
 }
 
 #[derive(Debug)]
 pub enum AdjustPageMsg {
+    ExposureChange(f64),
+    GammaChange(f64),
+    HighlightsChange(f64),
+    ShadowsChange(f64),
+    WhitesChange(f64),
+    BlacksChange(f64),
     ContrastChange(f64),
     BrightnessChange(f64),
     SaturationChange(f64),
+    HueChange(f64),
+    TemperatureChange(f64),
+    TintChange(f64),
+    HslBandSelected(HslBand),
+    HslHueChange(f64),
+    HslSaturationChange(f64),
+    HslLuminanceChange(f64),
+    LevelsInputBlackChange(f64),
+    LevelsInputWhiteChange(f64),
+    LevelsGammaChange(f64),
+    LevelsOutputBlackChange(f64),
+    LevelsOutputWhiteChange(f64),
+    CurvesChange(Curves),
+    OpenLut,
+    LutSelected(PathBuf),
+    ClearLut,
+    LutIntensityChange(f64),
+    VignetteAmountChange(f64),
+    VignetteMidpointChange(f64),
+    VignetteRoundnessChange(f64),
+    VignetteFeatherChange(f64),
+    SharpenAmountChange(f64),
+    SharpenRadiusChange(f64),
+    BlurRadiusChange(f64),
     Reset,
     // fixme: should be some way to disacrd msg's without pointing to a "nop" msg that does nothing
     NOP,
@@ -29,6 +106,9 @@ pub enum AdjustPageMsg {
 #[derive(Debug)]
 pub enum AdjustPageOutput {
     EffectUpdate(EffectParameters),
+    CurvesUpdate(Curves),
+    LutUpdate(Option<Arc<Lut>>, f32),
+    SpatialUpdate(SpatialParameters),
 }
 
 #[relm4::component(pub)]
@@ -40,6 +120,23 @@ impl SimpleComponent for AdjustPageModel {
     view! {
         adw::PreferencesPage {
             set_hexpand: true,
+            adw::PreferencesGroup {
+                set_title: "Light",
+
+                gtk::Box {
+                    set_hexpand: true,
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 10,
+
+                    model.exposure_slider.widget(){},
+                    model.gamma_slider.widget(){},
+                    model.highlights_slider.widget(){},
+                    model.shadows_slider.widget(){},
+                    model.whites_slider.widget(){},
+                    model.blacks_slider.widget(){},
+                },
+            },
+
             adw::PreferencesGroup {
                 gtk::Box {
                     set_hexpand: true,
@@ -52,6 +149,136 @@ impl SimpleComponent for AdjustPageModel {
                 },
             },
 
+            adw::PreferencesGroup {
+                set_title: "Colour Balance",
+
+                gtk::Box {
+                    set_hexpand: true,
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 10,
+
+                    model.hue_slider.widget(){},
+                    model.temperature_slider.widget(){},
+                    model.tint_slider.widget(){},
+                },
+            },
+
+            adw::PreferencesGroup {
+                set_title: "Colour Mixer",
+
+                adw::ComboRow {
+                    set_title: "Colour",
+                    #[wrap(Some)]
+                    set_model = &gtk::StringList::new(&HslBand::ALL.map(|band| band.label())),
+                    set_selected: 0,
+
+                    connect_selected_item_notify[sender] => move |row| {
+                        let band = HslBand::ALL[row.selected() as usize];
+                        sender.input(AdjustPageMsg::HslBandSelected(band));
+                    },
+                },
+
+                gtk::Box {
+                    set_hexpand: true,
+                    set_margin_top: 10,
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 10,
+
+                    model.hsl_hue_slider.widget(){},
+                    model.hsl_saturation_slider.widget(){},
+                    model.hsl_luminance_slider.widget(){},
+                },
+            },
+
+            adw::PreferencesGroup {
+                set_title: "Levels",
+
+                gtk::Box {
+                    set_hexpand: true,
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 10,
+
+                    model.levels_input_black_slider.widget(){},
+                    model.levels_input_white_slider.widget(){},
+                    model.levels_gamma_slider.widget(){},
+                    model.levels_output_black_slider.widget(){},
+                    model.levels_output_white_slider.widget(){},
+                },
+            },
+
+            adw::PreferencesGroup {
+                set_title: "Curves",
+
+                model.curves_panel.widget(){},
+            },
+
+            adw::PreferencesGroup {
+                set_title: "Look",
+
+                adw::ActionRow {
+                    set_title: "LUT",
+                    #[watch]
+                    set_subtitle: &model.lut_subtitle(),
+
+                    add_suffix = &gtk::Button {
+                        set_icon_name: "document-open-symbolic",
+                        set_tooltip_text: Some("Open .cube LUT"),
+                        set_valign: gtk::Align::Center,
+                        add_css_class: "flat",
+
+                        connect_clicked => AdjustPageMsg::OpenLut,
+                    },
+
+                    add_suffix = &gtk::Button {
+                        set_icon_name: "edit-clear-symbolic",
+                        set_tooltip_text: Some("Remove LUT"),
+                        set_valign: gtk::Align::Center,
+                        add_css_class: "flat",
+                        #[watch]
+                        set_visible: model.lut.is_some(),
+
+                        connect_clicked => AdjustPageMsg::ClearLut,
+                    },
+                },
+
+                gtk::Box {
+                    set_hexpand: true,
+                    set_margin_top: 10,
+                    set_orientation: gtk::Orientation::Vertical,
+
+                    model.lut_intensity_slider.widget(){},
+                },
+            },
+
+            adw::PreferencesGroup {
+                set_title: "Vignette",
+
+                gtk::Box {
+                    set_hexpand: true,
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 10,
+
+                    model.vignette_amount_slider.widget(){},
+                    model.vignette_midpoint_slider.widget(){},
+                    model.vignette_roundness_slider.widget(){},
+                    model.vignette_feather_slider.widget(){},
+                },
+            },
+
+            adw::PreferencesGroup {
+                set_title: "Detail",
+
+                gtk::Box {
+                    set_hexpand: true,
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_spacing: 10,
+
+                    model.sharpen_amount_slider.widget(){},
+                    model.sharpen_radius_slider.widget(){},
+                    model.blur_radius_slider.widget(){},
+                },
+            },
+
             adw::PreferencesGroup {
                 set_valign: gtk::Align::End,
                 set_vexpand: true,
@@ -71,34 +298,156 @@ impl SimpleComponent for AdjustPageModel {
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
-        let contrast_slider = AdjustRowModel::build_slider(
-            "Contrast",
-            EffectParameters::contrast_range(),
-        )
-        .forward(sender.input_sender(), |msg| match msg {
-            AdjustRowOutput::ValueChanged(val) => ContrastChange(val),
-            _ => NOP,
-        });
-
-        let brigtness_slider =
-            AdjustRowModel::build_slider("Brightness", EffectParameters::brigntess_range())
-                .forward(sender.input_sender(), |msg| match msg {
-                    AdjustRowOutput::ValueChanged(val) => BrightnessChange(val),
-                    _ => NOP,
-                });
-
-        let saturation_slider =
-            AdjustRowModel::build_slider("Saturation", EffectParameters::saturation_range())
-                .forward(sender.input_sender(), |msg| match msg {
-                    AdjustRowOutput::ValueChanged(val) => SaturationChange(val),
-                    _ => NOP,
-                });
+        let tonal_range = EffectParameters::tonal_range();
+        let hsl_range = EffectParameters::hsl_range();
+        let vignette_shape_range = SpatialParameters::vignette_shape_range();
 
         let model = AdjustPageModel {
             parameters: EffectParameters::new(),
-            contrast_slider,
-            brigtness_slider,
-            saturation_slider,
+            exposure_slider: build_slider(
+                "Exposure",
+                EffectParameters::exposure_range(),
+                &sender,
+                ExposureChange,
+            ),
+            gamma_slider: build_slider(
+                "Gamma",
+                EffectParameters::gamma_range(),
+                &sender,
+                GammaChange,
+            ),
+            highlights_slider: build_slider("Highlights", tonal_range, &sender, HighlightsChange),
+            shadows_slider: build_slider("Shadows", tonal_range, &sender, ShadowsChange),
+            whites_slider: build_slider("Whites", tonal_range, &sender, WhitesChange),
+            blacks_slider: build_slider("Blacks", tonal_range, &sender, BlacksChange),
+            contrast_slider: build_slider(
+                "Contrast",
+                EffectParameters::contrast_range(),
+                &sender,
+                ContrastChange,
+            ),
+            brigtness_slider: build_slider(
+                "Brightness",
+                EffectParameters::brigntess_range(),
+                &sender,
+                BrightnessChange,
+            ),
+            saturation_slider: build_slider(
+                "Saturation",
+                EffectParameters::saturation_range(),
+                &sender,
+                SaturationChange,
+            ),
+            hue_slider: build_slider("Hue", EffectParameters::hue_range(), &sender, HueChange),
+            temperature_slider: build_slider(
+                "Temperature",
+                EffectParameters::temperature_range(),
+                &sender,
+                TemperatureChange,
+            ),
+            tint_slider: build_slider("Tint", EffectParameters::tint_range(), &sender, TintChange),
+            hsl_band: HslBand::Red,
+            hsl_hue_slider: build_slider("Hue", hsl_range, &sender, HslHueChange),
+            hsl_saturation_slider: build_slider(
+                "Saturation",
+                hsl_range,
+                &sender,
+                HslSaturationChange,
+            ),
+            hsl_luminance_slider: build_slider("Luminance", hsl_range, &sender, HslLuminanceChange),
+            levels_input_black_slider: build_edge_slider(
+                "Input Black",
+                EffectParameters::levels_black_range(),
+                &sender,
+                LevelsInputBlackChange,
+            ),
+            levels_input_white_slider: build_edge_slider(
+                "Input White",
+                EffectParameters::levels_white_range(),
+                &sender,
+                LevelsInputWhiteChange,
+            ),
+            levels_gamma_slider: build_slider(
+                "Gamma",
+                EffectParameters::levels_gamma_range(),
+                &sender,
+                LevelsGammaChange,
+            ),
+            levels_output_black_slider: build_edge_slider(
+                "Output Black",
+                EffectParameters::levels_black_range(),
+                &sender,
+                LevelsOutputBlackChange,
+            ),
+            levels_output_white_slider: build_edge_slider(
+                "Output White",
+                EffectParameters::levels_white_range(),
+                &sender,
+                LevelsOutputWhiteChange,
+            ),
+            curves: Curves::default(),
+            curves_panel: CurvesPanelModel::builder().launch(()).forward(
+                sender.input_sender(),
+                |msg| match msg {
+                    CurvesPanelOutput::CurvesChanged(curves) => AdjustPageMsg::CurvesChange(curves),
+                },
+            ),
+            lut: None,
+            lut_path: None,
+            lut_error: None,
+            lut_intensity: 1.0,
+            lut_intensity_slider: build_edge_slider(
+                "Intensity",
+                (
+                    Range::new_with_default(0.0, 1.0, 1.0),
+                    Range::new_with_default(0.0, 100.0, 100.0),
+                ),
+                &sender,
+                LutIntensityChange,
+            ),
+            spatial: SpatialParameters::new(),
+            vignette_amount_slider: build_slider(
+                "Amount",
+                SpatialParameters::vignette_amount_range(),
+                &sender,
+                VignetteAmountChange,
+            ),
+            vignette_midpoint_slider: build_edge_slider(
+                "Midpoint",
+                vignette_shape_range,
+                &sender,
+                VignetteMidpointChange,
+            ),
+            vignette_roundness_slider: build_slider(
+                "Roundness",
+                SpatialParameters::vignette_roundness_range(),
+                &sender,
+                VignetteRoundnessChange,
+            ),
+            vignette_feather_slider: build_edge_slider(
+                "Feather",
+                vignette_shape_range,
+                &sender,
+                VignetteFeatherChange,
+            ),
+            sharpen_amount_slider: build_edge_slider(
+                "Sharpen",
+                SpatialParameters::sharpen_amount_range(),
+                &sender,
+                SharpenAmountChange,
+            ),
+            sharpen_radius_slider: build_edge_slider(
+                "Sharpen Radius",
+                SpatialParameters::sharpen_radius_range(),
+                &sender,
+                SharpenRadiusChange,
+            ),
+            blur_radius_slider: build_edge_slider(
+                "Blur",
+                SpatialParameters::blur_radius_range(),
+                &sender,
+                BlurRadiusChange,
+            ),
         };
 
         let widgets = view_output!();
@@ -108,35 +457,138 @@ impl SimpleComponent for AdjustPageModel {
 
     fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
         match message {
-            ContrastChange(level) => {
-                self.parameters.set_contrast(level as f32);
-                sender
-                    .output(AdjustPageOutput::EffectUpdate(self.parameters))
-                    .unwrap()
+            ExposureChange(stops) => self.parameters.set_exposure(stops as f32),
+            GammaChange(level) => self.parameters.set_gamma(level as f32),
+            HighlightsChange(level) => self.parameters.set_highlights(level as f32),
+            ShadowsChange(level) => self.parameters.set_shadows(level as f32),
+            WhitesChange(level) => self.parameters.set_whites(level as f32),
+            BlacksChange(level) => self.parameters.set_blacks(level as f32),
+            ContrastChange(level) => self.parameters.set_contrast(level as f32),
+            BrightnessChange(level) => self.parameters.set_brightness(level as f32),
+            SaturationChange(level) => self.parameters.set_saturation(level as f32),
+            HueChange(degrees) => self.parameters.set_hue(degrees as f32),
+            TemperatureChange(level) => self.parameters.set_temperature(level as f32),
+            TintChange(level) => self.parameters.set_tint(level as f32),
+            AdjustPageMsg::HslBandSelected(band) => {
+                self.hsl_band = band;
+                let hsl = &self.parameters.hsl;
+                let idx = band as usize;
+                self.hsl_hue_slider
+                    .emit(AdjustRowMsg::SilentSet(hsl.hue[idx] as f64));
+                self.hsl_saturation_slider
+                    .emit(AdjustRowMsg::SilentSet(hsl.saturation[idx] as f64));
+                self.hsl_luminance_slider
+                    .emit(AdjustRowMsg::SilentSet(hsl.luminance[idx] as f64));
+                return;
             }
-            BrightnessChange(level) => {
-                self.parameters.set_brightness(level as f32);
+            HslHueChange(level) => self.parameters.set_hsl_hue(self.hsl_band, level as f32),
+            HslSaturationChange(level) => self
+                .parameters
+                .set_hsl_saturation(self.hsl_band, level as f32),
+            HslLuminanceChange(level) => self
+                .parameters
+                .set_hsl_luminance(self.hsl_band, level as f32),
+            LevelsInputBlackChange(level) => self.parameters.set_levels_input_black(level as f32),
+            LevelsInputWhiteChange(level) => self.parameters.set_levels_input_white(level as f32),
+            LevelsGammaChange(level) => self.parameters.set_levels_gamma(level as f32),
+            LevelsOutputBlackChange(level) => self.parameters.set_levels_output_black(level as f32),
+            LevelsOutputWhiteChange(level) => self.parameters.set_levels_output_white(level as f32),
+            AdjustPageMsg::CurvesChange(curves) => {
+                self.curves = curves;
                 sender
-                    .output(AdjustPageOutput::EffectUpdate(self.parameters))
-                    .unwrap()
+                    .output(AdjustPageOutput::CurvesUpdate(self.curves.clone()))
+                    .unwrap();
+                return;
             }
-            SaturationChange(level) => {
-                self.parameters.set_saturation(level as f32);
-                sender
-                    .output(AdjustPageOutput::EffectUpdate(self.parameters))
-                    .unwrap()
+            AdjustPageMsg::OpenLut => {
+                Self::launch_lut_opener(&sender);
+                return;
+            }
+            AdjustPageMsg::LutSelected(path) => {
+                match Lut::load(&path) {
+                    Ok(lut) => {
+                        self.lut = Some(Arc::new(lut));
+                        self.lut_path = Some(path);
+                        self.lut_error = None;
+                    }
+                    Err(e) => {
+                        tracing::warn!("failed to load lut {}: {e:?}", path.display());
+                        self.lut_error = Some(e.to_string());
+                    }
+                }
+                self.send_lut_update(&sender);
+                return;
+            }
+            AdjustPageMsg::ClearLut => {
+                self.clear_lut();
+                self.send_lut_update(&sender);
+                return;
+            }
+            LutIntensityChange(intensity) => {
+                self.lut_intensity = intensity as f32;
+                self.send_lut_update(&sender);
+                return;
+            }
+            VignetteAmountChange(amount) => {
+                self.spatial.set_vignette_amount(amount as f32);
+                self.send_spatial_update(&sender);
+                return;
+            }
+            VignetteMidpointChange(midpoint) => {
+                self.spatial.set_vignette_midpoint(midpoint as f32);
+                self.send_spatial_update(&sender);
+                return;
+            }
+            VignetteRoundnessChange(roundness) => {
+                self.spatial.set_vignette_roundness(roundness as f32);
+                self.send_spatial_update(&sender);
+                return;
+            }
+            VignetteFeatherChange(feather) => {
+                self.spatial.set_vignette_feather(feather as f32);
+                self.send_spatial_update(&sender);
+                return;
+            }
+            SharpenAmountChange(amount) => {
+                self.spatial.set_sharpen_amount(amount as f32);
+                self.send_spatial_update(&sender);
+                return;
+            }
+            SharpenRadiusChange(radius) => {
+                self.spatial.set_sharpen_radius(radius as f32);
+                self.send_spatial_update(&sender);
+                return;
+            }
+            BlurRadiusChange(radius) => {
+                self.spatial.set_blur_radius(radius as f32);
+                self.send_spatial_update(&sender);
+                return;
             }
             AdjustPageMsg::Reset => {
                 self.parameters.reset();
-                self.saturation_slider.emit(AdjustRowMsg::SilentReset);
-                self.contrast_slider.emit(AdjustRowMsg::SilentReset);
-                self.brigtness_slider.emit(AdjustRowMsg::SilentReset);
+                for slider in self.sliders() {
+                    slider.emit(AdjustRowMsg::SilentReset);
+                }
+                self.curves = Curves::default();
+                self.curves_panel.emit(CurvesPanelMsg::SilentReset);
                 sender
-                    .output(AdjustPageOutput::EffectUpdate(self.parameters))
-                    .unwrap()
+                    .output(AdjustPageOutput::CurvesUpdate(self.curves.clone()))
+                    .unwrap();
+
+                self.clear_lut();
+                self.lut_intensity = 1.0;
+                self.lut_intensity_slider.emit(AdjustRowMsg::SilentReset);
+                self.send_lut_update(&sender);
+
+                self.spatial = SpatialParameters::new();
+                self.send_spatial_update(&sender);
             }
-            NOP => {}
+            NOP => return,
         }
+
+        sender
+            .output(AdjustPageOutput::EffectUpdate(self.parameters))
+            .unwrap()
     }
 }
 
@@ -144,4 +596,148 @@ impl AdjustPageModel {
     pub fn export_settings(&self) -> EffectParameters {
         self.parameters.clone()
     }
+
+    pub fn curves_settings(&self) -> Curves {
+        self.curves.clone()
+    }
+
+    pub fn lut_settings(&self) -> Option<LutSettings> {
+        self.lut_path.as_ref().map(|path| LutSettings {
+            path: path.clone(),
+            intensity: self.lut_intensity,
+        })
+    }
+
+    pub fn spatial_settings(&self) -> SpatialParameters {
+        self.spatial
+    }
+
+    fn lut_subtitle(&self) -> String {
+        if let Some(error) = &self.lut_error {
+            return error.clone();
+        }
+
+        match &self.lut_path {
+            Some(path) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            None => "None".to_string(),
+        }
+    }
+
+    fn clear_lut(&mut self) {
+        self.lut = None;
+        self.lut_path = None;
+        self.lut_error = None;
+    }
+
+    fn send_lut_update(&self, sender: &ComponentSender<Self>) {
+        sender
+            .output(AdjustPageOutput::LutUpdate(
+                self.lut.clone(),
+                self.lut_intensity,
+            ))
+            .unwrap()
+    }
+
+    fn send_spatial_update(&self, sender: &ComponentSender<Self>) {
+        sender
+            .output(AdjustPageOutput::SpatialUpdate(self.spatial))
+            .unwrap()
+    }
+
+    fn launch_lut_opener(sender: &ComponentSender<Self>) {
+        let filters = gio::ListStore::new::<gtk::FileFilter>();
+
+        let cube_filter = gtk::FileFilter::new();
+        cube_filter.add_suffix("cube");
+        cube_filter.set_name(Some("Cube LUT"));
+        filters.append(&cube_filter);
+
+        let file_dialog = gtk::FileDialog::builder()
+            .title("Open LUT")
+            .accept_label("Open")
+            .modal(true)
+            .filters(&filters)
+            .build();
+
+        let cancelable = gio::Cancellable::new();
+        let window = relm4::main_adw_application().active_window().unwrap();
+
+        let sender = sender.clone();
+        file_dialog.open(Some(&window), Some(&cancelable), move |result| {
+            let Some(path) = result.ok().and_then(|file| file.path()) else {
+                return;
+            };
+            sender.input(AdjustPageMsg::LutSelected(path));
+        });
+    }
+
+    fn sliders(&self) -> [&Controller<AdjustRowModel>; 27] {
+        [
+            &self.exposure_slider,
+            &self.gamma_slider,
+            &self.highlights_slider,
+            &self.shadows_slider,
+            &self.whites_slider,
+            &self.blacks_slider,
+            &self.contrast_slider,
+            &self.brigtness_slider,
+            &self.saturation_slider,
+            &self.hue_slider,
+            &self.temperature_slider,
+            &self.tint_slider,
+            &self.hsl_hue_slider,
+            &self.hsl_saturation_slider,
+            &self.hsl_luminance_slider,
+            &self.levels_input_black_slider,
+            &self.levels_input_white_slider,
+            &self.levels_gamma_slider,
+            &self.levels_output_black_slider,
+            &self.levels_output_white_slider,
+            &self.vignette_amount_slider,
+            &self.vignette_midpoint_slider,
+            &self.vignette_roundness_slider,
+            &self.vignette_feather_slider,
+            &self.sharpen_amount_slider,
+            &self.sharpen_radius_slider,
+            &self.blur_radius_slider,
+        ]
+    }
+}
+
+fn build_slider(
+    label: &str,
+    ranges: (Range, Range),
+    sender: &ComponentSender<AdjustPageModel>,
+    on_change: fn(f64) -> AdjustPageMsg,
+) -> Controller<AdjustRowModel> {
+    AdjustRowModel::build_slider(label, ranges).forward(sender.input_sender(), move |msg| match msg
+    {
+        AdjustRowOutput::ValueChanged(val) => on_change(val),
+        _ => NOP,
+    })
+}
+
+/// For values that start from one end, like black and white points.
+fn build_edge_slider(
+    label: &str,
+    (value_range, display_range): (Range, Range),
+    sender: &ComponentSender<AdjustPageModel>,
+    on_change: fn(f64) -> AdjustPageMsg,
+) -> Controller<AdjustRowModel> {
+    AdjustRowModel::builder()
+        .launch(AdjustRowInit::new(
+            label,
+            true,
+            true,
+            value_range,
+            display_range,
+            SliderFillMode::EdgeToEdge,
+        ))
+        .forward(sender.input_sender(), move |msg| match msg {
+            AdjustRowOutput::ValueChanged(val) => on_change(val),
+            _ => NOP,
+        })
 }
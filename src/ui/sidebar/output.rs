@@ -1,35 +1,89 @@
-use relm4::adw::prelude::{ActionRowExt, ComboRowExt, PreferencesGroupExt, PreferencesRowExt};
-use relm4::gtk::prelude::{ListBoxRowExt, WidgetExt};
-use relm4::{adw, Component, ComponentParts, ComponentSender};
+use relm4::adw::prelude::{
+    ActionRowExt, ComboRowExt, EntryRowExt, PreferencesGroupExt, PreferencesRowExt,
+};
+use relm4::gtk::prelude::{EditableExt, ListBoxRowExt, WidgetExt};
+use relm4::{adw, gtk, Component, ComponentParts, ComponentSender};
 
+use crate::renderer::ToneMapping;
 use crate::ui::sidebar::output::OutputPageMsg::{
-    AudioCodecChange, AudioStreamChange, ContainerChange, CustomEncoding, VideoCodecChange,
+    AudioCodecChange, AudioStreamChange, BitDepthChange, ChromaChange, CommentChange,
+    ContainerChange, CustomEncoding, KeepAnamorphic, PresetChange, ResolutionChange, SavePreset,
+    StripLocation, SubtitleBurnIn, SubtitlePassthrough, TargetSizeChange, TitleChange,
+    ToneMappingChange, VideoBitrateChange, VideoCodecChange, VideoLevelChange, VideoProfileChange,
+    VideoStreamChange,
 };
-use crate::ui::sidebar::OutputContainerSettings;
+use crate::ui::sidebar::{MetadataExportSettings, OutputContainerSettings, SubtitleExportSettings};
+use crate::video::export::{validate_container, validate_video_encoding};
 use crate::video::metadata::{
-    AudioCodec, ContainerFormat, VideoCodec, VideoContainerInfo, AUDIO_BITRATE_DEFAULT,
+    AudioCodec, BitDepth, ChromaSubsampling, ContainerFormat, VideoCodec, VideoContainerInfo,
+    VideoInfo,
 };
+use crate::video::preset::{load_presets, ExportPreset, RateControl};
+use crate::video::selectable::SelectableOption;
+
+// output heights offered for downscaling, None keeps the source height
+const OUTPUT_HEIGHTS: [Option<u32>; 6] = [
+    None,
+    Some(2160),
+    Some(1440),
+    Some(1080),
+    Some(720),
+    Some(480),
+];
 
 pub struct OutputPageModel {
     video_info: VideoContainerInfo,
+    is_anamorphic: bool,
+    keep_anamorphic: bool,
+    is_hdr: bool,
+    tone_mapping: ToneMapping,
     export_settings: OutputContainerSettings,
     selected_audio_stream_idx: u32,
+    video_stream_idx: u32,
     custom_encoding: bool,
+    encoding_error: Option<String>,
+    metadata: MetadataExportSettings,
+    subtitles: SubtitleExportSettings,
+    subtitle_rows: Vec<adw::SwitchRow>,
+    presets: Vec<ExportPreset>,
+    rate_control: RateControl,
+    video_kbps: u32,
+    target_megabytes: f32,
 }
 
 #[derive(Debug)]
 pub enum OutputPageMsg {
-    VideoInfo(VideoContainerInfo),
+    VideoInfo(VideoInfo),
     CustomEncoding(bool),
+    KeepAnamorphic(bool),
+    ToneMappingChange(ToneMapping),
+    VideoStreamChange(u32),
     VideoCodecChange(VideoCodec),
+    VideoProfileChange(u32),
+    VideoLevelChange(u32),
+    ChromaChange(ChromaSubsampling),
+    BitDepthChange(BitDepth),
     AudioCodecChange(AudioCodec),
     AudioStreamChange(u32),
     ContainerChange(ContainerFormat),
+    StripLocation(bool),
+    TitleChange(String),
+    CommentChange(String),
+    SubtitlePassthrough(u32, bool),
+    SubtitleBurnIn(u32),
+    PresetChange(u32),
+    SavePreset(String),
+    ResolutionChange(u32),
+    VideoBitrateChange(u32),
+    TargetSizeChange(f32),
 }
 
 #[derive(Debug)]
 pub enum OutputPageOutput {
     ExportFrame,
+    PreviewSubtitles(Option<u32>),
+    ToneMapping(ToneMapping),
+    VideoStream(u32),
 }
 
 #[relm4::component(pub)]
@@ -44,6 +98,17 @@ impl Component for OutputPageModel {
             set_hexpand: true,
 
             adw::PreferencesGroup {
+                #[name= "preset_row"]
+                adw::ComboRow{
+                    set_title: "Preset",
+                    #[wrap(Some)]
+                    set_model = &presets_string_list(&model.presets),
+                    connect_selected_item_notify [sender] => move |dropdown| {
+                        sender.input(PresetChange(dropdown.selected()))
+                    }
+                },
+
+                #[name= "custom_encoding_row"]
                 adw::SwitchRow {
                     set_title: "Custom encoding",
                     set_subtitle: "will lose lossless enconding",
@@ -76,6 +141,8 @@ impl Component for OutputPageModel {
                 #[name= "video_codec_row"]
                 adw::ComboRow{
                     set_title: "Codec",
+                    #[watch]
+                    set_subtitle: model.encoding_error.as_deref().unwrap_or(""),
                     #[wrap(Some)]
                     set_model = &VideoCodec::string_list(),
                     connect_selected_item_notify [sender] => move |dropdown| {
@@ -83,6 +150,118 @@ impl Component for OutputPageModel {
                         sender.input(VideoCodecChange(codec));
                     }
                 },
+
+                #[name= "video_profile_row"]
+                adw::ComboRow{
+                    set_title: "Profile",
+                    set_visible: false,
+                    connect_selected_item_notify [sender] => move |dropdown| {
+                        sender.input(VideoProfileChange(dropdown.selected()))
+                    }
+                },
+
+                #[name= "video_level_row"]
+                adw::ComboRow{
+                    set_title: "Level",
+                    set_visible: false,
+                    connect_selected_item_notify [sender] => move |dropdown| {
+                        sender.input(VideoLevelChange(dropdown.selected()))
+                    }
+                },
+
+                #[name= "resolution_row"]
+                adw::ComboRow{
+                    set_title: "Resolution",
+                    #[wrap(Some)]
+                    set_model = &resolutions_string_list(),
+                    connect_selected_item_notify [sender] => move |dropdown| {
+                        sender.input(ResolutionChange(dropdown.selected()))
+                    }
+                },
+
+                adw::SwitchRow {
+                    set_title: "Keep anamorphic pixels",
+                    set_subtitle: "Flag the pixel aspect ratio instead of resampling to square pixels",
+                    #[watch]
+                    set_visible: model.is_anamorphic,
+                    connect_active_notify[sender] => move |row| {
+                        sender.input(KeepAnamorphic(row.is_active()))
+                    },
+                },
+
+                #[name= "video_bitrate_row"]
+                adw::SpinRow::with_range(0.0, 200_000.0, 500.0) {
+                    set_title: "Bitrate (kbit/s)",
+                    set_subtitle: "0 keeps the source bitrate",
+                    #[watch]
+                    set_sensitive: model.target_megabytes == 0.0,
+                    connect_value_notify [sender] => move |row| {
+                        sender.input(VideoBitrateChange(row.value() as u32))
+                    }
+                },
+
+                #[name= "target_size_row"]
+                adw::SpinRow::with_range(0.0, 10_000.0, 1.0) {
+                    set_title: "Target size (MB)",
+                    set_subtitle: "Fit the export under this size, 0 turns it off",
+                    set_digits: 1,
+                    connect_value_notify [sender] => move |row| {
+                        sender.input(TargetSizeChange(row.value() as f32))
+                    }
+                },
+
+                #[name= "chroma_row"]
+                adw::ComboRow{
+                    set_title: "Chroma Subsampling",
+                    #[wrap(Some)]
+                    set_model = &ChromaSubsampling::string_list(),
+                    connect_selected_item_notify [sender] => move |dropdown| {
+                        let chroma = ChromaSubsampling::from_string_list_index(dropdown.selected());
+                        sender.input(ChromaChange(chroma));
+                    }
+                },
+
+                #[name= "bit_depth_row"]
+                adw::ComboRow{
+                    set_title: "Bit Depth",
+                    #[wrap(Some)]
+                    set_model = &BitDepth::string_list(),
+                    connect_selected_item_notify [sender] => move |dropdown| {
+                        let depth = BitDepth::from_string_list_index(dropdown.selected());
+                        sender.input(BitDepthChange(depth));
+                    }
+                },
+            },
+
+            // these also change the preview, so not tied to custom encoding
+            adw::PreferencesGroup {
+                #[watch]
+                set_visible: model.is_hdr || model.video_info.video_streams.len() >= 2,
+
+                #[name= "video_stream_row"]
+                adw::ComboRow{
+                    set_title: "Video stream",
+                    #[watch]
+                    set_visible: model.video_info.video_streams.len() >= 2,
+                    connect_selected_item_notify [sender] => move |dropdown| {
+                        sender.input(VideoStreamChange(dropdown.selected()))
+                    }
+                },
+
+                #[name= "tone_mapping_row"]
+                adw::ComboRow {
+                    set_title: "HDR tone mapping",
+                    #[watch]
+                    set_visible: model.is_hdr,
+                    set_subtitle: "Off keeps the HDR signal, clipped to 8 bit",
+                    #[wrap(Some)]
+                    set_model = &ToneMapping::string_list(),
+                    set_selected: ToneMapping::default().to_string_list_index(),
+                    connect_selected_item_notify [sender] => move |dropdown| {
+                        let mode = ToneMapping::from_string_list_index(dropdown.selected());
+                        sender.input(ToneMappingChange(mode));
+                    }
+                },
             },
 
              adw::PreferencesGroup {
@@ -110,6 +289,60 @@ impl Component for OutputPageModel {
                     }
                 },
             },
+
+            #[name= "subtitle_group"]
+            adw::PreferencesGroup {
+                set_title: "Subtitles",
+                set_visible: false,
+
+                #[name= "burn_in_row"]
+                adw::ComboRow{
+                    set_title: "Burn in",
+                    set_subtitle: "Render onto the video",
+                    connect_selected_item_notify [sender] => move |dropdown| {
+                        sender.input(SubtitleBurnIn(dropdown.selected()))
+                    }
+                },
+            },
+
+            adw::PreferencesGroup {
+                #[watch]
+                set_sensitive: model.custom_encoding,
+
+                adw::EntryRow {
+                    set_title: "Save as preset",
+                    set_show_apply_button: true,
+                    connect_apply[sender] => move |row| {
+                        sender.input(SavePreset(row.text().to_string()))
+                    },
+                },
+            },
+
+            adw::PreferencesGroup {
+                set_title: "Metadata",
+
+                adw::SwitchRow {
+                    set_title: "Remove location",
+                    set_subtitle: "GPS coordinates and place names",
+                    connect_active_notify[sender] => move |row| {
+                        sender.input(StripLocation(row.is_active()))
+                    },
+                },
+
+                adw::EntryRow {
+                    set_title: "Title",
+                    connect_changed[sender] => move |row| {
+                        sender.input(TitleChange(row.text().to_string()))
+                    },
+                },
+
+                adw::EntryRow {
+                    set_title: "Comment",
+                    connect_changed[sender] => move |row| {
+                        sender.input(CommentChange(row.text().to_string()))
+                    },
+                },
+            },
         }
     }
 
@@ -125,14 +358,35 @@ impl Component for OutputPageModel {
             audio_bitrate: 0,
             video_bitrate: 0,
             video_codec: VideoCodec::Unknown,
+            video_profile: None,
+            video_level: None,
+            chroma_subsampling: ChromaSubsampling::YUV420,
+            bit_depth: BitDepth::Eight,
             container: ContainerFormat::Unknown,
+            max_height: None,
+            target_size: None,
+            keep_anamorphic: false,
+            tone_mapping: ToneMapping::default(),
         };
 
         let model = OutputPageModel {
             video_info: VideoContainerInfo::default(),
+            is_anamorphic: false,
+            keep_anamorphic: false,
+            is_hdr: false,
+            tone_mapping: ToneMapping::default(),
             export_settings: settings,
             custom_encoding: false,
             selected_audio_stream_idx: 0,
+            video_stream_idx: 0,
+            encoding_error: None,
+            metadata: MetadataExportSettings::default(),
+            subtitles: SubtitleExportSettings::default(),
+            subtitle_rows: Vec::new(),
+            presets: load_presets(),
+            rate_control: RateControl::MatchSource,
+            video_kbps: 0,
+            target_megabytes: 0.0,
         };
 
         let widgets = view_output!();
@@ -148,15 +402,35 @@ impl Component for OutputPageModel {
         _root: &Self::Root,
     ) {
         match message {
-            OutputPageMsg::VideoInfo(video_info) => {
+            OutputPageMsg::VideoInfo(info) => {
+                self.is_anamorphic = info.is_anamorphic();
+                self.is_hdr = info.is_hdr();
+                self.video_stream_idx = info.video_stream_idx;
+                let video_info = info.container_info;
                 self.video_info = video_info.clone();
-                self.export_settings = self.export_settings_from_video_info();
+                self.export_settings =
+                    OutputContainerSettings::from_container_info(&self.video_info);
 
-                let video_idx = video_info.video_codec.to_string_list_index();
+                widgets
+                    .video_stream_row
+                    .set_model(Some(&self.video_info.video_streams_string_list()));
+                widgets.video_stream_row.set_selected(self.video_stream_idx);
+
+                let video_idx = video_info.video_codec.encodable().to_string_list_index();
                 let container_idx = video_info.container.to_string_list_index();
 
                 widgets.video_codec_row.set_selected(video_idx);
                 widgets.container_row.set_selected(container_idx);
+                widgets
+                    .bit_depth_row
+                    .set_selected(self.export_settings.bit_depth.to_string_list_index());
+                update_profile_rows(widgets, self.export_settings.video_codec);
+
+                // a new file starts untonemapped, the row notifies the renderer if it changes
+                self.tone_mapping = ToneMapping::default();
+                widgets
+                    .tone_mapping_row
+                    .set_selected(self.tone_mapping.to_string_list_index());
 
                 if self.video_info.audio_streams.len() >= 2 {
                     widgets.audio_stream_row.set_visible(true);
@@ -176,9 +450,73 @@ impl Component for OutputPageModel {
                         _ => widgets.audio_codec_row.set_selected(audio_idx),
                     }
                 }
+
+                self.subtitles = SubtitleExportSettings::default();
+                for row in self.subtitle_rows.drain(..) {
+                    widgets.subtitle_group.remove(&row);
+                }
+
+                let subtitle_streams = &self.video_info.subtitle_streams;
+                widgets
+                    .subtitle_group
+                    .set_visible(!subtitle_streams.is_empty());
+                widgets
+                    .burn_in_row
+                    .set_model(Some(&self.video_info.subtitle_streams_string_list()));
+
+                for (idx, stream) in subtitle_streams.iter().enumerate() {
+                    let row = adw::SwitchRow::builder()
+                        .title(stream.display())
+                        .subtitle(if stream.codec.is_text() {
+                            "Keep as subtitle track"
+                        } else {
+                            "Bitmap subtitles can't be exported"
+                        })
+                        .sensitive(stream.codec.is_text())
+                        .build();
+
+                    let row_sender = sender.clone();
+                    row.connect_active_notify(move |row| {
+                        row_sender.input(SubtitlePassthrough(idx as u32, row.is_active()))
+                    });
+
+                    widgets.subtitle_group.add(&row);
+                    self.subtitle_rows.push(row);
+                }
+            }
+            VideoCodecChange(codec) => {
+                // presets select their codec before their profile, which must survive this
+                if codec != self.export_settings.video_codec {
+                    self.export_settings.video_codec = codec;
+                    self.export_settings.video_profile = None;
+                    self.export_settings.video_level = None;
+                    update_profile_rows(widgets, codec);
+                }
+
+                self.validate_encoding();
+            }
+            VideoProfileChange(idx) => {
+                let profiles = self.export_settings.video_codec.profiles();
+                self.export_settings.video_profile = auto_list_value(profiles, idx);
+                self.validate_encoding();
+            }
+            VideoLevelChange(idx) => {
+                let levels = self.export_settings.video_codec.levels();
+                self.export_settings.video_level = auto_list_value(levels, idx);
+                self.validate_encoding();
+            }
+            ChromaChange(chroma) => {
+                self.export_settings.chroma_subsampling = chroma;
+                self.validate_encoding();
+            }
+            BitDepthChange(depth) => {
+                self.export_settings.bit_depth = depth;
+                self.validate_encoding();
+            }
+            AudioCodecChange(codec) => {
+                self.export_settings.audio_codec = codec;
+                self.validate_encoding();
             }
-            VideoCodecChange(codec) => self.export_settings.video_codec = codec,
-            AudioCodecChange(codec) => self.export_settings.audio_codec = codec,
             AudioStreamChange(stream_idx) => {
                 self.export_settings.audio_stream_idx = stream_idx;
 
@@ -192,9 +530,128 @@ impl Component for OutputPageModel {
                     _ => widgets.audio_codec_row.set_selected(audio_idx),
                 }
             }
-            ContainerChange(container) => self.export_settings.container = container,
+            ContainerChange(container) => {
+                self.export_settings.container = container;
+                self.validate_encoding();
+            }
+            StripLocation(strip) => self.metadata.strip_location = strip,
+            // empty entries keep the source's value
+            TitleChange(title) => self.metadata.title = Some(title).filter(|t| !t.is_empty()),
+            CommentChange(comment) => {
+                self.metadata.comment = Some(comment).filter(|c| !c.is_empty())
+            }
+            SubtitlePassthrough(stream_idx, enabled) => {
+                let streams = &mut self.subtitles.passthrough_streams;
+                streams.retain(|idx| *idx != stream_idx);
+                if enabled {
+                    streams.push(stream_idx);
+                }
+            }
+            // index 0 is "None"
+            SubtitleBurnIn(idx) => {
+                let stream_idx = idx.checked_sub(1).filter(|stream_idx| {
+                    self.video_info
+                        .subtitle_streams
+                        .get(*stream_idx as usize)
+                        .is_some_and(|stream| stream.codec.is_text())
+                });
+
+                self.subtitles.burn_in_stream = stream_idx;
+                sender
+                    .output(OutputPageOutput::PreviewSubtitles(stream_idx))
+                    .unwrap();
+            }
+            // index 0 is "Custom"
+            PresetChange(idx) => {
+                if let Some(preset) = idx
+                    .checked_sub(1)
+                    .and_then(|idx| self.presets.get(idx as usize))
+                    .cloned()
+                {
+                    self.apply_preset(widgets, &preset);
+                }
+            }
+            SavePreset(name) => {
+                let name = name.trim();
+                if !name.is_empty() {
+                    let preset = ExportPreset::from_container_settings(
+                        name.to_string(),
+                        &self.export_settings(),
+                        self.rate_control,
+                    );
+
+                    match preset.save() {
+                        Ok(path) => {
+                            tracing::info!("saved preset to {}", path.display());
+                            self.presets = load_presets();
+                            widgets
+                                .preset_row
+                                .set_model(Some(&presets_string_list(&self.presets)));
+                        }
+                        Err(err) => tracing::error!("could not save preset: {err:?}"),
+                    }
+                }
+            }
+            ResolutionChange(idx) => {
+                self.export_settings.max_height =
+                    OUTPUT_HEIGHTS.get(idx as usize).copied().flatten();
+            }
+            VideoBitrateChange(kbps) => {
+                self.video_kbps = kbps;
+                self.update_rate_control();
+            }
+            TargetSizeChange(megabytes) => {
+                self.target_megabytes = megabytes;
+                self.update_rate_control();
+            }
+            KeepAnamorphic(keep) => self.keep_anamorphic = keep,
+            VideoStreamChange(stream_idx) => {
+                // also fires when the stream list is replaced
+                if stream_idx == self.video_stream_idx {
+                    return;
+                }
+                let Some(stream) = self.video_info.video_streams.get(stream_idx as usize) else {
+                    return;
+                };
+
+                self.video_stream_idx = stream_idx;
+                self.is_anamorphic = stream.is_anamorphic();
+                self.is_hdr = stream.is_hdr();
+                self.video_info.video_codec = stream.codec;
+                self.video_info.video_bitrate = stream.bitrate;
+                self.video_info.video_bit_depth = stream.bit_depth;
+
+                // the video side follows the new stream, the other choices are kept
+                let settings = self.export_settings;
+                self.export_settings = OutputContainerSettings {
+                    no_audio: settings.no_audio,
+                    audio_stream_idx: settings.audio_stream_idx,
+                    audio_codec: settings.audio_codec,
+                    audio_bitrate: settings.audio_bitrate,
+                    container: settings.container,
+                    max_height: settings.max_height,
+                    ..OutputContainerSettings::from_container_info(&self.video_info)
+                };
+                let video_idx = self.export_settings.video_codec.to_string_list_index();
+                widgets.video_codec_row.set_selected(video_idx);
+                widgets
+                    .bit_depth_row
+                    .set_selected(self.export_settings.bit_depth.to_string_list_index());
+                update_profile_rows(widgets, self.export_settings.video_codec);
+                self.update_rate_control();
+                self.validate_encoding();
+
+                sender
+                    .output(OutputPageOutput::VideoStream(stream_idx))
+                    .unwrap();
+            }
+            ToneMappingChange(mode) => {
+                self.tone_mapping = mode;
+                sender.output(OutputPageOutput::ToneMapping(mode)).unwrap();
+            }
             CustomEncoding(enabled) => {
                 self.custom_encoding = enabled;
+                self.validate_encoding();
             }
         }
         self.update_view(widgets, sender);
@@ -202,33 +659,163 @@ impl Component for OutputPageModel {
 }
 
 impl OutputPageModel {
-    fn export_settings_from_video_info(&self) -> OutputContainerSettings {
-        OutputContainerSettings {
-            no_audio: false,
-            audio_stream_idx: 0,
-            video_bitrate: self.video_info.video_bitrate,
-            video_codec: self.video_info.video_codec,
-            container: self.video_info.container,
-            audio_bitrate: if !self.video_info.audio_streams.is_empty() {
-                self.video_info.audio_streams[0].bitrate
-            } else {
-                AUDIO_BITRATE_DEFAULT
-            },
-            audio_codec: if !self.video_info.audio_streams.is_empty() {
-                self.video_info.audio_streams[0].codec
-            } else {
-                AudioCodec::NoAudio
-            },
-        }
+    fn apply_preset(&mut self, widgets: &mut OutputPageModelWidgets, preset: &ExportPreset) {
+        let settings =
+            preset.container_settings(&self.video_info, self.export_settings.audio_stream_idx);
+        self.export_settings = settings;
+        self.rate_control = preset.rate_control;
+        self.custom_encoding = true;
+
+        widgets.custom_encoding_row.set_active(true);
+        widgets
+            .container_row
+            .set_selected(settings.container.to_string_list_index());
+        widgets
+            .video_codec_row
+            .set_selected(settings.video_codec.to_string_list_index());
+        widgets
+            .chroma_row
+            .set_selected(settings.chroma_subsampling.to_string_list_index());
+        widgets
+            .bit_depth_row
+            .set_selected(settings.bit_depth.to_string_list_index());
+        widgets
+            .audio_codec_row
+            .set_selected(settings.audio_codec.to_string_list_index());
+
+        update_profile_rows(widgets, settings.video_codec);
+        widgets.video_profile_row.set_selected(auto_list_index(
+            settings.video_codec.profiles(),
+            settings.video_profile,
+        ));
+        widgets.video_level_row.set_selected(auto_list_index(
+            settings.video_codec.levels(),
+            settings.video_level,
+        ));
+
+        let resolution_idx = OUTPUT_HEIGHTS
+            .iter()
+            .position(|height| *height == settings.max_height)
+            .unwrap_or(0);
+        widgets.resolution_row.set_selected(resolution_idx as u32);
+
+        (self.video_kbps, self.target_megabytes) = match preset.rate_control {
+            RateControl::MatchSource => (0, 0.0),
+            RateControl::Bitrate { kbps } => (kbps, 0.0),
+            RateControl::TargetSize { megabytes } => (0, megabytes),
+        };
+        widgets.video_bitrate_row.set_value(self.video_kbps as f64);
+        widgets
+            .target_size_row
+            .set_value(self.target_megabytes as f64);
+
+        self.validate_encoding();
+    }
+
+    // a target size wins over a fixed bitrate
+    fn update_rate_control(&mut self) {
+        self.rate_control = if self.target_megabytes > 0.0 {
+            RateControl::TargetSize {
+                megabytes: self.target_megabytes,
+            }
+        } else if self.video_kbps > 0 {
+            RateControl::Bitrate {
+                kbps: self.video_kbps,
+            }
+        } else {
+            RateControl::MatchSource
+        };
+
+        self.export_settings.target_size = self.rate_control.target_size();
+        self.export_settings.video_bitrate = match self.rate_control {
+            RateControl::Bitrate { kbps } => kbps * 1000,
+            _ => self.video_info.video_bitrate,
+        };
+    }
+
+    fn validate_encoding(&mut self) {
+        self.encoding_error = if self.custom_encoding {
+            validate_container(&self.export_settings)
+                .and_then(|()| validate_video_encoding(&self.export_settings))
+                .err()
+                .map(|err| err.to_string())
+        } else {
+            None
+        };
+    }
+
+    pub fn metadata_settings(&self) -> MetadataExportSettings {
+        self.metadata.clone()
+    }
+
+    pub fn subtitle_settings(&self) -> SubtitleExportSettings {
+        self.subtitles.clone()
     }
 
     pub fn export_settings(&self) -> OutputContainerSettings {
-        if !self.custom_encoding {
-            self.export_settings_from_video_info()
+        let mut settings = if !self.custom_encoding {
+            OutputContainerSettings::from_container_info(&self.video_info)
         } else {
             // todo: pass container info regardless
             //  changing container shouldn't trigger a reencoding
             self.export_settings
-        }
+        };
+
+        settings.keep_anamorphic = self.is_anamorphic && self.keep_anamorphic;
+        settings.tone_mapping = self.tone_mapping;
+        settings
+    }
+}
+
+fn update_profile_rows(widgets: &mut OutputPageModelWidgets, codec: VideoCodec) {
+    let profiles = codec.profiles();
+    widgets.video_profile_row.set_visible(!profiles.is_empty());
+    widgets
+        .video_profile_row
+        .set_model(Some(&auto_string_list(profiles)));
+
+    let levels = codec.levels();
+    widgets.video_level_row.set_visible(!levels.is_empty());
+    widgets
+        .video_level_row
+        .set_model(Some(&auto_string_list(levels)));
+}
+
+fn presets_string_list(presets: &[ExportPreset]) -> gtk::StringList {
+    let list = gtk::StringList::new(&["Custom"]);
+    for preset in presets {
+        list.append(&preset.name);
     }
+    list
+}
+
+fn resolutions_string_list() -> gtk::StringList {
+    let list = gtk::StringList::new(&["Original"]);
+    for height in OUTPUT_HEIGHTS.iter().flatten() {
+        list.append(&format!("{height}p"));
+    }
+    list
+}
+
+fn auto_string_list(options: &[&str]) -> gtk::StringList {
+    let list = gtk::StringList::new(&["Auto"]);
+    for option in options {
+        list.append(option);
+    }
+    list
+}
+
+// index 0 is "Auto", which leaves the choice to the encoder
+fn auto_list_value(options: &'static [&'static str], idx: u32) -> Option<&'static str> {
+    if idx == 0 {
+        None
+    } else {
+        options.get(idx as usize - 1).copied()
+    }
+}
+
+fn auto_list_index(options: &[&str], value: Option<&str>) -> u32 {
+    value
+        .and_then(|value| options.iter().position(|option| *option == value))
+        .map_or(0, |idx| idx as u32 + 1)
 }
@@ -0,0 +1,404 @@
+use crate::renderer::{RedactBounds, RedactRegion, RedactShape, RedactStyle, MAX_REDACT_REGIONS};
+use crate::ui::preview::RedactionEdit;
+use crate::ui::slider::adjust_row::{AdjustRowInit, AdjustRowModel, AdjustRowMsg, AdjustRowOutput};
+use crate::ui::slider::slider::SliderFillMode;
+use crate::video::selectable::SelectableOption;
+use gst::ClockTime;
+use relm4::adw::prelude::{ActionRowExt, ComboRowExt, PreferencesGroupExt, PreferencesRowExt};
+use relm4::gtk::prelude::{ButtonExt, OrientableExt, WidgetExt};
+use relm4::{
+    adw, gtk, Component, ComponentController, ComponentParts, ComponentSender, Controller,
+};
+
+/// Size of a new region, centred on the frame.
+const NEW_REGION_SIZE: f32 = 0.3;
+
+pub struct RedactPageModel {
+    regions: Vec<RedactRegion>,
+    selected: Option<usize>,
+    /// Timestamp of the frame on the preview, where moved regions get their keyframes.
+    frame_time: Option<ClockTime>,
+    strength_slider: Controller<AdjustRowModel>,
+}
+
+#[derive(Debug)]
+pub enum RedactPageMsg {
+    AddRegion(RedactShape),
+    /// Reads the region row, stale notifies from rebuilding the list are ignored.
+    SelectionChanged,
+    ShapeChange(RedactShape),
+    StyleChange(RedactStyle),
+    StrengthChange(f64),
+    Follow(bool),
+    RemoveKeyframe,
+    RemoveRegion,
+    Edited(RedactionEdit),
+    FrameTime(Option<ClockTime>),
+    Reset,
+    NOP,
+}
+
+#[derive(Debug)]
+pub enum RedactPageOutput {
+    RedactionsChanged(Vec<RedactRegion>, Option<usize>),
+}
+
+#[relm4::component(pub)]
+impl Component for RedactPageModel {
+    type Input = RedactPageMsg;
+    type Output = RedactPageOutput;
+    type Init = ();
+    type CommandOutput = ();
+
+    view! {
+        adw::PreferencesPage {
+            set_hexpand: true,
+
+            adw::PreferencesGroup {
+                set_title: "Regions",
+                #[watch]
+                set_description: Some(&model.regions_description()),
+
+                adw::ButtonRow {
+                    set_title: "Add Rectangle",
+                    set_start_icon_name: Some("list-add-symbolic"),
+                    #[watch]
+                    set_sensitive: model.can_add_region(),
+
+                    connect_activated => RedactPageMsg::AddRegion(RedactShape::Rectangle),
+                },
+
+                adw::ButtonRow {
+                    set_title: "Add Ellipse",
+                    set_start_icon_name: Some("list-add-symbolic"),
+                    #[watch]
+                    set_sensitive: model.can_add_region(),
+
+                    connect_activated => RedactPageMsg::AddRegion(RedactShape::Ellipse),
+                },
+            },
+
+            adw::PreferencesGroup {
+                #[watch]
+                set_visible: model.selected.is_some(),
+
+                #[name = "region_row"]
+                adw::ComboRow {
+                    set_title: "Region",
+
+                    connect_selected_item_notify => RedactPageMsg::SelectionChanged,
+                },
+
+                #[name = "shape_row"]
+                adw::ComboRow {
+                    set_title: "Shape",
+                    #[wrap(Some)]
+                    set_model = &RedactShape::string_list(),
+
+                    connect_selected_item_notify[sender] => move |row| {
+                        let shape = RedactShape::from_string_list_index(row.selected());
+                        sender.input(RedactPageMsg::ShapeChange(shape))
+                    },
+                },
+
+                #[name = "style_row"]
+                adw::ComboRow {
+                    set_title: "Style",
+                    #[wrap(Some)]
+                    set_model = &RedactStyle::string_list(),
+
+                    connect_selected_item_notify[sender] => move |row| {
+                        let style = RedactStyle::from_string_list_index(row.selected());
+                        sender.input(RedactPageMsg::StyleChange(style))
+                    },
+                },
+
+                gtk::Box {
+                    set_hexpand: true,
+                    set_margin_top: 10,
+                    set_orientation: gtk::Orientation::Vertical,
+
+                    model.strength_slider.widget(){},
+                },
+            },
+
+            adw::PreferencesGroup {
+                set_title: "Motion",
+                #[watch]
+                set_visible: model.selected.is_some(),
+
+                #[name = "follow_row"]
+                adw::SwitchRow {
+                    set_title: "Follow over time",
+                    set_subtitle: "Moving the region adds a keyframe on the current frame",
+
+                    connect_active_notify[sender] => move |row| {
+                        sender.input(RedactPageMsg::Follow(row.is_active()))
+                    },
+                },
+
+                adw::ActionRow {
+                    set_title: "Keyframes",
+                    #[watch]
+                    set_subtitle: &model.keyframes_subtitle(),
+                    #[watch]
+                    set_visible: model.selected_region().is_some_and(RedactRegion::is_tracked),
+
+                    add_suffix = &gtk::Button {
+                        set_icon_name: "edit-delete-symbolic",
+                        set_tooltip_text: Some("Remove Keyframe on This Frame"),
+                        set_valign: gtk::Align::Center,
+                        add_css_class: "flat",
+
+                        connect_clicked => RedactPageMsg::RemoveKeyframe,
+                    },
+                },
+            },
+
+            adw::PreferencesGroup {
+                #[watch]
+                set_visible: model.selected.is_some(),
+
+                adw::ButtonRow {
+                    set_title: "Remove Region",
+
+                    connect_activated => RedactPageMsg::RemoveRegion,
+                },
+            },
+
+            adw::PreferencesGroup {
+                set_valign: gtk::Align::End,
+                set_vexpand: true,
+
+                adw::ButtonRow {
+                    set_title: "Reset",
+                    add_css_class: "destructive-action",
+
+                    connect_activated => RedactPageMsg::Reset,
+                }
+            }
+        }
+    }
+
+    fn init(
+        _init: Self::Init,
+        root: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let (value_range, display_range) = RedactRegion::strength_range();
+        let strength_slider = AdjustRowModel::builder()
+            .launch(AdjustRowInit::new(
+                "Strength",
+                true,
+                true,
+                value_range,
+                display_range,
+                SliderFillMode::EdgeToEdge,
+            ))
+            .forward(sender.input_sender(), |msg| match msg {
+                AdjustRowOutput::ValueChanged(val) => RedactPageMsg::StrengthChange(val),
+                _ => RedactPageMsg::NOP,
+            });
+
+        let model = RedactPageModel {
+            regions: Vec::new(),
+            selected: None,
+            frame_time: None,
+            strength_slider,
+        };
+
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update_with_view(
+        &mut self,
+        widgets: &mut Self::Widgets,
+        message: Self::Input,
+        sender: ComponentSender<Self>,
+        _root: &Self::Root,
+    ) {
+        match message {
+            RedactPageMsg::AddRegion(shape) => {
+                if !self.can_add_region() {
+                    return;
+                }
+                let start = (1.0 - NEW_REGION_SIZE) / 2.0;
+                let bounds = RedactBounds::new(start, start, NEW_REGION_SIZE, NEW_REGION_SIZE);
+                self.regions.push(RedactRegion::new(shape, bounds));
+                self.selected = Some(self.regions.len() - 1);
+                self.sync_rows(widgets, true);
+            }
+            RedactPageMsg::SelectionChanged => {
+                let idx = widgets.region_row.selected() as usize;
+                if idx >= self.regions.len() || Some(idx) == self.selected {
+                    return;
+                }
+                self.selected = Some(idx);
+                self.sync_rows(widgets, false);
+            }
+            RedactPageMsg::ShapeChange(shape) => {
+                let Some(region) = self.selected_region_mut().filter(|r| r.shape != shape) else {
+                    return;
+                };
+                region.shape = shape;
+                // region names include their shape
+                self.sync_rows(widgets, true);
+            }
+            RedactPageMsg::StyleChange(style) => {
+                let Some(region) = self.selected_region_mut().filter(|r| r.style != style) else {
+                    return;
+                };
+                region.style = style;
+            }
+            RedactPageMsg::StrengthChange(strength) => {
+                let Some(region) = self.selected_region_mut() else {
+                    return;
+                };
+                region.strength = strength as f32;
+            }
+            RedactPageMsg::Follow(follow) => {
+                let time = self.frame_time;
+                let Some(region) = self
+                    .selected_region_mut()
+                    .filter(|r| r.is_tracked() != follow)
+                else {
+                    return;
+                };
+
+                if follow {
+                    // images have no timestamps, their one frame is at zero
+                    region.track(time.unwrap_or(ClockTime::ZERO));
+                } else {
+                    region.untrack(time);
+                }
+            }
+            RedactPageMsg::RemoveKeyframe => {
+                let time = self.frame_time.unwrap_or(ClockTime::ZERO);
+                let Some(region) = self.selected_region_mut() else {
+                    return;
+                };
+                if !region.remove_keyframe(time) {
+                    return;
+                }
+                if !region.is_tracked() {
+                    widgets.follow_row.set_active(false);
+                }
+            }
+            RedactPageMsg::RemoveRegion => {
+                let Some(idx) = self.selected else {
+                    return;
+                };
+                self.regions.remove(idx);
+                self.selected = match self.regions.len() {
+                    0 => None,
+                    len => Some(idx.min(len - 1)),
+                };
+                self.sync_rows(widgets, true);
+            }
+            RedactPageMsg::Edited(RedactionEdit::Selected(idx)) => {
+                if idx >= self.regions.len() {
+                    return;
+                }
+                self.selected = Some(idx);
+                self.sync_rows(widgets, false);
+            }
+            RedactPageMsg::Edited(RedactionEdit::Moved { idx, time, bounds }) => {
+                let Some(region) = self.regions.get_mut(idx) else {
+                    return;
+                };
+                region.set_bounds(time, bounds);
+            }
+            RedactPageMsg::FrameTime(time) => {
+                self.frame_time = time;
+                return;
+            }
+            RedactPageMsg::Reset => {
+                self.regions.clear();
+                self.selected = None;
+                self.sync_rows(widgets, true);
+            }
+            RedactPageMsg::NOP => return,
+        }
+
+        sender
+            .output(RedactPageOutput::RedactionsChanged(
+                self.regions.clone(),
+                self.selected,
+            ))
+            .unwrap();
+
+        self.update_view(widgets, sender);
+    }
+}
+
+impl RedactPageModel {
+    pub fn redact_settings(&self) -> Vec<RedactRegion> {
+        self.regions.clone()
+    }
+
+    fn can_add_region(&self) -> bool {
+        self.regions.len() < MAX_REDACT_REGIONS
+    }
+
+    fn regions_description(&self) -> String {
+        if self.can_add_region() {
+            "Drag a region on the preview to move it, or its corners to resize it".to_string()
+        } else {
+            format!("At most {MAX_REDACT_REGIONS} regions can be hidden, remove one to add another")
+        }
+    }
+
+    fn selected_region(&self) -> Option<&RedactRegion> {
+        self.selected.and_then(|idx| self.regions.get(idx))
+    }
+
+    fn selected_region_mut(&mut self) -> Option<&mut RedactRegion> {
+        self.selected.and_then(|idx| self.regions.get_mut(idx))
+    }
+
+    fn keyframes_subtitle(&self) -> String {
+        match self
+            .selected_region()
+            .map_or(0, |region| region.keyframes().len())
+        {
+            1 => "1 keyframe".to_string(),
+            count => format!("{count} keyframes"),
+        }
+    }
+
+    /// Shows the selected region's settings, rebuilding the region list when regions were added,
+    /// removed or renamed.
+    fn sync_rows(&self, widgets: &RedactPageModelWidgets, rebuild_list: bool) {
+        if rebuild_list {
+            let names: Vec<String> = self
+                .regions
+                .iter()
+                .enumerate()
+                .map(|(idx, region)| format!("{} {}", region.shape.display(), idx + 1))
+                .collect();
+            let names: Vec<&str> = names.iter().map(String::as_str).collect();
+            widgets
+                .region_row
+                .set_model(Some(&gtk::StringList::new(&names)));
+        }
+
+        let Some(region) = self.selected_region() else {
+            return;
+        };
+
+        widgets
+            .region_row
+            .set_selected(self.selected.unwrap() as u32);
+        widgets
+            .shape_row
+            .set_selected(region.shape.to_string_list_index());
+        widgets
+            .style_row
+            .set_selected(region.style.to_string_list_index());
+        widgets.follow_row.set_active(region.is_tracked());
+        self.strength_slider
+            .emit(AdjustRowMsg::SilentSet(region.strength as f64));
+    }
+}
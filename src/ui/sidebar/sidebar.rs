@@ -1,26 +1,33 @@
-use crate::renderer::EffectParameters;
-use crate::ui::preview::{CropMode, Orientation};
+use crate::renderer::{
+    Curves, EffectParameters, Lut, RedactRegion, SpatialParameters, ToneMapping,
+};
+use crate::ui::preview::{CropMode, Orientation, RedactionEdit};
 use crate::ui::sidebar::adjust::{AdjustPageModel, AdjustPageOutput};
 use crate::ui::sidebar::crop::{CropPageModel, CropPageMsg, CropPageOutput};
 use crate::ui::sidebar::output::{OutputPageModel, OutputPageMsg, OutputPageOutput};
+use crate::ui::sidebar::redact::{RedactPageModel, RedactPageMsg, RedactPageOutput};
 use crate::ui::sidebar::ControlsExportSettings;
-use crate::video::metadata::VideoContainerInfo;
+use crate::video::metadata::VideoInfo;
+use gst::ClockTime;
 use relm4::gtk::prelude::ButtonExt;
 use relm4::{
     adw, gtk, Component, ComponentController, ComponentParts, ComponentSender, Controller,
     SimpleComponent,
 };
+use std::sync::Arc;
 
 pub struct ControlsModel {
     crop_page: Controller<CropPageModel>,
     output_page: Controller<OutputPageModel>,
     adjust_page: Controller<AdjustPageModel>,
+    redact_page: Controller<RedactPageModel>,
     stack: adw::ViewStack,
 }
 
 #[derive(Debug)]
 pub enum ControlsMsg {
-    VideoLoaded((VideoContainerInfo, Orientation)),
+    VideoLoaded(VideoInfo),
+    SetBaseOrientation(Orientation),
     Rotate,
     ExportFrame,
     Orient(Orientation),
@@ -31,7 +38,17 @@ pub enum ControlsMsg {
     CropPageSelected,
     OutputPageSelected,
     AdjustPageSelected,
+    RedactPageSelected,
     EffectsChanged(EffectParameters),
+    CurvesChanged(Curves),
+    SpatialChanged(SpatialParameters),
+    LutChanged(Option<Arc<Lut>>, f32),
+    RedactionsChanged(Vec<RedactRegion>, Option<usize>),
+    RedactionEdited(RedactionEdit),
+    FrameTime(Option<ClockTime>),
+    PreviewSubtitles(Option<u32>),
+    ToneMapping(ToneMapping),
+    VideoStream(u32),
 }
 
 #[derive(Debug)]
@@ -41,12 +58,21 @@ pub enum ControlsOutput {
     HideCropBox,
     TempResetZoom,
     RestoreZoom,
+    ShowRedactions,
+    HideRedactions,
     OrientVideo(Orientation),
     StraightenBegin,
     Straigten(f64),
     StraightenEnd,
     SetCropMode(CropMode),
     EffectsChanged(EffectParameters),
+    CurvesChanged(Curves),
+    SpatialChanged(SpatialParameters),
+    LutChanged(Option<Arc<Lut>>, f32),
+    RedactionsChanged(Vec<RedactRegion>, Option<usize>),
+    PreviewSubtitles(Option<u32>),
+    ToneMapping(ToneMapping),
+    VideoStream(u32),
 }
 
 #[relm4::component(pub)]
@@ -72,6 +98,7 @@ impl SimpleComponent for ControlsModel {
                         "crop_page" => sender.input(ControlsMsg::CropPageSelected),
                         "output_page" => sender.input(ControlsMsg::OutputPageSelected),
                         "adjust_page" => sender.input(ControlsMsg::AdjustPageSelected),
+                        "redact_page" => sender.input(ControlsMsg::RedactPageSelected),
                         _ => {},
                     }
 
@@ -101,6 +128,11 @@ impl SimpleComponent for ControlsModel {
                 .launch(())
                 .forward(sender.input_sender(), |msg| match msg {
                     OutputPageOutput::ExportFrame => ControlsMsg::ExportFrame,
+                    OutputPageOutput::PreviewSubtitles(stream) => {
+                        ControlsMsg::PreviewSubtitles(stream)
+                    }
+                    OutputPageOutput::ToneMapping(mode) => ControlsMsg::ToneMapping(mode),
+                    OutputPageOutput::VideoStream(stream) => ControlsMsg::VideoStream(stream),
                 });
 
         let adjust_page =
@@ -108,6 +140,22 @@ impl SimpleComponent for ControlsModel {
                 .launch(())
                 .forward(sender.input_sender(), |msg| match msg {
                     AdjustPageOutput::EffectUpdate(params) => ControlsMsg::EffectsChanged(params),
+                    AdjustPageOutput::CurvesUpdate(curves) => ControlsMsg::CurvesChanged(curves),
+                    AdjustPageOutput::SpatialUpdate(parameters) => {
+                        ControlsMsg::SpatialChanged(parameters)
+                    }
+                    AdjustPageOutput::LutUpdate(lut, intensity) => {
+                        ControlsMsg::LutChanged(lut, intensity)
+                    }
+                });
+
+        let redact_page =
+            RedactPageModel::builder()
+                .launch(())
+                .forward(sender.input_sender(), |msg| match msg {
+                    RedactPageOutput::RedactionsChanged(regions, selected) => {
+                        ControlsMsg::RedactionsChanged(regions, selected)
+                    }
                 });
 
         let widgets = view_output!();
@@ -116,6 +164,7 @@ impl SimpleComponent for ControlsModel {
             crop_page,
             output_page,
             adjust_page,
+            redact_page,
             stack: widgets.stack.clone(),
         };
 
@@ -142,17 +191,26 @@ impl SimpleComponent for ControlsModel {
             "crop-symbolic",
         );
 
+        model.stack.add_titled_with_icon(
+            model.redact_page.widget(),
+            Some("redact_page"),
+            "Redact",
+            "view-conceal-symbolic",
+        );
+
         ComponentParts { model, widgets }
     }
 
     fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
         match message {
-            ControlsMsg::VideoLoaded((default_codec, base_orientaiton)) => {
-                self.output_page
-                    .emit(OutputPageMsg::VideoInfo(default_codec));
+            ControlsMsg::VideoLoaded(info) => {
                 self.crop_page
-                    .emit(CropPageMsg::SetBaseOrientation(base_orientaiton));
+                    .emit(CropPageMsg::SetBaseOrientation(info.orientation));
+                self.output_page.emit(OutputPageMsg::VideoInfo(info));
             }
+            ControlsMsg::SetBaseOrientation(orientation) => self
+                .crop_page
+                .emit(CropPageMsg::SetBaseOrientation(orientation)),
             ControlsMsg::SetCropMode(mode) => {
                 sender.output(ControlsOutput::SetCropMode(mode)).unwrap();
                 sender.output(ControlsOutput::ShowCropBox).unwrap();
@@ -168,20 +226,53 @@ impl SimpleComponent for ControlsModel {
             ControlsMsg::ExportFrame => sender.output(ControlsOutput::ExportFrame).unwrap(),
             ControlsMsg::Rotate => self.crop_page.emit(CropPageMsg::RotateRight90),
             ControlsMsg::CropPageSelected => {
+                sender.output(ControlsOutput::HideRedactions).unwrap();
                 sender.output(ControlsOutput::ShowCropBox).unwrap();
                 sender.output(ControlsOutput::TempResetZoom).unwrap();
             }
             ControlsMsg::OutputPageSelected => {
+                sender.output(ControlsOutput::HideRedactions).unwrap();
                 sender.output(ControlsOutput::HideCropBox).unwrap();
                 sender.output(ControlsOutput::RestoreZoom).unwrap();
             }
             ControlsMsg::AdjustPageSelected => {
+                sender.output(ControlsOutput::HideRedactions).unwrap();
                 sender.output(ControlsOutput::HideCropBox).unwrap();
                 sender.output(ControlsOutput::RestoreZoom).unwrap();
             }
+            ControlsMsg::RedactPageSelected => {
+                sender.output(ControlsOutput::HideCropBox).unwrap();
+                sender.output(ControlsOutput::TempResetZoom).unwrap();
+                sender.output(ControlsOutput::ShowRedactions).unwrap();
+            }
             ControlsMsg::EffectsChanged(params) => sender
                 .output(ControlsOutput::EffectsChanged(params))
                 .unwrap(),
+            ControlsMsg::CurvesChanged(curves) => sender
+                .output(ControlsOutput::CurvesChanged(curves))
+                .unwrap(),
+            ControlsMsg::SpatialChanged(parameters) => sender
+                .output(ControlsOutput::SpatialChanged(parameters))
+                .unwrap(),
+            ControlsMsg::LutChanged(lut, intensity) => sender
+                .output(ControlsOutput::LutChanged(lut, intensity))
+                .unwrap(),
+            ControlsMsg::RedactionsChanged(regions, selected) => sender
+                .output(ControlsOutput::RedactionsChanged(regions, selected))
+                .unwrap(),
+            ControlsMsg::RedactionEdited(edit) => {
+                self.redact_page.emit(RedactPageMsg::Edited(edit))
+            }
+            ControlsMsg::FrameTime(time) => self.redact_page.emit(RedactPageMsg::FrameTime(time)),
+            ControlsMsg::PreviewSubtitles(stream) => sender
+                .output(ControlsOutput::PreviewSubtitles(stream))
+                .unwrap(),
+            ControlsMsg::ToneMapping(mode) => {
+                sender.output(ControlsOutput::ToneMapping(mode)).unwrap()
+            }
+            ControlsMsg::VideoStream(stream) => {
+                sender.output(ControlsOutput::VideoStream(stream)).unwrap()
+            }
         }
     }
 }
@@ -190,12 +281,24 @@ impl ControlsModel {
     pub fn export_settings(&self) -> ControlsExportSettings {
         let export_container = self.output_page.model().export_settings();
         let effect_parameters = self.adjust_page.model().export_settings();
+        let curves = self.adjust_page.model().curves_settings();
+        let spatial = self.adjust_page.model().spatial_settings();
+        let lut = self.adjust_page.model().lut_settings();
+        let redactions = self.redact_page.model().redact_settings();
+        let metadata = self.output_page.model().metadata_settings();
+        let subtitles = self.output_page.model().subtitle_settings();
 
         // fixme: actually get value, for if it is default
         ControlsExportSettings {
             container: export_container,
             container_is_default: true,
             effect_parameters,
+            curves,
+            spatial,
+            lut,
+            redactions,
+            metadata,
+            subtitles,
         }
     }
 
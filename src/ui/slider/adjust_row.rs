@@ -21,6 +21,8 @@ pub enum AdjustRowMsg {
     DragEnd,
     Reset,
     SilentReset,
+    /// Moves the slider without reporting a change.
+    SilentSet(f64),
 }
 
 #[derive(Debug)]
@@ -173,6 +175,11 @@ impl Component for AdjustRowModel {
                 widgets.slider.reset();
                 self.update_label_from_slider(&widgets.slider, &widgets.value_label);
             }
+            AdjustRowMsg::SilentSet(value) => {
+                widgets.slider.set_value(value);
+                self.value = widgets.slider.value();
+                self.update_label_from_slider(&widgets.slider, &widgets.value_label);
+            }
         }
 
         self.update_view(widgets, sender);
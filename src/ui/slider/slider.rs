@@ -1,7 +1,7 @@
 use crate::range::Range;
+use crate::ui::{accent_colour, WIDGET_BACKGROUND_COLOUR};
 use gst::glib;
 use gst::subclass::prelude::{ObjectImpl, ObjectSubclass};
-use relm4::adw;
 use relm4::gtk::prelude::{SnapshotExt, WidgetExt};
 use relm4::gtk::subclass::prelude::{ObjectSubclassExt, ObjectSubclassIsExt};
 use relm4::gtk::subclass::widget::WidgetImpl;
@@ -14,8 +14,6 @@ pub enum SliderFillMode {
     CenterOut,
 }
 
-// todo: find better colours
-static BACKGROUND_COLOUR: gdk::RGBA = gdk::RGBA::new(0.23, 0.23, 0.23, 1.0);
 static FILL_COLOUR: gdk::RGBA = gdk::RGBA::new(0.3, 0.3, 0.3, 1.0);
 static BAR_WIDTH: f32 = 6f32;
 static BAR_OFFSET: f32 = BAR_WIDTH / 2.0;
@@ -116,11 +114,10 @@ impl WidgetImpl for Slider {
             }
         };
 
-        snapshot.append_color(&BACKGROUND_COLOUR, &widget_rect);
+        snapshot.append_color(&WIDGET_BACKGROUND_COLOUR, &widget_rect);
         snapshot.append_color(&self.fill_colour.get(), &fill_rect);
-        // todo: get actual accent colour
         if self.show_bar.get() {
-            snapshot.append_color(&adw::AccentColor::Blue.to_rgba(), &bar_rect);
+            snapshot.append_color(&accent_colour(), &bar_rect);
         }
 
         if self.show_ticks.get() {
@@ -203,6 +200,12 @@ impl crate::ui::slider::Slider {
         self.queue_draw();
     }
 
+    pub(crate) fn set_value(&self, value: f64) {
+        let range = self.imp().value_range.get();
+        self.imp().value.set(value.clamp(range.min, range.max));
+        self.queue_draw();
+    }
+
     pub(crate) fn reset(&self) {
         self.imp().value.set(self.imp().value_range.get().default);
         self.queue_draw();
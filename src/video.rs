@@ -1,4 +1,11 @@
+pub mod discover;
+pub mod encoder;
 pub mod export;
+pub mod export_queue;
 pub mod metadata;
 pub mod player;
+pub mod preset;
+pub mod selectable;
+pub mod subtitles;
+pub mod tags;
 pub mod thumbnail;
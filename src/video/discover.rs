@@ -0,0 +1,279 @@
+use crate::ui::preview::Orientation;
+use crate::video::metadata::{
+    AudioCodec, AudioStreamInfo, ContainerFormat, SubtitleCodec, SubtitleStreamInfo, VideoCodec,
+    VideoContainerInfo, VideoInfo, VideoStreamInfo,
+};
+use anyhow::{anyhow, Error};
+use gst::ClockTime;
+use gst_pbutils::prelude::DiscovererStreamInfoExt;
+use gst_pbutils::{
+    DiscovererAudioInfo, DiscovererInfo, DiscovererResult, DiscovererSubtitleInfo,
+    DiscovererVideoInfo,
+};
+
+/// Discovers the metadata for both the preview and the command line, no playbin needed.
+pub fn discover_uri(uri: &str) -> Result<VideoInfo, Error> {
+    let discoverer = gst_pbutils::Discoverer::new(ClockTime::from_seconds(5))?;
+    let info = discoverer.discover_uri(uri)?;
+
+    match info.result() {
+        DiscovererResult::Ok => VideoInfo::try_from(info),
+        result => Err(anyhow!("could not read {uri}: {result:?}")),
+    }
+}
+
+/// Maps the eight exif style `image-orientation` tag values, mirroring happens before rotating.
+pub fn orientation_from_tag(value: &str) -> Option<Orientation> {
+    let (mirrored, degrees) = match value.strip_prefix("flip-rotate-") {
+        Some(degrees) => (true, degrees),
+        None => (false, value.strip_prefix("rotate-")?),
+    };
+
+    match degrees.parse::<u32>().ok()? {
+        degrees @ (0 | 90 | 180 | 270) => {
+            Some(Orientation::new_with_base(degrees as f32, mirrored))
+        }
+        _ => None,
+    }
+}
+
+fn tag_string<T>(tags: &gst::TagListRef) -> Option<String>
+where
+    T: for<'a> gst::Tag<'a, TagType = &'a str>,
+{
+    tags.get::<T>().map(|tag| tag.get().to_string())
+}
+
+impl TryFrom<DiscovererInfo> for VideoInfo {
+    type Error = Error;
+
+    fn try_from(info: DiscovererInfo) -> Result<Self, Error> {
+        let container_info = VideoContainerInfo::from_discoverer(&info);
+        if container_info.video_streams.is_empty() {
+            return Err(anyhow!("no video stream found"));
+        }
+
+        let mut video_info = Self {
+            // zero when the container doesn't say, the player fills it in once playing
+            duration: info.duration().unwrap_or(ClockTime::ZERO),
+            container_info,
+            ..Default::default()
+        };
+        // playbin plays the first video stream by default, match it
+        video_info.select_video_stream(0)?;
+
+        Ok(video_info)
+    }
+}
+
+impl VideoContainerInfo {
+    fn from_discoverer(info: &DiscovererInfo) -> Self {
+        let global_tags = info.tags();
+
+        let container = global_tags
+            .as_deref()
+            .and_then(tag_string::<gst::tags::ContainerFormat>)
+            .map_or(ContainerFormat::Unknown, |desc| {
+                ContainerFormat::from_description(&desc)
+            });
+
+        let video_streams: Vec<_> = info
+            .video_streams()
+            .into_iter()
+            .map(|video| {
+                let mut stream = VideoStreamInfo::from(video);
+                // some containers only tag the codec globally
+                if stream.codec == VideoCodec::Unknown {
+                    stream.codec = global_tags
+                        .as_deref()
+                        .and_then(tag_string::<gst::tags::VideoCodec>)
+                        .map_or(VideoCodec::Unknown, |desc| {
+                            VideoCodec::from_description(&desc)
+                        });
+                }
+                stream
+            })
+            .collect();
+
+        let first_video_stream = video_streams.first();
+
+        Self {
+            container,
+            video_codec: first_video_stream.map_or(VideoCodec::Unknown, |stream| stream.codec),
+            video_bitrate: first_video_stream.map_or(0, |stream| stream.bitrate),
+            video_bit_depth: first_video_stream.map_or(8, |stream| stream.bit_depth),
+            video_streams,
+            audio_streams: info
+                .audio_streams()
+                .into_iter()
+                .map(AudioStreamInfo::from)
+                .collect(),
+            subtitle_streams: info
+                .subtitle_streams()
+                .into_iter()
+                .map(SubtitleStreamInfo::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<DiscovererVideoInfo> for VideoStreamInfo {
+    fn from(video: DiscovererVideoInfo) -> Self {
+        let tags = video.tags();
+
+        let orientation = tags
+            .as_deref()
+            .and_then(tag_string::<gst::tags::ImageOrientation>)
+            .and_then(|value| {
+                let orientation = orientation_from_tag(&value);
+                if orientation.is_none() {
+                    tracing::warn!("ignoring unknown image orientation {value}");
+                }
+                orientation
+            })
+            .unwrap_or_default();
+
+        let pixel_aspect_ratio = match video.par() {
+            par if par.numer() > 0 && par.denom() > 0 => par,
+            _ => gst::Fraction::new(1, 1),
+        };
+
+        let caps = video.caps();
+        let caps_structure = caps.as_ref().and_then(|caps| caps.structure(0));
+
+        let colorimetry = caps_structure
+            .and_then(|s| s.get::<&str>("colorimetry").ok())
+            .and_then(|value| value.parse().ok());
+        // parsers put the coded depth in the caps, the discoverer's depth is per pixel
+        let bit_depth = caps_structure
+            .and_then(|s| s.get::<u32>("bit-depth-luma").ok())
+            .unwrap_or(8);
+
+        VideoStreamInfo {
+            codec: tags
+                .as_deref()
+                .and_then(tag_string::<gst::tags::VideoCodec>)
+                .map_or(VideoCodec::Unknown, |desc| {
+                    VideoCodec::from_description(&desc)
+                }),
+            bitrate: match video.bitrate() {
+                0 => video.max_bitrate(),
+                bitrate => bitrate,
+            },
+            title: tags
+                .as_deref()
+                .and_then(tag_string::<gst::tags::Title>)
+                .unwrap_or_default(),
+            width: video.width(),
+            height: video.height(),
+            framerate: video.framerate(),
+            pixel_aspect_ratio,
+            colorimetry,
+            bit_depth,
+            orientation,
+            stream_id: video
+                .stream_id()
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl From<DiscovererAudioInfo> for AudioStreamInfo {
+    fn from(info: DiscovererAudioInfo) -> Self {
+        let tags = info.tags();
+
+        AudioStreamInfo {
+            codec: tags
+                .as_deref()
+                .and_then(tag_string::<gst::tags::AudioCodec>)
+                .map_or(AudioCodec::Unknown, |desc| {
+                    AudioCodec::from_description(&desc)
+                }),
+            title: tags
+                .as_deref()
+                .and_then(tag_string::<gst::tags::Title>)
+                .unwrap_or_default(),
+            bitrate: info.bitrate(),
+            language: info
+                .language()
+                .map(|lang| lang.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl From<DiscovererSubtitleInfo> for SubtitleStreamInfo {
+    fn from(info: DiscovererSubtitleInfo) -> Self {
+        let tags = info.tags();
+
+        let codec = info
+            .caps()
+            .and_then(|caps| {
+                caps.structure(0)
+                    .map(|s| SubtitleCodec::from_caps_name(s.name()))
+            })
+            .filter(|codec| *codec != SubtitleCodec::Unknown)
+            .or_else(|| {
+                tags.as_deref()
+                    .and_then(tag_string::<gst::tags::SubtitleCodec>)
+                    .map(|desc| SubtitleCodec::from_description(&desc))
+            })
+            .unwrap_or(SubtitleCodec::Unknown);
+
+        SubtitleStreamInfo {
+            codec,
+            title: tags
+                .as_deref()
+                .and_then(tag_string::<gst::tags::Title>)
+                .unwrap_or_default(),
+            language: info
+                .language()
+                .map(|lang| lang.to_string())
+                .unwrap_or_default(),
+            stream_id: info
+                .stream_id()
+                .map(|id| id.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_image_orientations() {
+        let expected = [
+            ("rotate-0", 0.0, false),
+            ("rotate-90", 90.0, false),
+            ("rotate-180", 180.0, false),
+            ("rotate-270", 270.0, false),
+            ("flip-rotate-0", 0.0, true),
+            ("flip-rotate-90", 90.0, true),
+            ("flip-rotate-180", 180.0, true),
+            ("flip-rotate-270", 270.0, true),
+        ];
+
+        for (tag, angle, mirrored) in expected {
+            let orientation = orientation_from_tag(tag).unwrap();
+            assert_eq!(orientation.base_angle, angle, "{tag}");
+            assert_eq!(orientation.mirrored, mirrored, "{tag}");
+        }
+
+        assert!(orientation_from_tag("rotate-45").is_none());
+        assert!(orientation_from_tag("upside-down").is_none());
+    }
+
+    #[test]
+    fn reset_keeps_tagged_mirroring() {
+        let mut orientation = orientation_from_tag("flip-rotate-90").unwrap();
+        orientation.mirror_horizontally();
+        orientation.rotate_90_clockwise();
+        orientation.reset();
+
+        assert!(orientation.mirrored);
+        assert_eq!(orientation.absolute_angle(), 90.0);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::ui::sidebar::OutputContainerSettings;
+use gst::prelude::{ElementExt, GstObjectExt, ObjectExt, StaticType, ToValue};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncodePass {
+    Single,
+    /// Only gathers stats for the second pass, the output is overwritten.
+    First,
+    Second,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BitrateUnit {
+    Bits,
+    Kilobits,
+}
+
+// encoders disagree on the property name and unit for bitrate
+fn bitrate_property(factory_name: &str) -> Option<(&'static str, BitrateUnit)> {
+    match factory_name {
+        "x264enc" | "x265enc" | "mpeg2enc" | "lamemp3enc" => {
+            Some(("bitrate", BitrateUnit::Kilobits))
+        }
+        "svtav1enc" | "av1enc" => Some(("target-bitrate", BitrateUnit::Kilobits)),
+        "vp8enc" | "vp9enc" => Some(("target-bitrate", BitrateUnit::Bits)),
+        "rav1enc" | "openh264enc" | "fdkaacenc" | "voaacenc" | "opusenc" | "vorbisenc" => {
+            Some(("bitrate", BitrateUnit::Bits))
+        }
+        name if name.starts_with("avenc_") => Some(("bitrate", BitrateUnit::Bits)),
+        // hardware encoders (nvcodec, va, qsv) all use kbit/s
+        name if name.starts_with("nv") || name.starts_with("va") || name.starts_with("qsv") => {
+            Some(("bitrate", BitrateUnit::Kilobits))
+        }
+        _ => None,
+    }
+}
+
+fn set_bitrate(encoder: &gst::Element, bitrate: u32) {
+    let Some(factory) = encoder.factory() else {
+        return;
+    };
+
+    let Some((property, unit)) = bitrate_property(factory.name().as_str()) else {
+        tracing::warn!("unknown bitrate property for {}", factory.name());
+        return;
+    };
+
+    let Some(pspec) = encoder.find_property(property) else {
+        return;
+    };
+
+    let value = match unit {
+        BitrateUnit::Bits => bitrate,
+        BitrateUnit::Kilobits => bitrate / 1000,
+    };
+
+    // property types vary between u32, i32 and u64
+    let value = match pspec.value_type() {
+        t if t == u64::static_type() => (value as u64).to_value(),
+        t if t == i32::static_type() => (value.min(i32::MAX as u32) as i32).to_value(),
+        _ => value.to_value(),
+    };
+    encoder.set_property_from_value(property, &value);
+}
+
+/// Stats file shared by the passes of one export, queued and cli exports can run at once.
+pub fn multipass_cache_file(save_uri: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    save_uri.hash(&mut hasher);
+    std::env::temp_dir().join(format!(
+        "moviola-multipass-{}-{:x}.log",
+        std::process::id(),
+        hasher.finish()
+    ))
+}
+
+pub fn remove_multipass_cache_file(save_uri: &str) {
+    let file = multipass_cache_file(save_uri);
+    // x264 keeps its macroblock tree next to the stats
+    let mbtree = file.with_extension("log.mbtree");
+    for path in [file, mbtree] {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+fn set_pass(encoder: &gst::Element, pass: EncodePass, cache_file: &Path) {
+    let Some(factory) = encoder.factory() else {
+        return;
+    };
+
+    let (property, value) = match (factory.name().as_str(), pass) {
+        (_, EncodePass::Single) => return,
+        ("x264enc", EncodePass::First) => ("pass", "pass1"),
+        ("x264enc", EncodePass::Second) => ("pass", "pass2"),
+        ("vp8enc" | "vp9enc", EncodePass::First) => ("multipass-mode", "first-pass"),
+        ("vp8enc" | "vp9enc", EncodePass::Second) => ("multipass-mode", "last-pass"),
+        (name, _) => {
+            tracing::warn!("{name} has no multipass support, encoding in one pass");
+            return;
+        }
+    };
+
+    encoder.set_property_from_str(property, value);
+    encoder.set_property("multipass-cache-file", cache_file.to_str());
+}
+
+/// Applies the bitrates from the export settings to encoders as encodebin creates them.
+pub fn configure_encoder(
+    element: &gst::Element,
+    settings: &OutputContainerSettings,
+    pass: EncodePass,
+    cache_file: &Path,
+) {
+    let Some(factory) = element.factory() else {
+        return;
+    };
+
+    let klass = factory.klass();
+    if !klass.contains("Encoder") {
+        return;
+    }
+
+    if klass.contains("Video") {
+        let codec = settings.video_codec.encodable();
+        if codec.uses_bitrate() && settings.video_bitrate != 0 {
+            set_bitrate(element, settings.video_bitrate);
+        }
+        set_pass(element, pass, cache_file);
+    } else if klass.contains("Audio") {
+        let codec = settings.audio_codec.encodable();
+        if codec.uses_bitrate() && settings.audio_bitrate != 0 {
+            set_bitrate(element, settings.audio_bitrate);
+        }
+    }
+}
@@ -1,10 +1,17 @@
-use crate::app::{App, AppMsg};
-use crate::renderer::renderer::RenderedFrame;
-use crate::renderer::{FrameSize, RenderCmd, TimerCmd};
+use crate::renderer::renderer::{FrameMemory, RenderedFrame};
+use crate::renderer::{FrameSize, RenderCmd, ToneMapping};
 use crate::ui::sidebar::{ControlsExportSettings, OutputContainerSettings};
-use crate::video::metadata::VideoInfo;
-use crate::video::player::{video_appsink, AppSinkUsage, Player};
-use anyhow::Error;
+use crate::video::encoder::{configure_encoder, multipass_cache_file, EncodePass};
+use crate::video::metadata::{
+    has_video_encoder_for_caps, VideoContainerInfo, VideoInfo, AUDIO_BITRATE_DEFAULT,
+};
+use crate::video::selectable::SelectableOption;
+use crate::video::subtitles::{
+    is_subtitle_caps, link_to_muxer, subtitle_appsrc, BurnInProgress, SubtitleData,
+    SubtitleRouting, DECODE_CAPS,
+};
+use crate::video::tags::{find_muxer, SharedSourceMetadata};
+use anyhow::{anyhow, Error};
 use gst::prelude::{
     BufferPoolExt, BufferPoolExtManual, Cast, ElementExt, ElementExtManual, GstBinExt,
     GstBinExtManual, GstObjectExt, ObjectExt, PadExt,
@@ -14,10 +21,10 @@ use gst_app::{AppSink, AppSrc};
 use gst_pbutils::prelude::EncodingProfileBuilder;
 use gst_pbutils::EncodingContainerProfile;
 use gst_video::VideoBufferPoolConfig;
-use relm4::ComponentSender;
 use std::ops::Deref;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{mpsc, Arc, Condvar, Mutex};
-use std::thread;
 use std::time::SystemTime;
 
 #[derive(Debug, Copy, Clone)]
@@ -32,47 +39,197 @@ impl TimelineExportSettings {
     }
 }
 
-impl Player {
-    pub fn export_video(
-        &mut self,
-        source_uri: String,
+pub fn frame_count(duration: ClockTime, framerate: gst::Fraction) -> u32 {
+    let fps = framerate.numer() as f64 / framerate.denom() as f64;
+    (duration.seconds_f64() * fps).ceil() as u32
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ExportEstimate {
+    pub duration: ClockTime,
+    pub output_size: FrameSize,
+    pub frame_count: u32,
+    /// None when the video codec picks its own rate (lossless and intra codecs).
+    pub file_size: Option<u64>,
+}
+
+impl ExportEstimate {
+    pub fn summary(&self) -> String {
+        let seconds = self.duration.seconds();
+        let size = match self.file_size {
+            Some(bytes) => format!("about {:.1} MB", bytes as f64 / 1e6),
+            None => "unknown".to_string(),
+        };
+
+        format!(
+            "Duration: {}:{:02}:{:02}\nResolution: {}×{}\nFrames: {}\nFile size: {size}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            self.output_size.width,
+            self.output_size.height,
+            self.frame_count,
+        )
+    }
+}
+
+pub fn estimate_export(
+    timeline_settings: TimelineExportSettings,
+    framerate: gst::Fraction,
+    output_size: FrameSize,
+    container: &OutputContainerSettings,
+    info: &VideoContainerInfo,
+) -> ExportEstimate {
+    let duration = timeline_settings.duration();
+    let audio_stream = info.audio_streams.get(container.audio_stream_idx as usize);
+
+    let audio_bitrate = match (container.audio_bitrate, audio_stream) {
+        _ if container.no_audio || audio_stream.is_none() => 0,
+        (0, Some(stream)) => stream.bitrate,
+        (bitrate, _) => bitrate,
+    };
+    let video_bitrate = match container.video_bitrate {
+        0 => info.video_bitrate,
+        bitrate => bitrate,
+    };
+
+    let file_size = if let Some(target_size) = container.target_size {
+        Some(target_size)
+    } else if container.video_codec.uses_bitrate() && video_bitrate != 0 {
+        let bits = (video_bitrate as u64 + audio_bitrate as u64) as f64 * duration.seconds_f64();
+        Some((bits / 8.0) as u64)
+    } else {
+        None
+    };
+
+    ExportEstimate {
+        duration,
+        output_size,
+        frame_count: frame_count(duration, framerate),
+        file_size,
+    }
+}
+
+// muxer overhead and encoder rate control slack
+const TARGET_SIZE_MARGIN: f64 = 0.95;
+const TARGET_SIZE_MAX_RETRIES: u32 = 3;
+// below this encoders fall apart, and a bitrate of 0 leaves them on their defaults
+const MIN_VIDEO_BITRATE: u32 = 50_000;
+
+/// Video bitrate that fits `duration` of video and audio into `target_size` bytes.
+pub fn target_size_video_bitrate(
+    target_size: u64,
+    duration: ClockTime,
+    audio_bitrate: u32,
+) -> Result<u32, Error> {
+    let seconds = duration.seconds_f64().max(0.001);
+    let total_bitrate = target_size as f64 * 8.0 / seconds * TARGET_SIZE_MARGIN;
+    let video_bitrate = total_bitrate - audio_bitrate as f64;
+
+    if video_bitrate < MIN_VIDEO_BITRATE as f64 {
+        return Err(anyhow!("target size too small for the audio and duration"));
+    }
+    Ok(video_bitrate as u32)
+}
+
+/// Lowers the video bitrate by the overshoot, spread over the duration.
+fn retry_video_bitrate(
+    video_bitrate: u32,
+    target_size: u64,
+    actual_size: u64,
+    duration: ClockTime,
+) -> u32 {
+    let seconds = duration.seconds_f64().max(0.001);
+    let overshoot_bitrate = actual_size.saturating_sub(target_size) as f64 * 8.0 / seconds;
+    let bitrate = (video_bitrate as f64 - overshoot_bitrate) * TARGET_SIZE_MARGIN;
+    (bitrate as u32).max(MIN_VIDEO_BITRATE)
+}
+
+/// Progress of an export that has to fit a file size, across encoder passes and retries.
+#[derive(Debug, Clone)]
+pub struct TargetSizeExport {
+    pub save_uri: String,
+    pub video_bitrate: u32,
+    pub pass: EncodePass,
+    target_size: u64,
+    duration: ClockTime,
+    two_pass: bool,
+    retries: u32,
+}
+
+impl TargetSizeExport {
+    pub fn new(
         save_uri: String,
-        timeline_settings: TimelineExportSettings,
-        controls_export_settings: ControlsExportSettings,
-        output_size: FrameSize,
-        frame_receiver: mpsc::Receiver<RenderedFrame>,
-        app_sender: ComponentSender<App>,
-        sample_sender: mpsc::Sender<RenderCmd>,
-        timer_sender: mpsc::Sender<TimerCmd>,
-    ) {
-        self.reset_pipeline();
-
-        let video_app_sink = video_appsink(
-            app_sender.clone(),
-            sample_sender,
-            timer_sender,
-            AppSinkUsage::Export,
-        );
+        target_size: u64,
+        duration: ClockTime,
+        container: &OutputContainerSettings,
+    ) -> Result<Self, Error> {
+        let audio_bitrate = match container.audio_bitrate {
+            _ if container.no_audio => 0,
+            0 => AUDIO_BITRATE_DEFAULT,
+            bitrate => bitrate,
+        };
+        let two_pass = container.video_codec.encodable().supports_two_pass();
 
-        let (decode, encode) = start_export_video(
-            source_uri,
+        Ok(Self {
             save_uri,
-            self.info.clone(),
-            output_size,
-            controls_export_settings,
-            timeline_settings,
-            video_app_sink,
-            frame_receiver,
-        );
+            video_bitrate: target_size_video_bitrate(target_size, duration, audio_bitrate)?,
+            pass: if two_pass {
+                EncodePass::First
+            } else {
+                EncodePass::Single
+            },
+            target_size,
+            duration,
+            two_pass,
+            retries: 0,
+        })
+    }
 
-        thread::spawn(move || {
-            wait_export_done_and_cleanup(decode, encode);
-            app_sender.input(AppMsg::ExportDone);
-        });
+    /// Moves on after a finished pass, returns false once the file is done.
+    pub fn advance(&mut self) -> bool {
+        if self.pass == EncodePass::First {
+            self.pass = EncodePass::Second;
+            return true;
+        }
+
+        let Ok(actual_size) = std::fs::metadata(&self.save_uri).map(|m| m.len()) else {
+            return false;
+        };
+
+        if actual_size <= self.target_size {
+            return false;
+        }
+
+        if self.retries == TARGET_SIZE_MAX_RETRIES || self.video_bitrate == MIN_VIDEO_BITRATE {
+            tracing::warn!(
+                "export is {actual_size} bytes, still over the {} byte target",
+                self.target_size
+            );
+            return false;
+        }
+
+        self.retries += 1;
+        self.video_bitrate = retry_video_bitrate(
+            self.video_bitrate,
+            self.target_size,
+            actual_size,
+            self.duration,
+        );
+        self.pass = if self.two_pass {
+            EncodePass::First
+        } else {
+            EncodePass::Single
+        };
+        tracing::info!(
+            "export is {actual_size} bytes, retrying at {}bps",
+            self.video_bitrate
+        );
+        true
     }
 }
 
-fn wait_for_eos(bus: gst::Bus) {
+fn wait_for_eos(bus: gst::Bus) -> Result<(), Error> {
     let now = SystemTime::now();
 
     for msg in bus.iter_timed(ClockTime::NONE) {
@@ -83,63 +240,187 @@ fn wait_for_eos(bus: gst::Bus) {
                 println!("Done? in {:?}", now.elapsed());
                 break;
             }
-            MessageView::Error(err) => {
-                println!(
-                    "Error from {:?}: {} ({:?})",
-                    err.src().map(|s| s.path_string()),
-                    err.error(),
-                    err.debug()
-                );
-                break;
-            }
+            MessageView::Error(err) => return Err(pipeline_error(err)),
             _ => (),
         }
     }
+
+    Ok(())
+}
+
+fn pipeline_error(err: &gst::message::Error) -> Error {
+    anyhow!(
+        "error from {:?}: {} ({:?})",
+        err.src().map(|s| s.path_string()),
+        err.error(),
+        err.debug()
+    )
+}
+
+/// Error posted on the pipeline's bus so far, without waiting.
+pub fn take_pipeline_error(pipeline: &gst::Pipeline) -> Option<Error> {
+    let msg = pipeline.bus()?.pop_filtered(&[gst::MessageType::Error])?;
+
+    match msg.view() {
+        gst::MessageView::Error(err) => Some(pipeline_error(err)),
+        _ => None,
+    }
+}
+
+/// Encoded caps (with profile and level) and the raw caps the encoder is fed.
+pub fn video_encoding_caps(container: &OutputContainerSettings) -> (gst::Caps, gst::Caps) {
+    let codec = container.video_codec.encodable();
+    let profile = container
+        .video_profile
+        .or_else(|| codec.default_profile(container.chroma_subsampling, container.bit_depth));
+    let codec_caps = codec.caps_with_profile(profile, container.video_level);
+
+    let raw_caps = gst_video::VideoCapsBuilder::new()
+        .format(container.chroma_subsampling.raw_format(container.bit_depth))
+        .build();
+
+    (codec_caps, raw_caps)
+}
+
+/// Checks the output container can store the selected codecs.
+pub fn validate_container(container: &OutputContainerSettings) -> Result<(), Error> {
+    let format = container.container;
+
+    let video_codec = container.video_codec.encodable();
+    if !video_codec.is_compatible_with(format) {
+        return Err(anyhow!(
+            "{} can not store {} video",
+            format.display(),
+            video_codec.display()
+        ));
+    }
+
+    let audio_codec = container.audio_codec.encodable();
+    if !container.no_audio && !audio_codec.is_compatible_with(format) {
+        return Err(anyhow!(
+            "{} can not store {} audio",
+            format.display(),
+            audio_codec.display()
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn validate_video_encoding(container: &OutputContainerSettings) -> Result<(), Error> {
+    let codec = container.video_codec.encodable();
+    let (codec_caps, raw_caps) = video_encoding_caps(container);
+
+    if has_video_encoder_for_caps(&codec_caps, &raw_caps) {
+        return Ok(());
+    }
+
+    let any_raw = gst_video::VideoCapsBuilder::new().build();
+    if !has_video_encoder_for_caps(&codec_caps, &any_raw) {
+        return Err(anyhow!(
+            "{} encoder does not support the selected profile or level",
+            codec.display()
+        ));
+    }
+
+    Err(anyhow!(
+        "{} encoder does not accept {} {} input",
+        codec.display(),
+        container.chroma_subsampling.display(),
+        container.bit_depth.display()
+    ))
+}
+
+/// Tone mapped HDR is exported as bt709, everything else keeps the source's colours.
+pub fn export_colorimetry(
+    info: &VideoInfo,
+    tone_mapping: ToneMapping,
+) -> Option<gst_video::VideoColorimetry> {
+    if info.is_hdr() && tone_mapping != ToneMapping::Off {
+        return Some(gst_video::VideoColorimetry::new(
+            gst_video::VideoColorRange::Range16_235,
+            gst_video::VideoColorMatrix::Bt709,
+            gst_video::VideoTransferFunction::Bt709,
+            gst_video::VideoColorPrimaries::Bt709,
+        ));
+    }
+
+    info.colorimetry
 }
 
 fn build_container_profile(
     info: &VideoInfo,
     container: OutputContainerSettings,
-) -> EncodingContainerProfile {
+) -> Result<EncodingContainerProfile, Error> {
     let container_caps = container.container.caps_builder().build();
-    let video_caps = container.video_codec.caps_builder().build();
+
+    validate_video_encoding(&container)?;
+    let (video_caps, mut raw_caps) = video_encoding_caps(&container);
+
+    // tags the output so players don't guess the colours from the frame size
+    if let Some(colorimetry) = export_colorimetry(info, container.tone_mapping) {
+        raw_caps
+            .make_mut()
+            .set("colorimetry", colorimetry.to_string());
+    }
 
     let video_profile = gst_pbutils::EncodingVideoProfile::builder(&video_caps)
         .name("video_profile")
+        .restriction(&raw_caps)
         .build();
     let container_builder = EncodingContainerProfile::builder(&container_caps)
         .name("Container")
         .add_profile(video_profile);
 
     if container.no_audio {
-        container_builder.build()
+        Ok(container_builder.build())
     } else {
         let audio_stream = &info.container_info.audio_streams[container.audio_stream_idx as usize];
 
-        let audio_caps = audio_stream.codec.caps_builder().build();
+        let audio_caps = audio_stream.codec.encodable().caps_builder().build();
         let audio_profile = gst_pbutils::EncodingAudioProfile::builder(&audio_caps)
             .name("audio_profile")
             .build();
 
-        container_builder.add_profile(audio_profile).build()
+        Ok(container_builder.add_profile(audio_profile).build())
     }
 }
 
-fn start_export_video(
+pub fn start_export_video(
     source_uri: String,
     save_uri: String,
     info: VideoInfo,
     output_size: FrameSize,
+    pass: EncodePass,
     encoding_settings: ControlsExportSettings,
     timeline_settings: TimelineExportSettings,
     video_appsink: AppSink,
     frame_receiver: mpsc::Receiver<RenderedFrame>,
+    overlay_sender: mpsc::Sender<RenderCmd>,
+    burn_in_progress: BurnInProgress,
 ) -> (gst::Pipeline, gst::Pipeline) {
     let (audio_sender, audio_recv) = mpsc::channel();
+    let source_metadata = SharedSourceMetadata::default();
+    let (subtitle_routing, subtitle_recvs) = SubtitleRouting::new(
+        &encoding_settings.subtitles,
+        &info,
+        encoding_settings.container.container,
+        output_size,
+        overlay_sender,
+        burn_in_progress,
+    );
 
     let decode = launch_decode_pipeline(
         !encoding_settings.container.no_audio,
+        info.video_stream_idx,
+        info.container_info
+            .video_streams
+            .get(info.video_stream_idx as usize)
+            .map(|stream| stream.stream_id.clone())
+            .unwrap_or_default(),
         audio_sender,
+        subtitle_routing,
+        source_metadata.clone(),
         timeline_settings,
         source_uri,
         video_appsink,
@@ -148,11 +429,14 @@ fn start_export_video(
     let encode = launch_encode_pipeline(
         frame_receiver,
         audio_recv,
+        subtitle_recvs,
+        source_metadata,
         info,
         output_size,
+        pass,
         encoding_settings,
         save_uri,
-        timeline_settings.start,
+        timeline_settings,
     )
     .expect("could not launch encode pipeline");
     (decode, encode)
@@ -160,7 +444,11 @@ fn start_export_video(
 
 fn launch_decode_pipeline(
     audio_enabled: bool,
+    video_stream_idx: u32,
+    video_stream_id: String,
     audio_sender: mpsc::Sender<Option<gst::Sample>>,
+    subtitle_routing: SubtitleRouting,
+    source_metadata: SharedSourceMetadata,
     timeline_settings: TimelineExportSettings,
     source_uri: String,
     video_appsink: AppSink,
@@ -168,6 +456,7 @@ fn launch_decode_pipeline(
     let pipeline = gst::Pipeline::default();
     let decode_bin = gst::ElementFactory::make("uridecodebin")
         .property("uri", source_uri.as_str())
+        .property("caps", gst::Caps::from_str(DECODE_CAPS)?)
         .build()?;
 
     pipeline
@@ -177,29 +466,38 @@ fn launch_decode_pipeline(
     let pipeline_weak = pipeline.downgrade();
     let c = Arc::new((Mutex::new(0), Condvar::new()));
     let c2 = Arc::clone(&c);
+    // uridecodebin doesn't expose pads in stream order, only count them when there are no ids
+    let video_pads = AtomicU32::new(0);
+    let is_selected_video = move |pad: &gst::Pad| {
+        if video_stream_id.is_empty() {
+            return video_pads.fetch_add(1, Ordering::Relaxed) == video_stream_idx;
+        }
+        pad.stream_id()
+            .is_some_and(|id| id.as_str() == video_stream_id)
+    };
+
+    let pads_routing = subtitle_routing.clone();
+    decode_bin.connect_no_more_pads(move |_| pads_routing.no_more_pads());
 
     decode_bin.connect_pad_added(move |_dbin, dbin_src_pad| {
         let Some(pipeline) = pipeline_weak.upgrade() else {
             return;
         };
 
-        let (is_audio, is_video) = {
-            let media_type = dbin_src_pad.current_caps().and_then(|caps| {
-                caps.structure(0).map(|s| {
-                    let name = s.name();
-                    (name.starts_with("audio/"), name.starts_with("video/"))
-                })
-            });
+        let media_type = dbin_src_pad
+            .current_caps()
+            .and_then(|caps| caps.structure(0).map(|s| s.name().to_string()));
 
-            match media_type {
-                None => {
-                    println!("Failed to get media type from pad {}", dbin_src_pad.name());
-                    return;
-                }
-                Some(media_type) => media_type,
-            }
+        let Some(media_type) = media_type else {
+            println!("Failed to get media type from pad {}", dbin_src_pad.name());
+            return;
         };
 
+        let is_audio = media_type.starts_with("audio/");
+        // other video streams are left unlinked, like subtitles that aren't exported
+        let is_video = media_type.starts_with("video/") && is_selected_video(dbin_src_pad);
+        let is_subtitle = is_subtitle_caps(&media_type);
+
         let audio_sample_sender = audio_sender.clone();
         let audio_eos_sender = audio_sender.clone();
         let link_to_encode_bin = |is_audio, is_video| -> Result<(), Error> {
@@ -252,6 +550,39 @@ fn launch_decode_pipeline(
 
                 let sink_pad = queue.static_pad("sink").expect("queue has no sinkpad");
                 dbin_src_pad.link(&sink_pad)?;
+            } else if is_subtitle {
+                let Some(stream_idx) = subtitle_routing.stream_idx(dbin_src_pad) else {
+                    tracing::warn!("no subtitle stream matches pad {}", dbin_src_pad.name());
+                    return Ok(());
+                };
+
+                let link_subtitles = || -> Result<(), Error> {
+                    let Some(elements) =
+                        subtitle_routing.elements_for_stream(stream_idx, &media_type)?
+                    else {
+                        return Ok(());
+                    };
+
+                    pipeline
+                        .add_many(&elements)
+                        .expect("failed to add subtitle elements to pipeline");
+                    gst::Element::link_many(&elements)?;
+
+                    for e in &elements {
+                        e.sync_state_with_parent()?;
+                    }
+
+                    let sink_pad = elements[0]
+                        .static_pad("sink")
+                        .expect("queue has no sinkpad");
+                    dbin_src_pad.link(&sink_pad)?;
+                    Ok(())
+                };
+
+                if let Err(err) = link_subtitles() {
+                    subtitle_routing.link_failed(stream_idx);
+                    return Err(err);
+                }
             }
 
             Ok(())
@@ -262,6 +593,15 @@ fn launch_decode_pipeline(
         }
 
         if is_audio || is_video {
+            // tags and chapters are sticky, so they pass through when the pad starts flowing
+            let pad_metadata = source_metadata.clone();
+            dbin_src_pad.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+                if let Some(event) = info.event() {
+                    pad_metadata.lock().unwrap().collect_event(event);
+                }
+                gst::PadProbeReturn::Ok
+            });
+
             //todo: see how it works with multiple audio tracks
             let (lock, cvar) = &*c2;
             let mut pads_connected = lock.lock().unwrap();
@@ -297,12 +637,16 @@ fn launch_decode_pipeline(
 fn launch_encode_pipeline(
     frame_receiver: mpsc::Receiver<RenderedFrame>,
     audio_recv: mpsc::Receiver<Option<gst::Sample>>,
+    subtitle_recvs: Vec<(u32, mpsc::Receiver<SubtitleData>)>,
+    source_metadata: SharedSourceMetadata,
     info: VideoInfo,
     output_size: FrameSize,
+    pass: EncodePass,
     encoding_settings: ControlsExportSettings,
     save_uri: String,
-    audio_start_offset: gst::ClockTime,
+    timeline_settings: TimelineExportSettings,
 ) -> Result<gst::Pipeline, Error> {
+    let audio_start_offset = timeline_settings.start;
     //  encoders don't accept DMABUF so not used right now. They might be downloading the current dmabuf
     //  which is stored linearly and in RGBA so output fine, if slow.
     let _dma_caps = gst_video::VideoCapsBuilder::new()
@@ -318,18 +662,42 @@ fn launch_encode_pipeline(
         .pixel_aspect_ratio(gst::Fraction::new(1, 1))
         .build();
 
-    let gst_video_info = gst_video::VideoInfo::builder(
+    // the muxer writes this as the container's aspect flag
+    let pixel_aspect_ratio = if encoding_settings.container.keep_anamorphic {
+        info.pixel_aspect_ratio
+    } else {
+        gst::Fraction::new(1, 1)
+    };
+
+    let mut video_info_builder = gst_video::VideoInfo::builder(
         gst_video::VideoFormat::Rgba,
         output_size.width,
         output_size.height,
     )
     .fps(info.framerate.clone())
-    .build()
-    .expect("Couldn't build video info");
+    .par(pixel_aspect_ratio);
+
+    // rendered frames are full range rgb, otherwise in the colours being exported
+    let colorimetry = export_colorimetry(&info, encoding_settings.container.tone_mapping);
+    let rgb_colorimetry = colorimetry.map(|colorimetry| {
+        gst_video::VideoColorimetry::new(
+            gst_video::VideoColorRange::Range0_255,
+            gst_video::VideoColorMatrix::Rgb,
+            colorimetry.transfer(),
+            colorimetry.primaries(),
+        )
+    });
+    if let Some(rgb_colorimetry) = rgb_colorimetry.as_ref() {
+        video_info_builder = video_info_builder.colorimetry(rgb_colorimetry);
+    }
+
+    let gst_video_info = video_info_builder
+        .build()
+        .expect("Couldn't build video info");
 
     let pipeline = gst::Pipeline::default();
 
-    let container_profile = build_container_profile(&info, encoding_settings.container);
+    let container_profile = build_container_profile(&info, encoding_settings.container)?;
     let encode_bin = gst::ElementFactory::make("encodebin")
         .property("profile", &container_profile)
         .build()?;
@@ -337,8 +705,31 @@ fn launch_encode_pipeline(
         .property("location", save_uri.as_str())
         .build()?;
 
+    let encoder_settings = encoding_settings.container;
+    let multipass_file = multipass_cache_file(&save_uri);
+    encode_bin
+        .downcast_ref::<gst::Bin>()
+        .expect("encodebin is a bin")
+        .connect_deep_element_added(move |_, _, element| {
+            configure_encoder(element, &encoder_settings, pass, &multipass_file)
+        });
+
+    let muxer = find_muxer(&encode_bin);
+    let subtitle_muxer = muxer.clone();
+    let metadata_settings = encoding_settings.metadata.clone();
+    // muxers write tags and chapters on finishing, applying again at eos catches late tags
+    let apply_metadata = move || {
+        if let Some(muxer) = muxer.as_ref() {
+            source_metadata.lock().unwrap().apply_to_muxer(
+                muxer,
+                &metadata_settings,
+                timeline_settings,
+            );
+        }
+    };
+
     // todo: get padding required by hardware passed in
-    let row_stride = (info.width as f32 / 32.0).ceil() as i32 * 128;
+    let dmabuf_row_stride = (info.width as f32 / 32.0).ceil() as i32 * 128;
     let alloc = gst_allocator::DmaBufAllocator::new();
 
     let mut frame_count = 0;
@@ -351,26 +742,41 @@ fn launch_encode_pipeline(
             gst_app::AppSrcCallbacks::builder()
                 .need_data(move |appsrc, _| {
                     let Ok(frame) = frame_receiver.recv() else {
+                        apply_metadata();
                         let _ = appsrc.end_of_stream();
                         return;
                     };
 
+                    if frame_count == 0 {
+                        apply_metadata();
+                    }
+
                     let timer = SystemTime::now();
 
-                    let mut buffer = gst::Buffer::new();
-                    let mem = unsafe {
-                        alloc
-                            .alloc_with_flags(
-                                frame.fd,
-                                (row_stride as u32 * output_size.height) as usize,
-                                gst_allocator::FdMemoryFlags::NONE,
-                            )
-                            .expect("Failed to allocate buffer")
+                    let (mut buffer, row_stride) = match frame.memory {
+                        FrameMemory::Dmabuf { fd, .. } => {
+                            let mem = unsafe {
+                                alloc
+                                    .alloc_with_flags(
+                                        fd,
+                                        (dmabuf_row_stride as u32 * output_size.height) as usize,
+                                        gst_allocator::FdMemoryFlags::NONE,
+                                    )
+                                    .expect("Failed to allocate buffer")
+                            };
+                            let mut buffer = gst::Buffer::new();
+                            buffer.get_mut().unwrap().append_memory(mem);
+                            (buffer, dmabuf_row_stride)
+                        }
+                        // readback frames are tightly packed
+                        FrameMemory::Cpu(pixels) => (
+                            gst::Buffer::from_mut_slice(pixels),
+                            output_size.width as i32 * 4,
+                        ),
                     };
 
                     {
                         let buffer = buffer.get_mut().unwrap();
-                        buffer.append_memory(mem);
                         gst_video::VideoMeta::add_full(
                             buffer,
                             gst_video::VideoFrameFlags::empty(),
@@ -450,14 +856,42 @@ fn launch_encode_pipeline(
         audio_src_pad.link(&encode_audio_sink_pad)?;
     }
 
+    for (stream_idx, subtitle_recv) in subtitle_recvs {
+        let appsrc = subtitle_appsrc(
+            &format!("subtitle appsrc {stream_idx}"),
+            subtitle_recv,
+            timeline_settings.start,
+        );
+        pipeline.add(&appsrc)?;
+
+        let linked = match subtitle_muxer.as_ref() {
+            Some(muxer) => link_to_muxer(&encode_bin, muxer, &appsrc),
+            None => Err(anyhow!("no muxer in encodebin")),
+        };
+
+        if let Err(err) = linked {
+            tracing::warn!("dropping subtitle stream {stream_idx}: {err}");
+            pipeline.remove(&appsrc)?;
+        }
+    }
+
     pipeline.set_state(gst::State::Playing)?;
     Ok(pipeline)
 }
 
-fn wait_export_done_and_cleanup(decode: gst::Pipeline, encode: gst::Pipeline) {
-    wait_for_eos(decode.bus().unwrap());
-    wait_for_eos(encode.bus().unwrap());
+pub fn wait_export_done_and_cleanup(
+    decode: gst::Pipeline,
+    encode: gst::Pipeline,
+) -> Result<(), Error> {
+    // the encoder never sees eos if decoding failed
+    let result =
+        wait_for_eos(decode.bus().unwrap()).and_then(|_| wait_for_eos(encode.bus().unwrap()));
+
+    cleanup_export(decode, encode);
+    result
+}
 
+pub fn cleanup_export(decode: gst::Pipeline, encode: gst::Pipeline) {
     decode.set_state(gst::State::Null).unwrap();
     encode.set_state(gst::State::Null).unwrap();
 }
@@ -466,12 +900,20 @@ fn wait_export_done_and_cleanup(decode: gst::Pipeline, encode: gst::Pipeline) {
 mod tests {
     use crate::config::*;
     use crate::renderer::renderer::RenderedFrame;
-    use crate::renderer::{FrameSize, RenderMode, RenderResopnse, RendererHandler};
+    use crate::renderer::{FrameSize, RenderMode, RenderResopnse, RendererHandler, ToneMapping};
     use crate::ui::sidebar::{ControlsExportSettings, OutputContainerSettings};
+    use crate::video::discover::discover_uri;
+    use crate::video::encoder::EncodePass;
     use crate::video::export::{
-        start_export_video, wait_export_done_and_cleanup, TimelineExportSettings,
+        estimate_export, export_colorimetry, retry_video_bitrate, start_export_video,
+        target_size_video_bitrate, wait_export_done_and_cleanup, TimelineExportSettings,
+        MIN_VIDEO_BITRATE,
+    };
+    use crate::video::metadata::{
+        AudioCodec, AudioStreamInfo, BitDepth, ChromaSubsampling, ContainerFormat, VideoCodec,
+        VideoContainerInfo, VideoInfo,
     };
-    use crate::video::metadata::{AudioCodec, ContainerFormat, VideoCodec, VideoInfo};
+    use crate::video::subtitles::BurnInProgress;
     use gst::ClockTime;
     use std::sync::atomic::{AtomicBool, AtomicU64};
     use std::sync::{mpsc, Arc};
@@ -506,20 +948,13 @@ mod tests {
         })
     }
 
-    fn discover_metadata(uri: &String) -> VideoInfo {
-        let discoverer = gst_pbutils::Discoverer::new(ClockTime::from_seconds_f64(2.0))
-            .expect("unable to make discoverer");
-        let info = discoverer.discover_uri(uri.as_str()).unwrap();
-        VideoInfo::from(info.clone())
-    }
-
     // fixme: why do appsrc allocs fail due to fd.
     #[tokio::test(flavor = "multi_thread")]
     async fn export_basic_video() {
         gst::init().unwrap();
 
         let source_uri = VIDEO_TEST_FILE_SHORT.to_string();
-        let video_info = discover_metadata(&source_uri);
+        let video_info = discover_uri(&source_uri).unwrap();
         let frame_size = FrameSize::new(video_info.width, video_info.height);
 
         let (handler, render_response) = RendererHandler::new(RenderMode::AllFrames);
@@ -535,6 +970,7 @@ mod tests {
         );
 
         let render_sender = handler.render_cmd_sender();
+        let overlay_sender = handler.render_cmd_sender();
         let app_sink = gst_app::AppSink::builder()
             .enable_last_sample(true)
             .max_buffers(1)
@@ -569,6 +1005,7 @@ mod tests {
             VIDEO_EXPORT_DST.to_string(),
             video_info,
             frame_size,
+            EncodePass::Single,
             ControlsExportSettings {
                 container: OutputContainerSettings {
                     no_audio: false,
@@ -578,9 +1015,23 @@ mod tests {
                     container: ContainerFormat::MP4,
                     video_codec: VideoCodec::X265,
                     video_bitrate: 0,
+                    video_profile: None,
+                    video_level: None,
+                    chroma_subsampling: ChromaSubsampling::YUV420,
+                    bit_depth: BitDepth::Eight,
+                    max_height: None,
+                    target_size: None,
+                    keep_anamorphic: false,
+                    tone_mapping: ToneMapping::default(),
                 },
                 container_is_default: true,
                 effect_parameters: Default::default(),
+                curves: Default::default(),
+                spatial: Default::default(),
+                lut: None,
+                redactions: Default::default(),
+                metadata: Default::default(),
+                subtitles: Default::default(),
             },
             TimelineExportSettings {
                 start: ClockTime::from_seconds_f64(0.5f64),
@@ -588,9 +1039,126 @@ mod tests {
             },
             app_sink,
             frame_recv,
+            overlay_sender,
+            BurnInProgress::default(),
         );
 
         listener.await.expect("could not await on renderer listne");
-        wait_export_done_and_cleanup(decode, encode);
+        wait_export_done_and_cleanup(decode, encode).unwrap();
+    }
+
+    #[test]
+    fn target_size_leaves_room_for_audio() {
+        // 8MB over 60s with 128kbit audio
+        let bitrate =
+            target_size_video_bitrate(8_000_000, ClockTime::from_seconds(60), 128_000).unwrap();
+        let total_bytes = (bitrate + 128_000) as u64 * 60 / 8;
+
+        assert!(total_bytes <= 8_000_000);
+        assert!(total_bytes > 7_000_000);
+    }
+
+    #[test]
+    fn target_size_too_small_for_audio_fails() {
+        let duration = ClockTime::from_seconds(60);
+
+        // 1MB over 60s is ~127kbit/s, all of it taken by the audio
+        assert!(target_size_video_bitrate(1_000_000, duration, 128_000).is_err());
+        assert!(target_size_video_bitrate(1_000, duration, 0).is_err());
+    }
+
+    #[test]
+    fn retry_lowers_bitrate_by_overshoot() {
+        let duration = ClockTime::from_seconds(10);
+        let bitrate = retry_video_bitrate(1_000_000, 1_000_000, 1_250_000, duration);
+
+        // 250KB over 10s is 200kbit/s
+        assert!(bitrate < 800_000);
+        assert!(bitrate > 700_000);
+        assert_eq!(
+            retry_video_bitrate(1_000_000, 1_000_000, 1_000_000, duration),
+            950_000
+        );
+        // overshooting by more than the whole bitrate stays at the minimum
+        assert_eq!(
+            retry_video_bitrate(100_000, 1_000_000, 5_000_000, duration),
+            MIN_VIDEO_BITRATE
+        );
+    }
+
+    #[test]
+    fn tone_mapped_hdr_exports_bt709() {
+        use gst_video::VideoTransferFunction;
+
+        let mut info = VideoInfo::default();
+        info.colorimetry = Some(gst_video::VideoColorimetry::new(
+            gst_video::VideoColorRange::Range16_235,
+            gst_video::VideoColorMatrix::Bt2020,
+            VideoTransferFunction::Smpte2084,
+            gst_video::VideoColorPrimaries::Bt2020,
+        ));
+
+        let mapped = export_colorimetry(&info, ToneMapping::Hable).unwrap();
+        assert_eq!(mapped.transfer(), VideoTransferFunction::Bt709);
+
+        let kept = export_colorimetry(&info, ToneMapping::Off).unwrap();
+        assert_eq!(kept.transfer(), VideoTransferFunction::Smpte2084);
+    }
+
+    #[test]
+    fn estimate_from_trim_and_bitrates() {
+        let info = VideoContainerInfo {
+            video_bitrate: 4_000_000,
+            audio_streams: vec![AudioStreamInfo {
+                codec: AudioCodec::AAC,
+                bitrate: 128_000,
+                language: String::new(),
+                title: String::new(),
+            }],
+            ..Default::default()
+        };
+        let mut container = OutputContainerSettings {
+            no_audio: false,
+            audio_stream_idx: 0,
+            audio_codec: AudioCodec::AAC,
+            audio_bitrate: 0,
+            container: ContainerFormat::MP4,
+            video_codec: VideoCodec::X264,
+            video_bitrate: 0,
+            video_profile: None,
+            video_level: None,
+            chroma_subsampling: ChromaSubsampling::YUV420,
+            bit_depth: BitDepth::Eight,
+            max_height: None,
+            target_size: None,
+            keep_anamorphic: false,
+            tone_mapping: ToneMapping::default(),
+        };
+        let timeline = TimelineExportSettings {
+            start: ClockTime::from_seconds(10),
+            end: ClockTime::from_seconds(20),
+        };
+        let framerate = gst::Fraction::new(30000, 1001);
+        let size = FrameSize::new(1280, 720);
+
+        // match source uses the discovered bitrates
+        let estimate = estimate_export(timeline, framerate, size, &container, &info);
+        assert_eq!(estimate.duration, ClockTime::from_seconds(10));
+        assert_eq!(estimate.frame_count, 300);
+        assert_eq!(estimate.file_size, Some(5_160_000));
+
+        container.video_bitrate = 1_000_000;
+        container.no_audio = true;
+        let estimate = estimate_export(timeline, framerate, size, &container, &info);
+        assert_eq!(estimate.file_size, Some(1_250_000));
+
+        container.target_size = Some(8_000_000);
+        let estimate = estimate_export(timeline, framerate, size, &container, &info);
+        assert_eq!(estimate.file_size, Some(8_000_000));
+
+        container.target_size = None;
+        container.video_codec = VideoCodec::FFV1;
+        let estimate = estimate_export(timeline, framerate, size, &container, &info);
+        assert_eq!(estimate.file_size, None);
     }
 }
@@ -0,0 +1,230 @@
+use crate::renderer::{
+    input_caps, FramePosition, Lut, RenderCmd, RenderMode, RenderResopnse, RendererHandler,
+};
+use crate::ui::sidebar::ControlsExportSettings;
+use crate::video::encoder::{remove_multipass_cache_file, EncodePass};
+use crate::video::export::{
+    cleanup_export, frame_count, start_export_video, take_pipeline_error, validate_video_encoding,
+    wait_export_done_and_cleanup, TargetSizeExport, TimelineExportSettings,
+};
+use crate::video::metadata::VideoInfo;
+use crate::video::subtitles::BurnInProgress;
+use anyhow::Error;
+use gst_app::AppSink;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+/// Everything an export needs, captured when it is queued so later edits don't change it.
+#[derive(Debug, Clone)]
+pub struct ExportJob {
+    pub source_uri: String,
+    pub save_uri: String,
+    pub info: VideoInfo,
+    pub position: FramePosition,
+    pub timeline: TimelineExportSettings,
+    /// Includes the effect parameters.
+    pub settings: ControlsExportSettings,
+}
+
+#[derive(Debug)]
+pub enum ExportEvent {
+    Started(u32),
+    Progress {
+        job_id: u32,
+        frames_done: u32,
+        frame_count: u32,
+    },
+    Finished(u32, Result<(), Error>),
+}
+
+/// Runs exports one after another on a worker thread, each with its own renderer.
+pub struct ExportQueue {
+    job_sender: mpsc::Sender<(u32, ExportJob)>,
+    next_job_id: u32,
+    _worker: thread::JoinHandle<()>,
+}
+
+impl ExportQueue {
+    pub fn new(on_event: impl Fn(ExportEvent) + Send + 'static) -> Self {
+        let (job_sender, job_recv) = mpsc::channel::<(u32, ExportJob)>();
+
+        let worker = thread::spawn(move || {
+            for (job_id, job) in job_recv {
+                on_event(ExportEvent::Started(job_id));
+
+                let result = run_export_job(&job, |frames_done, frame_count| {
+                    on_event(ExportEvent::Progress {
+                        job_id,
+                        frames_done,
+                        frame_count,
+                    })
+                });
+
+                on_event(ExportEvent::Finished(job_id, result));
+            }
+        });
+
+        Self {
+            job_sender,
+            next_job_id: 0,
+            _worker: worker,
+        }
+    }
+
+    /// Returns the id events for this job are reported with.
+    pub fn submit(&mut self, job: ExportJob) -> u32 {
+        let job_id = self.next_job_id;
+        self.next_job_id += 1;
+
+        self.job_sender
+            .send((job_id, job))
+            .expect("export worker stopped");
+        job_id
+    }
+}
+
+/// Blocks until the job is written, running more passes for target size exports.
+pub fn run_export_job(job: &ExportJob, on_progress: impl Fn(u32, u32)) -> Result<(), Error> {
+    let container = &job.settings.container;
+    // exporting with encoder defaults instead would ignore what was asked for
+    validate_video_encoding(container)?;
+
+    let Some(target_size) = container.target_size else {
+        return run_export_pass(job, job.settings.clone(), EncodePass::Single, &on_progress);
+    };
+
+    let mut sized = TargetSizeExport::new(
+        job.save_uri.clone(),
+        target_size,
+        job.timeline.duration(),
+        container,
+    )?;
+
+    let result = loop {
+        let mut settings = job.settings.clone();
+        settings.container.video_bitrate = sized.video_bitrate;
+
+        if let Err(err) = run_export_pass(job, settings, sized.pass, &on_progress) {
+            break Err(err);
+        }
+        if !sized.advance() {
+            break Ok(());
+        }
+    };
+
+    remove_multipass_cache_file(&job.save_uri);
+    result
+}
+
+fn run_export_pass(
+    job: &ExportJob,
+    settings: ControlsExportSettings,
+    pass: EncodePass,
+    on_progress: &impl Fn(u32, u32),
+) -> Result<(), Error> {
+    let (handler, render_response) = RendererHandler::new(RenderMode::AllFrames);
+    handler.send_render_cmd(RenderCmd::PositionFrame(job.position));
+    handler.send_render_cmd(RenderCmd::UpdateEffects(settings.effect_parameters));
+    if !settings.curves.is_identity() {
+        handler.send_render_cmd(RenderCmd::UpdateCurves(settings.curves.clone()));
+    }
+    if !settings.spatial.is_default() {
+        handler.send_render_cmd(RenderCmd::UpdateSpatial(settings.spatial));
+    }
+    if let Some(lut) = &settings.lut {
+        let table = Lut::load(&lut.path)?;
+        handler.send_render_cmd(RenderCmd::UpdateLut(Some(Arc::new(table)), lut.intensity));
+    }
+    if !settings.redactions.is_empty() {
+        handler.send_render_cmd(RenderCmd::UpdateRedactions(settings.redactions.clone()));
+    }
+
+    let samples_sent = Arc::new(AtomicU32::new(0));
+    let decode_finished = Arc::new(AtomicBool::new(false));
+    let burn_in_progress = BurnInProgress::default();
+    let app_sink = export_appsink(
+        handler.render_cmd_sender(),
+        samples_sent.clone(),
+        decode_finished.clone(),
+        burn_in_progress.clone(),
+    );
+
+    let (frame_sender, frame_recv) = mpsc::channel();
+    let (decode, encode) = start_export_video(
+        job.source_uri.clone(),
+        job.save_uri.clone(),
+        job.info.clone(),
+        job.position.output_frame_size(),
+        pass,
+        settings,
+        job.timeline,
+        app_sink,
+        frame_recv,
+        handler.render_cmd_sender(),
+        burn_in_progress,
+    );
+
+    let frame_count = frame_count(job.timeline.duration(), job.info.framerate);
+    let mut frames_done = 0;
+
+    // the trim can promise a frame more than the decoder produces, so also stop once it is drained
+    while frames_done < frame_count
+        && !(decode_finished.load(Ordering::Relaxed)
+            && frames_done == samples_sent.load(Ordering::Relaxed))
+    {
+        match render_response.recv_timeout(Duration::from_millis(100)) {
+            Ok(RenderResopnse::FrameRendered(frame)) => {
+                if frame_sender.send(frame).is_err() {
+                    break;
+                }
+                frames_done += 1;
+                on_progress(frames_done, frame_count);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                if let Some(err) = take_pipeline_error(&decode) {
+                    cleanup_export(decode, encode);
+                    handler.send_render_cmd(RenderCmd::Quit);
+                    return Err(err);
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    // closing the channel ends the video stream
+    drop(frame_sender);
+    let result = wait_export_done_and_cleanup(decode, encode);
+    handler.send_render_cmd(RenderCmd::Quit);
+    result
+}
+
+fn export_appsink(
+    render_sender: mpsc::Sender<RenderCmd>,
+    samples_sent: Arc<AtomicU32>,
+    decode_finished: Arc<AtomicBool>,
+    burn_in_progress: BurnInProgress,
+) -> AppSink {
+    AppSink::builder()
+        .max_buffers(1)
+        .sync(false)
+        .caps(&input_caps())
+        .callbacks(
+            gst_app::AppSinkCallbacks::builder()
+                .new_sample(move |appsink| {
+                    let sample = appsink.pull_sample().unwrap();
+                    if let Some(pts) = sample.buffer().and_then(|buffer| buffer.pts()) {
+                        burn_in_progress.wait_for(pts);
+                    }
+                    samples_sent.fetch_add(1, Ordering::Relaxed);
+                    render_sender.send(RenderCmd::RenderSample(sample)).unwrap();
+                    Ok(gst::FlowSuccess::Ok)
+                })
+                .eos(move |_| {
+                    decode_finished.store(true, Ordering::Relaxed);
+                })
+                .build(),
+        )
+        .build()
+}
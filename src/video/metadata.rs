@@ -1,15 +1,14 @@
 use crate::ui::preview::Orientation;
+use crate::video::selectable::{has_element_for_caps, has_muxer_accepting, SelectableOption};
+use anyhow::{anyhow, Error};
 use gst::caps::{Builder, NoFeature};
 use gst::ClockTime;
-use gst_pbutils::prelude::DiscovererStreamInfoExt;
-use gst_pbutils::{DiscovererAudioInfo, DiscovererInfo};
 use relm4::gtk;
+use serde::{Deserialize, Serialize};
 
-pub static VIDEO_BITRATE_DEFAULT: u32 = 3000000;
 pub static AUDIO_BITRATE_DEFAULT: u32 = 128000;
 
-// todo: add stream title
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct AudioStreamInfo {
     pub(crate) codec: AudioCodec,
     pub(crate) bitrate: u32,
@@ -17,151 +16,191 @@ pub struct AudioStreamInfo {
     pub(crate) title: String,
 }
 
-impl From<DiscovererAudioInfo> for AudioStreamInfo {
-    fn from(info: DiscovererAudioInfo) -> Self {
-        let mut codec = AudioCodec::Unknown;
-        let mut title = "".to_string();
+#[derive(Debug, Clone, Serialize)]
+pub struct SubtitleStreamInfo {
+    pub(crate) codec: SubtitleCodec,
+    pub(crate) language: String,
+    pub(crate) title: String,
+    /// Matches the stream-start of the decoded pad, empty when the discoverer didn't give one.
+    #[serde(skip)]
+    pub(crate) stream_id: String,
+}
 
-        if let Some(tags) = info.tags() {
-            for (tag, val) in tags.iter() {
-                // println!("{tag:?}: {val:?}");
-                match tag.as_str() {
-                    "audio-codec" => {
-                        codec = AudioCodec::from_description(val.get::<&str>().unwrap());
-                    }
-                    "title" => {
-                        title = val.get::<&str>().unwrap().to_string();
-                    }
-                    _ => {}
-                }
-            }
-        }
+impl SubtitleStreamInfo {
+    pub fn display(&self) -> String {
+        let name = if !self.title.is_empty() {
+            self.title.as_str()
+        } else if !self.language.is_empty() {
+            self.language.as_str()
+        } else {
+            "Unknown"
+        };
 
-        AudioStreamInfo {
-            codec,
-            title,
-            bitrate: info.bitrate(),
-            language: if let Some(lang) = info.language() {
-                lang.to_string()
-            } else {
-                "".to_string()
-            },
-        }
+        format!("{name} ({})", self.codec.display())
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct VideoContainerInfo {
-    pub(crate) container: ContainerFormat,
-    pub(crate) video_codec: VideoCodec,
-    pub(crate) video_bitrate: u32,
-    pub(crate) audio_streams: Vec<AudioStreamInfo>,
+#[derive(Debug, Clone, Serialize)]
+pub struct VideoStreamInfo {
+    pub(crate) codec: VideoCodec,
+    pub(crate) bitrate: u32,
+    pub(crate) title: String,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    #[serde(serialize_with = "serialize_fraction")]
+    pub(crate) framerate: gst::Fraction,
+    #[serde(serialize_with = "serialize_fraction")]
+    pub(crate) pixel_aspect_ratio: gst::Fraction,
+    /// None when the stream doesn't say, decoders then guess from the frame size.
+    #[serde(serialize_with = "serialize_colorimetry")]
+    pub(crate) colorimetry: Option<gst_video::VideoColorimetry>,
+    pub(crate) bit_depth: u32,
+    pub(crate) orientation: Orientation,
+    /// Matches the stream-start of the decoded pad, empty when the discoverer didn't give one.
+    #[serde(skip)]
+    pub(crate) stream_id: String,
 }
 
-impl From<DiscovererInfo> for VideoContainerInfo {
-    fn from(info: DiscovererInfo) -> Self {
-        let mut audio_streams = vec![];
-        for audio_info in info.audio_streams() {
-            audio_streams.push(AudioStreamInfo::from(audio_info));
-        }
-
-        let mut video_codec = VideoCodec::Unknown;
-        let mut container = ContainerFormat::Unknown;
+impl VideoStreamInfo {
+    pub fn is_hdr(&self) -> bool {
+        is_hdr_colorimetry(self.colorimetry)
+    }
 
-        if let Some(tags) = info.tags() {
-            for (tag, val) in tags.iter() {
-                match tag.as_str() {
-                    "video-codec" => {
-                        video_codec = VideoCodec::from_description(val.get::<&str>().unwrap())
-                    }
-                    "container-format" => {
-                        container = ContainerFormat::from_description(val.get::<&str>().unwrap())
-                    }
-                    _ => {}
-                }
-            }
-        }
+    pub fn is_anamorphic(&self) -> bool {
+        self.pixel_aspect_ratio.numer() != self.pixel_aspect_ratio.denom()
+    }
 
-        let mut video_bitrate = 0;
-        for vidinfo in info.video_streams() {
-            video_bitrate = vidinfo.bitrate();
-        }
+    pub fn display(&self) -> String {
+        let name = if !self.title.is_empty() {
+            self.title.as_str()
+        } else {
+            self.codec.display()
+        };
 
-        Self {
-            container,
-            video_codec,
-            video_bitrate,
-            audio_streams,
-        }
+        format!("{name} ({}x{})", self.width, self.height)
     }
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct VideoContainerInfo {
+    pub(crate) container: ContainerFormat,
+    /// Codec, bitrate and bit depth of the selected video stream.
+    pub(crate) video_codec: VideoCodec,
+    pub(crate) video_bitrate: u32,
+    pub(crate) video_bit_depth: u32,
+    pub(crate) video_streams: Vec<VideoStreamInfo>,
+    pub(crate) audio_streams: Vec<AudioStreamInfo>,
+    pub(crate) subtitle_streams: Vec<SubtitleStreamInfo>,
+}
+
 impl Default for VideoContainerInfo {
     fn default() -> Self {
         Self {
             container: ContainerFormat::Unknown,
             video_codec: VideoCodec::Unknown,
             video_bitrate: 0,
+            video_bit_depth: 8,
+            video_streams: Vec::new(),
             audio_streams: Vec::new(),
+            subtitle_streams: Vec::new(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct VideoInfo {
+    #[serde(serialize_with = "serialize_seconds")]
     pub(crate) duration: ClockTime,
+    #[serde(serialize_with = "serialize_fraction")]
     pub(crate) framerate: gst::Fraction,
     pub(crate) width: u32,
     pub(crate) height: u32,
+    #[serde(serialize_with = "serialize_fraction")]
+    pub(crate) pixel_aspect_ratio: gst::Fraction,
     pub(crate) aspect_ratio: f64,
+    /// None when the stream doesn't say, decoders then guess from the frame size.
+    #[serde(serialize_with = "serialize_colorimetry")]
+    pub(crate) colorimetry: Option<gst_video::VideoColorimetry>,
+    /// Index into `container_info.video_streams`, the fields above describe that stream.
+    pub(crate) video_stream_idx: u32,
     pub(crate) container_info: VideoContainerInfo,
     pub(crate) orientation: Orientation,
 }
 
-impl From<DiscovererInfo> for VideoInfo {
-    fn from(info: DiscovererInfo) -> Self {
-        let mut width = 0;
-        let mut height = 0;
-        let mut framerate = gst::Fraction::new(0, 1);
-        let mut orientation = Orientation::default();
-
-        // todo: handle multiple video streams (need to update struct to match audiostream)
-        for vidinfo in info.video_streams() {
-            width = vidinfo.width();
-            height = vidinfo.height();
-            framerate = vidinfo.framerate();
-
-            if let Some(tags) = vidinfo.tags() {
-                for (tag, val) in tags.iter() {
-                    match tag.as_str() {
-                        "image-orientation" => {
-                            // 7 is length of "rotate-" string
-                            let degrees = val.get::<&str>().unwrap()[7..]
-                                .to_string()
-                                .parse::<u32>()
-                                .unwrap();
-                            println!("degress: {degrees}");
-                            orientation.base_angle = degrees as f32;
-                        }
-                        _ => {}
-                    }
-                }
-            }
-        }
+// probe output is meant for people and scripts, not the gst serde formats
+fn serialize_seconds<S: serde::Serializer>(time: &ClockTime, s: S) -> Result<S::Ok, S::Error> {
+    s.serialize_f64(time.seconds_f64())
+}
 
-        let aspect_ratio = width as f64 / height as f64;
-        let duration = info.duration().unwrap();
-        let container_info = VideoContainerInfo::from(info);
+fn serialize_fraction<S: serde::Serializer>(
+    fraction: &gst::Fraction,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    s.serialize_str(&format!("{}/{}", fraction.numer(), fraction.denom()))
+}
 
-        Self {
-            duration,
-            framerate,
-            width,
-            height,
-            aspect_ratio,
-            container_info,
-            orientation,
+fn serialize_colorimetry<S: serde::Serializer>(
+    colorimetry: &Option<gst_video::VideoColorimetry>,
+    s: S,
+) -> Result<S::Ok, S::Error> {
+    match colorimetry {
+        Some(colorimetry) => s.serialize_str(&colorimetry.to_string()),
+        None => s.serialize_none(),
+    }
+}
+
+fn is_hdr_colorimetry(colorimetry: Option<gst_video::VideoColorimetry>) -> bool {
+    colorimetry.is_some_and(|colorimetry| {
+        matches!(
+            colorimetry.transfer(),
+            gst_video::VideoTransferFunction::Smpte2084
+                | gst_video::VideoTransferFunction::AribStdB67
+        )
+    })
+}
+
+impl VideoInfo {
+    /// Makes the stream at `idx` the one previewed and exported.
+    pub fn select_video_stream(&mut self, idx: u32) -> Result<(), Error> {
+        let stream = self
+            .container_info
+            .video_streams
+            .get(idx as usize)
+            .ok_or_else(|| anyhow!("no video stream {idx}"))?;
+        if stream.width == 0 || stream.height == 0 {
+            return Err(anyhow!("video stream {idx} has no frame size"));
         }
+
+        self.width = stream.width;
+        self.height = stream.height;
+        self.framerate = stream.framerate;
+        self.pixel_aspect_ratio = stream.pixel_aspect_ratio;
+        self.colorimetry = stream.colorimetry;
+        self.orientation = stream.orientation;
+        self.container_info.video_codec = stream.codec;
+        self.container_info.video_bitrate = stream.bitrate;
+        self.container_info.video_bit_depth = stream.bit_depth;
+        self.video_stream_idx = idx;
+        self.aspect_ratio = self.display_width() as f64 / self.height as f64;
+
+        Ok(())
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        is_hdr_colorimetry(self.colorimetry)
+    }
+
+    pub fn is_anamorphic(&self) -> bool {
+        self.pixel_aspect_ratio.numer() != self.pixel_aspect_ratio.denom()
+    }
+
+    /// Horizontal stretch from stored pixels to square display pixels.
+    pub fn pixel_aspect(&self) -> f32 {
+        self.pixel_aspect_ratio.numer() as f32 / self.pixel_aspect_ratio.denom() as f32
+    }
+
+    pub fn display_width(&self) -> u32 {
+        (self.width as f32 * self.pixel_aspect()).round() as u32
     }
 }
 
@@ -172,28 +211,41 @@ impl Default for VideoInfo {
             framerate: gst::Fraction::from(0),
             width: 0,
             height: 0,
+            pixel_aspect_ratio: gst::Fraction::new(1, 1),
             aspect_ratio: 0.,
+            colorimetry: None,
+            video_stream_idx: 0,
             container_info: VideoContainerInfo::default(),
             orientation: Orientation::default(),
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum AudioCodec {
     AAC,
     AC3,
     DTS,
     EAC3,
+    FLAC,
+    MP3,
     OPUS,
     RAW,
+    VORBIS,
     Unknown,
     NoAudio,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum VideoCodec {
     AV1,
+    DNXHD,
+    FFV1,
+    MJPEG,
+    MPEG2,
+    PRORES,
     VP8,
     VP9,
     X264,
@@ -201,7 +253,17 @@ pub enum VideoCodec {
     Unknown,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleCodec {
+    ASS,
+    BITMAP,
+    TEXT,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ContainerFormat {
     MP4,
     MKV,
@@ -210,99 +272,184 @@ pub enum ContainerFormat {
 }
 
 impl VideoContainerInfo {
+    pub fn video_streams_string_list(&self) -> gtk::StringList {
+        let list = gtk::StringList::new(&[]);
+
+        for stream in self.video_streams.iter() {
+            list.append(stream.display().as_str());
+        }
+
+        list
+    }
+
     pub fn audio_streams_string_list(&self) -> gtk::StringList {
         let list = gtk::StringList::new(&[]);
 
         for stream in self.audio_streams.iter() {
-            list.append(stream.language.as_str());
+            if stream.language.is_empty() {
+                list.append("Unknown");
+            } else {
+                list.append(stream.language.as_str());
+            }
+        }
+
+        list
+    }
+
+    pub fn subtitle_streams_string_list(&self) -> gtk::StringList {
+        let list = gtk::StringList::new(&["None"]);
+
+        for stream in self.subtitle_streams.iter() {
+            list.append(stream.display().as_str());
         }
 
         list
     }
 }
 
-// todo: use trait to make string_list, from_string_list_index, to_string_list_index generic
 // fixme: add e-ac3 support
-impl AudioCodec {
-    pub fn display(&self) -> &str {
+impl SelectableOption for AudioCodec {
+    const OPTIONS: &'static [Self] = &[
+        AudioCodec::AAC,
+        AudioCodec::AC3,
+        AudioCodec::DTS,
+        AudioCodec::EAC3,
+        AudioCodec::FLAC,
+        AudioCodec::MP3,
+        AudioCodec::OPUS,
+        AudioCodec::RAW,
+        AudioCodec::VORBIS,
+    ];
+    const UNLISTED: Self = AudioCodec::Unknown;
+
+    fn display(&self) -> &'static str {
         match self {
             AudioCodec::AAC => "AAC",
             AudioCodec::AC3 => "AC-3",
             AudioCodec::DTS => "DTS",
             AudioCodec::EAC3 => "E-AC-3",
+            AudioCodec::FLAC => "FLAC",
+            AudioCodec::MP3 => "MP3",
             AudioCodec::OPUS => "Opus",
-            AudioCodec::RAW => "Raw",
+            AudioCodec::RAW => "PCM",
+            AudioCodec::VORBIS => "Vorbis",
             AudioCodec::Unknown => "Unknown",
             AudioCodec::NoAudio => "No Audio",
         }
     }
 
+    fn is_available(&self) -> bool {
+        match self {
+            AudioCodec::Unknown | AudioCodec::NoAudio => false,
+            // raw audio is passed straight to the muxer
+            AudioCodec::RAW => true,
+            _ => has_element_for_caps(
+                &self.caps_builder().build(),
+                gst::ElementFactoryType::AUDIO_ENCODER,
+            ),
+        }
+    }
+
+    fn is_compatible_with(&self, container: ContainerFormat) -> bool {
+        match self {
+            AudioCodec::NoAudio => true,
+            AudioCodec::Unknown => false,
+            _ => container.accepts(&self.caps_builder().build()),
+        }
+    }
+}
+
+impl AudioCodec {
     pub fn caps_builder(&self) -> Builder<NoFeature> {
         match self {
-            AudioCodec::AAC => gst::Caps::builder("audio/mpeg"),
+            AudioCodec::AAC => gst::Caps::builder("audio/mpeg").field("mpegversion", 4i32),
             AudioCodec::AC3 => gst::Caps::builder("audio/x-ac3"),
             AudioCodec::DTS => gst::Caps::builder("audio/x-dts"),
             AudioCodec::EAC3 => gst::Caps::builder("audio/x-eac3"),
+            AudioCodec::FLAC => gst::Caps::builder("audio/x-flac"),
+            AudioCodec::MP3 => gst::Caps::builder("audio/mpeg")
+                .field("mpegversion", 1i32)
+                .field("layer", 3i32),
             AudioCodec::OPUS => gst::Caps::builder("audio/x-opus"),
             AudioCodec::RAW => gst::Caps::builder("audio/x-raw"),
+            AudioCodec::VORBIS => gst::Caps::builder("audio/x-vorbis"),
             AudioCodec::Unknown => gst::Caps::builder(""),
             AudioCodec::NoAudio => gst::Caps::builder(""),
         }
     }
 
-    pub fn string_list() -> gtk::StringList {
-        gtk::StringList::new(&[
-            AudioCodec::AAC.display(),
-            AudioCodec::AC3.display(),
-            AudioCodec::DTS.display(),
-            AudioCodec::EAC3.display(),
-            AudioCodec::OPUS.display(),
-            AudioCodec::RAW.display(),
-        ])
-    }
-
-    pub fn from_string_list_index(idx: u32) -> Self {
-        match idx {
-            0 => AudioCodec::AAC,
-            1 => AudioCodec::AC3,
-            2 => AudioCodec::DTS,
-            3 => AudioCodec::EAC3,
-            4 => AudioCodec::OPUS,
-            5 => AudioCodec::RAW,
-            _ => AudioCodec::Unknown,
-        }
-    }
-
-    pub fn to_string_list_index(&self) -> u32 {
-        match self {
-            AudioCodec::AAC => 0,
-            AudioCodec::AC3 => 1,
-            AudioCodec::DTS => 2,
-            AudioCodec::EAC3 => 3,
-            AudioCodec::OPUS => 4,
-            AudioCodec::RAW => 5,
-            AudioCodec::Unknown => 100,
-            AudioCodec::NoAudio => 100,
-        }
-    }
-
     pub fn from_description(description: &str) -> Self {
         match description {
+            desc if desc.contains("MP3") || desc.contains("Layer 3") => AudioCodec::MP3,
             desc if desc.starts_with("MPEG") => AudioCodec::AAC,
             desc if desc.starts_with("Opus") => AudioCodec::OPUS,
             desc if desc.starts_with("AC-3") => AudioCodec::AC3,
             desc if desc.starts_with("E-AC-3") => AudioCodec::EAC3,
             desc if desc.starts_with("DTS") => AudioCodec::DTS,
-            desc if desc.starts_with("Raw") || desc.starts_with("Uncompressed") => AudioCodec::RAW,
+            desc if desc.contains("FLAC") => AudioCodec::FLAC,
+            desc if desc.starts_with("Vorbis") => AudioCodec::VORBIS,
+            desc if desc.starts_with("Raw")
+                || desc.starts_with("Uncompressed")
+                || desc.contains("PCM")
+                || desc.starts_with("A-Law")
+                || desc.starts_with("Mu-Law") =>
+            {
+                AudioCodec::RAW
+            }
             _ => AudioCodec::Unknown,
         }
     }
+
+    /// Codec to encode with when no local encoder exists for this one.
+    pub fn fallback(&self) -> Self {
+        match self {
+            AudioCodec::NoAudio => AudioCodec::NoAudio,
+            // keep lossless sources lossless
+            AudioCodec::FLAC => AudioCodec::RAW,
+            AudioCodec::VORBIS => AudioCodec::OPUS,
+            _ => AudioCodec::AAC,
+        }
+    }
+
+    pub fn encodable(&self) -> Self {
+        if self.is_available() {
+            *self
+        } else {
+            self.fallback()
+        }
+    }
+
+    pub fn uses_bitrate(&self) -> bool {
+        !matches!(
+            self,
+            AudioCodec::FLAC | AudioCodec::RAW | AudioCodec::Unknown | AudioCodec::NoAudio
+        )
+    }
 }
 
-impl VideoCodec {
-    pub fn display(&self) -> &str {
+impl SelectableOption for VideoCodec {
+    const OPTIONS: &'static [Self] = &[
+        VideoCodec::AV1,
+        VideoCodec::DNXHD,
+        VideoCodec::FFV1,
+        VideoCodec::MJPEG,
+        VideoCodec::MPEG2,
+        VideoCodec::PRORES,
+        VideoCodec::VP8,
+        VideoCodec::VP9,
+        VideoCodec::X264,
+        VideoCodec::X265,
+    ];
+    const UNLISTED: Self = VideoCodec::Unknown;
+
+    fn display(&self) -> &'static str {
         match self {
             VideoCodec::AV1 => "AV1",
+            VideoCodec::DNXHD => "DNxHD/HR",
+            VideoCodec::FFV1 => "FFV1",
+            VideoCodec::MJPEG => "MJPEG",
+            VideoCodec::MPEG2 => "MPEG-2",
+            VideoCodec::PRORES => "ProRes",
             VideoCodec::VP8 => "VP8",
             VideoCodec::VP9 => "VP9",
             VideoCodec::X264 => "H264",
@@ -311,69 +458,247 @@ impl VideoCodec {
         }
     }
 
+    fn is_available(&self) -> bool {
+        match self {
+            VideoCodec::Unknown => false,
+            _ => has_element_for_caps(
+                &self.caps_builder().build(),
+                gst::ElementFactoryType::VIDEO_ENCODER,
+            ),
+        }
+    }
+
+    fn is_compatible_with(&self, container: ContainerFormat) -> bool {
+        match self {
+            VideoCodec::Unknown => false,
+            _ => container.accepts(&self.caps_builder().build()),
+        }
+    }
+}
+
+impl VideoCodec {
     pub fn caps_builder(&self) -> Builder<NoFeature> {
-        // todo: should this information be set on encoding profile or caps?
+        // todo: should stream-format and alignment be set on encoding profile or caps?
         // x-av1 needs stream-format: obu-stream, alignment: tu
-        // x-265 needs stream-format: byte-stream, alignment: au, profile: alot of options (main or main 10)
-        // x-264 needs stream-format: avc/byte-stream, alignment: au, profile: alot of options (high?)
-        // x-vp9 needs profile 0
-        // x-vp8 needs profile 0
+        // x-265 needs stream-format: byte-stream, alignment: au
+        // x-264 needs stream-format: avc/byte-stream, alignment: au
+        // profiles and levels are added by caps_with_profile
         match self {
             VideoCodec::AV1 => gst::Caps::builder("video/x-av1"),
+            VideoCodec::DNXHD => gst::Caps::builder("video/x-dnxhd"),
+            VideoCodec::FFV1 => gst::Caps::builder("video/x-ffv").field("ffvversion", 1i32),
+            VideoCodec::MJPEG => gst::Caps::builder("image/jpeg"),
+            VideoCodec::MPEG2 => gst::Caps::builder("video/mpeg")
+                .field("mpegversion", 2i32)
+                .field("systemstream", false),
+            VideoCodec::PRORES => gst::Caps::builder("video/x-prores"),
             VideoCodec::VP8 => gst::Caps::builder("video/x-vp8"),
             VideoCodec::VP9 => gst::Caps::builder("video/x-vp9"),
-            VideoCodec::X264 => gst::Caps::builder("video/x-h264").field("profile", "high"),
+            VideoCodec::X264 => gst::Caps::builder("video/x-h264"),
             VideoCodec::X265 => gst::Caps::builder("video/x-h265"),
             VideoCodec::Unknown => gst::Caps::builder(""),
         }
     }
 
-    pub fn string_list() -> gtk::StringList {
-        gtk::StringList::new(&[
-            VideoCodec::AV1.display(),
-            VideoCodec::VP8.display(),
-            VideoCodec::VP9.display(),
-            VideoCodec::X264.display(),
-            VideoCodec::X265.display(),
-        ])
+    pub fn from_description(description: &str) -> Self {
+        match description {
+            desc if desc.contains("AV1") => VideoCodec::AV1,
+            desc if desc.contains("VP8") => VideoCodec::VP8,
+            desc if desc.contains("VP9") => VideoCodec::VP9,
+            desc if desc.contains("H.264") => VideoCodec::X264,
+            desc if desc.contains("H.265") || desc.contains("HEVC") => VideoCodec::X265,
+            desc if desc.contains("ProRes") => VideoCodec::PRORES,
+            desc if desc.contains("DNx") => VideoCodec::DNXHD,
+            desc if desc.contains("MPEG-2 Video") => VideoCodec::MPEG2,
+            // exact, "JPEG 2000" is a different codec
+            "Motion JPEG" | "JPEG" => VideoCodec::MJPEG,
+            desc if desc.contains("FFV1") || desc.contains("FFMpeg v1") => VideoCodec::FFV1,
+            _ => VideoCodec::Unknown,
+        }
     }
 
-    pub fn from_string_list_index(idx: u32) -> Self {
-        match idx {
-            0 => VideoCodec::AV1,
-            1 => VideoCodec::VP8,
-            2 => VideoCodec::VP9,
-            3 => VideoCodec::X264,
-            4 => VideoCodec::X265,
-            _ => VideoCodec::Unknown,
+    /// Intra and lossless codecs pick their own rate, setting a bitrate breaks some (dnxhd).
+    pub fn uses_bitrate(&self) -> bool {
+        matches!(
+            self,
+            VideoCodec::AV1
+                | VideoCodec::MPEG2
+                | VideoCodec::VP8
+                | VideoCodec::VP9
+                | VideoCodec::X264
+                | VideoCodec::X265
+        )
+    }
+
+    /// Encoders with a stats file for a second pass (x264enc, vp8enc, vp9enc).
+    pub fn supports_two_pass(&self) -> bool {
+        matches!(self, VideoCodec::X264 | VideoCodec::VP8 | VideoCodec::VP9)
+    }
+
+    // prores encoders call their profiles variants
+    fn profile_field(&self) -> &str {
+        match self {
+            VideoCodec::PRORES => "variant",
+            _ => "profile",
         }
     }
 
-    pub fn to_string_list_index(&self) -> u32 {
+    pub fn profiles(&self) -> &'static [&'static str] {
         match self {
-            VideoCodec::AV1 => 0,
-            VideoCodec::VP8 => 1,
-            VideoCodec::VP9 => 2,
-            VideoCodec::X264 => 3,
-            VideoCodec::X265 => 4,
-            VideoCodec::Unknown => 100,
+            VideoCodec::AV1 => &["main", "high", "professional"],
+            VideoCodec::MPEG2 => &["simple", "main", "high", "4:2:2"],
+            VideoCodec::PRORES => &["proxy", "lt", "standard", "hq", "4444", "4444xq"],
+            VideoCodec::VP9 => &["0", "1", "2", "3"],
+            VideoCodec::X264 => &[
+                "baseline",
+                "main",
+                "high",
+                "high-10",
+                "high-4:2:2",
+                "high-4:4:4",
+            ],
+            VideoCodec::X265 => &["main", "main-10", "main-422-10", "main-444", "main-444-10"],
+            _ => &[],
         }
     }
 
-    pub fn from_description(description: &str) -> Self {
-        match description {
-            desc if desc.contains("AV1") => VideoCodec::AV1,
-            desc if desc.contains("VP8") => VideoCodec::VP8,
-            desc if desc.contains("VP9") => VideoCodec::VP9,
-            desc if desc.contains("H.264") => VideoCodec::X264,
-            desc if desc.contains("H.265") || desc.contains("HEVC") => VideoCodec::X265,
-            _ => VideoCodec::Unknown,
+    pub fn levels(&self) -> &'static [&'static str] {
+        match self {
+            VideoCodec::AV1 => &["4.0", "4.1", "5.0", "5.1", "5.2", "5.3", "6.0", "6.1"],
+            VideoCodec::MPEG2 => &["low", "main", "high-1440", "high"],
+            VideoCodec::X264 => &[
+                "3", "3.1", "3.2", "4", "4.1", "4.2", "5", "5.1", "5.2", "6", "6.1", "6.2",
+            ],
+            VideoCodec::X265 => &["3", "3.1", "4", "4.1", "5", "5.1", "5.2", "6", "6.1", "6.2"],
+            _ => &[],
+        }
+    }
+
+    /// Lowest profile able to carry the given chroma subsampling and bit depth.
+    pub fn default_profile(
+        &self,
+        chroma: ChromaSubsampling,
+        bit_depth: BitDepth,
+    ) -> Option<&'static str> {
+        use BitDepth::*;
+        use ChromaSubsampling::*;
+
+        let profile = match (self, chroma, bit_depth) {
+            (VideoCodec::AV1, YUV420, _) => "main",
+            (VideoCodec::AV1, YUV444, _) => "high",
+            (VideoCodec::AV1, YUV422, _) => "professional",
+            (VideoCodec::MPEG2, YUV420, Eight) => "main",
+            (VideoCodec::MPEG2, YUV422, Eight) => "4:2:2",
+            (VideoCodec::PRORES, YUV444, _) => "4444",
+            (VideoCodec::PRORES, _, Ten) => "hq",
+            (VideoCodec::PRORES, _, Eight) => "standard",
+            (VideoCodec::VP9, YUV420, Eight) => "0",
+            (VideoCodec::VP9, _, Eight) => "1",
+            (VideoCodec::VP9, YUV420, Ten) => "2",
+            (VideoCodec::VP9, _, Ten) => "3",
+            (VideoCodec::X264, YUV420, Eight) => "high",
+            (VideoCodec::X264, YUV420, Ten) => "high-10",
+            (VideoCodec::X264, YUV422, _) => "high-4:2:2",
+            (VideoCodec::X264, YUV444, _) => "high-4:4:4",
+            (VideoCodec::X265, YUV420, Eight) => "main",
+            (VideoCodec::X265, YUV420, Ten) => "main-10",
+            (VideoCodec::X265, YUV422, _) => "main-422-10",
+            (VideoCodec::X265, YUV444, Eight) => "main-444",
+            (VideoCodec::X265, YUV444, Ten) => "main-444-10",
+            _ => return None,
+        };
+
+        Some(profile)
+    }
+
+    pub fn caps_with_profile(&self, profile: Option<&str>, level: Option<&str>) -> gst::Caps {
+        self.caps_builder()
+            .field_if_some(self.profile_field(), profile)
+            .field_if_some("level", level)
+            .build()
+    }
+
+    /// Codec to encode with when no local encoder exists for this one.
+    pub fn fallback(&self) -> Self {
+        match self {
+            // intermediate codecs keep quality high, prefer the lossless option
+            VideoCodec::PRORES | VideoCodec::DNXHD if VideoCodec::FFV1.is_available() => {
+                VideoCodec::FFV1
+            }
+            VideoCodec::VP8 if VideoCodec::VP9.is_available() => VideoCodec::VP9,
+            _ => VideoCodec::X264,
+        }
+    }
+
+    pub fn encodable(&self) -> Self {
+        if self.is_available() {
+            *self
+        } else {
+            self.fallback()
         }
     }
 }
 
-impl ContainerFormat {
+impl SubtitleCodec {
     pub fn display(&self) -> &str {
+        match self {
+            SubtitleCodec::ASS => "ASS",
+            SubtitleCodec::BITMAP => "Bitmap",
+            SubtitleCodec::TEXT => "Text",
+            SubtitleCodec::Unknown => "Unknown",
+        }
+    }
+
+    pub fn from_caps_name(name: &str) -> Self {
+        match name {
+            "application/x-ass" | "application/x-ssa" => SubtitleCodec::ASS,
+            "text/x-raw" | "application/x-subtitle" | "application/x-subtitle-vtt" => {
+                SubtitleCodec::TEXT
+            }
+            name if name.starts_with("subpicture/") => SubtitleCodec::BITMAP,
+            _ => SubtitleCodec::Unknown,
+        }
+    }
+
+    pub fn from_description(description: &str) -> Self {
+        let description = description.to_lowercase();
+
+        // bitmap checked first, dvb/pgs descriptions also mention subtitles
+        if ["dvd", "dvb", "pgs", "vobsub", "bitmap"]
+            .iter()
+            .any(|name| description.contains(name))
+        {
+            SubtitleCodec::BITMAP
+        } else if ["ssa", "ass", "substation"]
+            .iter()
+            .any(|name| description.contains(name))
+        {
+            SubtitleCodec::ASS
+        } else if ["text", "subrip", "srt", "utf-8", "vtt", "subtitle"]
+            .iter()
+            .any(|name| description.contains(name))
+        {
+            SubtitleCodec::TEXT
+        } else {
+            SubtitleCodec::Unknown
+        }
+    }
+
+    /// Bitmap subtitles can't be rendered or muxed as text.
+    pub fn is_text(&self) -> bool {
+        matches!(self, SubtitleCodec::ASS | SubtitleCodec::TEXT)
+    }
+}
+
+impl SelectableOption for ContainerFormat {
+    const OPTIONS: &'static [Self] = &[
+        ContainerFormat::MP4,
+        ContainerFormat::MKV,
+        ContainerFormat::QUICKTIME,
+    ];
+    const UNLISTED: Self = ContainerFormat::Unknown;
+
+    fn display(&self) -> &'static str {
         match self {
             ContainerFormat::MP4 => "MP4",
             ContainerFormat::MKV => "MKV",
@@ -382,6 +707,15 @@ impl ContainerFormat {
         }
     }
 
+    fn is_available(&self) -> bool {
+        match self {
+            ContainerFormat::Unknown => false,
+            _ => has_element_for_caps(&self.caps_builder().build(), gst::ElementFactoryType::MUXER),
+        }
+    }
+}
+
+impl ContainerFormat {
     // todo: use encoding profile file extension
     pub fn file_extension(&self) -> &str {
         match self {
@@ -392,6 +726,17 @@ impl ContainerFormat {
         }
     }
 
+    /// Only matroska can store ASS styling, other containers get plain text.
+    pub fn supports_styled_subtitles(&self) -> bool {
+        matches!(self, ContainerFormat::MKV)
+    }
+
+    /// Checks an installed muxer for this container takes a stream with `stream_caps`.
+    pub fn accepts(&self, stream_caps: &gst::Caps) -> bool {
+        *self != ContainerFormat::Unknown
+            && has_muxer_accepting(&self.caps_builder().build(), stream_caps)
+    }
+
     pub fn caps_builder(&self) -> Builder<NoFeature> {
         match self {
             ContainerFormat::MP4 => gst::Caps::builder("video/quicktime").field("variant", "iso"),
@@ -403,38 +748,348 @@ impl ContainerFormat {
         }
     }
 
-    pub fn string_list() -> gtk::StringList {
-        gtk::StringList::new(&[
-            ContainerFormat::MP4.display(),
-            ContainerFormat::MKV.display(),
-            ContainerFormat::QUICKTIME.display(),
-        ])
+    pub fn from_description(description: &str) -> Self {
+        // see webm report as matroska?
+        match description {
+            "Matroska" => ContainerFormat::MKV,
+            "ISO MP4/M4A" => ContainerFormat::MP4,
+            "Quicktime" => ContainerFormat::QUICKTIME,
+            _ => ContainerFormat::Unknown,
+        }
     }
+}
 
-    pub fn from_string_list_index(idx: u32) -> Self {
-        match idx {
-            0 => ContainerFormat::MP4,
-            1 => ContainerFormat::MKV,
-            2 => ContainerFormat::QUICKTIME,
-            _ => ContainerFormat::Unknown,
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChromaSubsampling {
+    YUV420,
+    YUV422,
+    YUV444,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BitDepth {
+    #[serde(rename = "8bit")]
+    Eight,
+    #[serde(rename = "10bit")]
+    Ten,
+}
+
+impl SelectableOption for ChromaSubsampling {
+    const OPTIONS: &'static [Self] = &[
+        ChromaSubsampling::YUV420,
+        ChromaSubsampling::YUV422,
+        ChromaSubsampling::YUV444,
+    ];
+    const UNLISTED: Self = ChromaSubsampling::YUV420;
+
+    fn display(&self) -> &'static str {
+        match self {
+            ChromaSubsampling::YUV420 => "4:2:0",
+            ChromaSubsampling::YUV422 => "4:2:2",
+            ChromaSubsampling::YUV444 => "4:4:4",
+        }
+    }
+}
+
+impl ChromaSubsampling {
+    /// Raw format handed to the encoder for this subsampling and bit depth.
+    pub fn raw_format(&self, bit_depth: BitDepth) -> gst_video::VideoFormat {
+        match (self, bit_depth) {
+            (ChromaSubsampling::YUV420, BitDepth::Eight) => gst_video::VideoFormat::I420,
+            (ChromaSubsampling::YUV422, BitDepth::Eight) => gst_video::VideoFormat::Y42b,
+            (ChromaSubsampling::YUV444, BitDepth::Eight) => gst_video::VideoFormat::Y444,
+            (ChromaSubsampling::YUV420, BitDepth::Ten) => gst_video::VideoFormat::I42010le,
+            (ChromaSubsampling::YUV422, BitDepth::Ten) => gst_video::VideoFormat::I42210le,
+            (ChromaSubsampling::YUV444, BitDepth::Ten) => gst_video::VideoFormat::Y44410le,
         }
     }
+}
 
-    pub fn to_string_list_index(&self) -> u32 {
+impl BitDepth {
+    /// Closest depth for a source's bits per component, deeper sources get 10 bit.
+    pub fn from_bits(bits: u32) -> Self {
+        if bits > 8 {
+            BitDepth::Ten
+        } else {
+            BitDepth::Eight
+        }
+    }
+}
+
+impl SelectableOption for BitDepth {
+    const OPTIONS: &'static [Self] = &[BitDepth::Eight, BitDepth::Ten];
+    const UNLISTED: Self = BitDepth::Eight;
+
+    fn display(&self) -> &'static str {
         match self {
-            ContainerFormat::MP4 => 0,
-            ContainerFormat::MKV => 1,
-            ContainerFormat::QUICKTIME => 2,
-            ContainerFormat::Unknown => 100,
+            BitDepth::Eight => "8-bit",
+            BitDepth::Ten => "10-bit",
         }
     }
-    pub fn from_description(description: &str) -> Self {
-        // see webm report as matroska?
-        match description {
-            "Matroska" => ContainerFormat::MKV,
-            "ISO MP4/M4A" => ContainerFormat::MP4,
-            "Quicktime" => ContainerFormat::QUICKTIME,
-            _ => ContainerFormat::Unknown,
+}
+
+/// Checks an installed encoder can output `codec_caps` while accepting `raw_caps` as input.
+pub fn has_video_encoder_for_caps(codec_caps: &gst::Caps, raw_caps: &gst::Caps) -> bool {
+    let encoders = gst::ElementFactory::factories_with_type(
+        gst::ElementFactoryType::VIDEO_ENCODER,
+        gst::Rank::MARGINAL,
+    );
+    let encoders =
+        gst::ElementFactory::list_filter(&encoders, codec_caps, gst::PadDirection::Src, false);
+    !gst::ElementFactory::list_filter(&encoders, raw_caps, gst::PadDirection::Sink, false)
+        .is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn video_codec_from_description() {
+        assert_eq!(
+            VideoCodec::from_description("Apple ProRes 422 HQ"),
+            VideoCodec::PRORES
+        );
+        assert_eq!(
+            VideoCodec::from_description("Avid DNxHD"),
+            VideoCodec::DNXHD
+        );
+        assert_eq!(
+            VideoCodec::from_description("MPEG-2 Video"),
+            VideoCodec::MPEG2
+        );
+        assert_eq!(
+            VideoCodec::from_description("Motion JPEG"),
+            VideoCodec::MJPEG
+        );
+        assert_eq!(VideoCodec::from_description("JPEG"), VideoCodec::MJPEG);
+        assert_eq!(
+            VideoCodec::from_description("JPEG 2000"),
+            VideoCodec::Unknown
+        );
+        assert_eq!(VideoCodec::from_description("FFMpeg v1"), VideoCodec::FFV1);
+        assert_eq!(
+            VideoCodec::from_description("H.264 (High Profile)"),
+            VideoCodec::X264
+        );
+    }
+
+    #[test]
+    fn default_profile_is_listed() {
+        let codecs = [
+            VideoCodec::AV1,
+            VideoCodec::MPEG2,
+            VideoCodec::PRORES,
+            VideoCodec::VP9,
+            VideoCodec::X264,
+            VideoCodec::X265,
+        ];
+        let chromas = [
+            ChromaSubsampling::YUV420,
+            ChromaSubsampling::YUV422,
+            ChromaSubsampling::YUV444,
+        ];
+
+        for codec in codecs {
+            for chroma in chromas {
+                for depth in [BitDepth::Eight, BitDepth::Ten] {
+                    if let Some(profile) = codec.default_profile(chroma, depth) {
+                        assert!(codec.profiles().contains(&profile));
+                    }
+                }
+            }
         }
+
+        assert_eq!(
+            VideoCodec::X264.default_profile(ChromaSubsampling::YUV420, BitDepth::Eight),
+            Some("high")
+        );
+    }
+
+    #[test]
+    fn audio_codec_from_description() {
+        assert_eq!(
+            AudioCodec::from_description("MPEG-1 Layer 3 (MP3)"),
+            AudioCodec::MP3
+        );
+        assert_eq!(AudioCodec::from_description("MPEG-4 AAC"), AudioCodec::AAC);
+        assert_eq!(
+            AudioCodec::from_description("Free Lossless Audio Codec (FLAC)"),
+            AudioCodec::FLAC
+        );
+        assert_eq!(AudioCodec::from_description("Vorbis"), AudioCodec::VORBIS);
+        assert_eq!(
+            AudioCodec::from_description("Uncompressed 24-bit PCM audio"),
+            AudioCodec::RAW
+        );
+    }
+
+    #[test]
+    fn subtitle_codec_from_description() {
+        assert_eq!(
+            SubtitleCodec::from_description("Advanced SubStation Alpha"),
+            SubtitleCodec::ASS
+        );
+        assert_eq!(
+            SubtitleCodec::from_description("DVB subtitles"),
+            SubtitleCodec::BITMAP
+        );
+        assert_eq!(
+            SubtitleCodec::from_description("Timed Text"),
+            SubtitleCodec::TEXT
+        );
+        assert_eq!(
+            SubtitleCodec::from_description("SubRip subtitle format"),
+            SubtitleCodec::TEXT
+        );
+    }
+
+    #[test]
+    fn selecting_a_video_stream_updates_info() {
+        let stream = |codec, width, height| VideoStreamInfo {
+            codec,
+            bitrate: 1_000_000,
+            title: String::new(),
+            width,
+            height,
+            framerate: gst::Fraction::new(30, 1),
+            pixel_aspect_ratio: gst::Fraction::new(1, 1),
+            colorimetry: None,
+            bit_depth: 8,
+            orientation: Orientation::default(),
+            stream_id: String::new(),
+        };
+
+        let mut info = VideoInfo::default();
+        info.container_info.video_streams = vec![
+            stream(VideoCodec::X264, 1920, 1080),
+            stream(VideoCodec::VP9, 1280, 720),
+        ];
+        info.select_video_stream(1).unwrap();
+
+        assert_eq!((info.width, info.height), (1280, 720));
+        assert_eq!(info.video_stream_idx, 1);
+        assert_eq!(info.container_info.video_codec, VideoCodec::VP9);
+        assert!(info.select_video_stream(2).is_err());
+        assert_eq!(info.video_stream_idx, 1);
+    }
+
+    #[test]
+    fn probe_json_shape() {
+        let colorimetry = gst_video::VideoColorimetry::new(
+            gst_video::VideoColorRange::Range16_235,
+            gst_video::VideoColorMatrix::Bt709,
+            gst_video::VideoTransferFunction::Bt709,
+            gst_video::VideoColorPrimaries::Bt709,
+        );
+        let orientation = Orientation::new_with_base(90.0, false);
+
+        let mut info = VideoInfo {
+            duration: ClockTime::from_mseconds(2500),
+            ..Default::default()
+        };
+        info.container_info.container = ContainerFormat::MKV;
+        info.container_info.video_streams = vec![VideoStreamInfo {
+            codec: VideoCodec::X265,
+            bitrate: 4_000_000,
+            title: "Main".to_string(),
+            width: 1920,
+            height: 1080,
+            framerate: gst::Fraction::new(30000, 1001),
+            pixel_aspect_ratio: gst::Fraction::new(1, 1),
+            colorimetry: Some(colorimetry),
+            bit_depth: 10,
+            orientation,
+            stream_id: "video-id".to_string(),
+        }];
+        info.container_info.audio_streams = vec![AudioStreamInfo {
+            codec: AudioCodec::OPUS,
+            bitrate: 128_000,
+            language: "en".to_string(),
+            title: String::new(),
+        }];
+        info.container_info.subtitle_streams = vec![SubtitleStreamInfo {
+            codec: SubtitleCodec::TEXT,
+            language: "de".to_string(),
+            title: "Forced".to_string(),
+            stream_id: "subtitle-id".to_string(),
+        }];
+        info.select_video_stream(0).unwrap();
+
+        let orientation = serde_json::json!({
+            "base_angle": 90.0,
+            "base_mirrored": false,
+            "angle": 0.0,
+            "mirrored": false,
+        });
+        // stream ids only match pads, they stay out of the probe output
+        let expected = serde_json::json!({
+            "duration": 2.5,
+            "framerate": "30000/1001",
+            "width": 1920,
+            "height": 1080,
+            "pixel_aspect_ratio": "1/1",
+            "aspect_ratio": 1920.0 / 1080.0,
+            "colorimetry": "bt709",
+            "video_stream_idx": 0,
+            "container_info": {
+                "container": "mkv",
+                "video_codec": "x265",
+                "video_bitrate": 4_000_000,
+                "video_bit_depth": 10,
+                "video_streams": [{
+                    "codec": "x265",
+                    "bitrate": 4_000_000,
+                    "title": "Main",
+                    "width": 1920,
+                    "height": 1080,
+                    "framerate": "30000/1001",
+                    "pixel_aspect_ratio": "1/1",
+                    "colorimetry": "bt709",
+                    "bit_depth": 10,
+                    "orientation": orientation,
+                }],
+                "audio_streams": [{
+                    "codec": "opus",
+                    "bitrate": 128_000,
+                    "language": "en",
+                    "title": "",
+                }],
+                "subtitle_streams": [{
+                    "codec": "text",
+                    "language": "de",
+                    "title": "Forced",
+                }],
+            },
+            "orientation": orientation,
+        });
+
+        assert_eq!(serde_json::to_value(&info).unwrap(), expected);
+    }
+
+    #[test]
+    fn option_indices_round_trip() {
+        fn round_trip<T: SelectableOption + std::fmt::Debug>() {
+            for (idx, option) in T::OPTIONS.iter().enumerate() {
+                assert_eq!(option.to_string_list_index(), idx as u32);
+                assert_eq!(T::from_string_list_index(idx as u32), *option);
+            }
+            assert_eq!(
+                T::from_string_list_index(T::OPTIONS.len() as u32),
+                T::UNLISTED
+            );
+        }
+
+        round_trip::<AudioCodec>();
+        round_trip::<VideoCodec>();
+        round_trip::<ContainerFormat>();
+        round_trip::<ChromaSubsampling>();
+        round_trip::<BitDepth>();
+
+        assert_eq!(
+            AudioCodec::NoAudio.to_string_list_index(),
+            gtk::INVALID_LIST_POSITION
+        );
     }
 }
@@ -1,12 +1,9 @@
 use crate::app::{App, AppMsg};
-use crate::renderer::{RenderCmd, TimerCmd, TimerEvent};
-use crate::ui::preview::Orientation;
-use crate::video::metadata::{
-    AudioCodec, AudioStreamInfo, ContainerFormat, VideoCodec, VideoContainerInfo, VideoInfo,
-    AUDIO_BITRATE_DEFAULT, VIDEO_BITRATE_DEFAULT,
-};
+use crate::renderer::{input_caps, RenderCmd, TimerCmd, TimerEvent};
+use crate::video::metadata::VideoInfo;
+use anyhow::Error;
 use gst::glib::FlagsClass;
-use gst::prelude::{ElementExt, ElementExtManual, GstObjectExt, ObjectExt, PadExt};
+use gst::prelude::{ElementExt, ElementExtManual, GstObjectExt, ObjectExt};
 use gst::{Bus, ClockTime, FlowSuccess, SeekFlags, State};
 use gst_app::AppSink;
 use relm4::ComponentSender;
@@ -55,12 +52,7 @@ impl Player {
             .unwrap();
         playbin.set_property_from_value("flags", &flags);
 
-        let app_sink = video_appsink(
-            app_sender,
-            sample_sender,
-            timer_sender,
-            AppSinkUsage::Preview,
-        );
+        let app_sink = video_appsink(app_sender, sample_sender, timer_sender);
 
         playbin.set_property("video-sink", &app_sink);
 
@@ -147,6 +139,38 @@ impl Player {
         self.set_is_playing(!self.is_playing);
     }
 
+    /// Shows the subtitle stream in the preview, matching what burn-in will export.
+    pub fn set_subtitle_stream(&mut self, stream_idx: Option<u32>) {
+        let flags = self.playbin.property_value("flags");
+        let flags_class = FlagsClass::with_type(flags.type_()).unwrap();
+        let builder = flags_class.builder_with_value(flags).unwrap();
+
+        let builder = if let Some(idx) = stream_idx {
+            self.playbin.set_property("current-text", idx as i32);
+            builder.set_by_nick("text")
+        } else {
+            builder.unset_by_nick("text")
+        };
+
+        self.playbin
+            .set_property_from_value("flags", &builder.build().unwrap());
+    }
+
+    /// Switches the previewed video stream, the info then describes the new stream.
+    pub fn set_video_stream(&mut self, stream_idx: u32) -> Result<(), Error> {
+        self.info.select_video_stream(stream_idx)?;
+        self.playbin
+            .set_property("current-video", stream_idx as i32);
+
+        // the switch shows with the next buffer, a paused preview needs a fresh one
+        if !self.is_playing {
+            if let Ok(position) = self.position() {
+                self.seek(position);
+            }
+        }
+        Ok(())
+    }
+
     pub fn seek(&self, timestamp: ClockTime) {
         self.playbin
             .seek_simple(SeekFlags::FLUSH | SeekFlags::KEY_UNIT, timestamp)
@@ -164,110 +188,15 @@ impl Player {
         self.is_mute = false;
     }
 
-    pub fn discover_metadata(&mut self) -> VideoInfo {
-        let duration = self.playbin.query_duration::<ClockTime>().unwrap();
-
-        let video_tags = self
-            .playbin
-            .emit_by_name::<Option<gst::TagList>>("get-video-tags", &[&0])
-            .expect("no video stream present");
-        let pad = self
-            .playbin
-            .emit_by_name::<Option<gst::Pad>>("get-video-pad", &[&0])
-            .expect("no pad availble for video stream");
-
-        let caps = pad.current_caps().unwrap();
-        let cap_struct = caps.structure(0).unwrap();
-
-        let width = cap_struct.get::<i32>("width").unwrap() as u32;
-        let height = cap_struct.get::<i32>("height").unwrap() as u32;
-
-        let orientation = if let Some(orientaiton) = video_tags.get::<gst::tags::ImageOrientation>()
-        {
-            // todo: generate orientation from tag
-            Orientation::new_with_base(90f32)
-        } else {
-            Orientation::default()
-        };
-
-        let framerate = cap_struct.get::<gst::Fraction>("framerate").unwrap();
-        let aspect_ratio = width as f64 / height as f64;
-
-        let video_codec = if let Some(tag) = video_tags.get::<gst::tags::VideoCodec>() {
-            VideoCodec::from_description(tag.get())
-        } else {
-            VideoCodec::Unknown
-        };
-
-        let video_bitrate = if let Some(tag) = video_tags.get::<gst::tags::Bitrate>() {
-            tag.get()
-        } else {
-            VIDEO_BITRATE_DEFAULT
-        };
-
-        let container = if let Some(tag) = video_tags.get::<gst::tags::ContainerFormat>() {
-            ContainerFormat::from_description(tag.get())
-        } else {
-            ContainerFormat::Unknown
-        };
-
-        let num_audio_streams = self.playbin.property::<i32>("n-audio");
-        let mut audio_streams_info: Vec<AudioStreamInfo> =
-            Vec::with_capacity(num_audio_streams as usize);
-
-        for i in 0..num_audio_streams {
-            let audio_tags = self
-                .playbin
-                .emit_by_name::<Option<gst::TagList>>("get-audio-tags", &[&i])
-                .expect("unable to get first audio stream");
-
-            let audio_codec = if let Some(tag) = audio_tags.get::<gst::tags::AudioCodec>() {
-                AudioCodec::from_description(tag.get())
-            } else {
-                AudioCodec::Unknown
-            };
-
-            let audio_bitrate = if let Some(tag) = audio_tags.get::<gst::tags::Bitrate>() {
-                tag.get()
-            } else {
-                AUDIO_BITRATE_DEFAULT
-            };
-
-            let language = if let Some(tag) = audio_tags.get::<gst::tags::LanguageCode>() {
-                tag.get().to_string()
-            } else {
-                "Unknown".to_string()
-            };
-
-            let stream_info = AudioStreamInfo {
-                title: "".to_string(),
-                codec: audio_codec,
-                bitrate: audio_bitrate,
-                language,
-            };
-
-            audio_streams_info.push(stream_info);
+    /// Takes the discovered info, filling in the duration from playbin when the container has none.
+    pub fn set_info(&mut self, mut info: VideoInfo) {
+        if info.duration == ClockTime::ZERO {
+            if let Some(duration) = self.playbin.query_duration::<ClockTime>() {
+                info.duration = duration;
+            }
         }
 
-        let codec_info = VideoContainerInfo {
-            container,
-            video_codec,
-            video_bitrate,
-            audio_streams: audio_streams_info,
-        };
-
-        let video_info = VideoInfo {
-            duration,
-            framerate,
-            width,
-            height,
-            aspect_ratio,
-            container_info: codec_info,
-            orientation,
-        };
-        self.info = video_info.clone();
-
-        video_info
+        self.info = info;
     }
 
     pub fn pipeline_bus(&self) -> Bus {
@@ -278,7 +207,8 @@ impl Player {
         for msg in bus.iter_timed(ClockTime::NONE) {
             use gst::MessageView;
             match msg.view() {
-                MessageView::AsyncDone(..) => {
+                // errors are reported by discovery after this returns
+                MessageView::AsyncDone(..) | MessageView::Error(..) => {
                     break;
                 }
                 _ => (),
@@ -287,33 +217,19 @@ impl Player {
     }
 }
 
-#[derive(PartialEq)]
-pub(crate) enum AppSinkUsage {
-    Export,
-    Preview,
-}
-
 pub(crate) fn video_appsink(
     app_sender: ComponentSender<App>,
     sample_sender: mpsc::Sender<RenderCmd>,
     timer_sender: mpsc::Sender<TimerCmd>,
-    usage: AppSinkUsage,
 ) -> AppSink {
-    // todo: way to customize eos message
-    //  (for preview want app_sendr, for export want to just send none to stop appsrc)
-    //  if export set sync to false?
-
+    // exports run on their own renderer, see video::export_queue
     let preroll_sender = sample_sender.clone();
     let preroll_timer_sender = timer_sender.clone();
     AppSink::builder()
         .enable_last_sample(true)
         .max_buffers(1)
-        .sync(usage == AppSinkUsage::Preview)
-        .caps(
-            &gst_video::VideoCapsBuilder::new()
-                .format(gst_video::VideoFormat::Rgba)
-                .build(),
-        )
+        .sync(true)
+        .caps(&input_caps())
         .callbacks(
             gst_app::AppSinkCallbacks::builder()
                 .new_sample(move |appsink| {
@@ -335,12 +251,7 @@ pub(crate) fn video_appsink(
                     Ok(FlowSuccess::Ok)
                 })
                 .eos(move |_| {
-                    // todo: implement a ExportingVideoFinished
-                    let msg = match usage {
-                        AppSinkUsage::Export => AppMsg::VideoFinished,
-                        AppSinkUsage::Preview => AppMsg::VideoFinished,
-                    };
-                    app_sender.input(msg);
+                    app_sender.input(AppMsg::VideoFinished);
                 })
                 .build(),
         )
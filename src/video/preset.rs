@@ -0,0 +1,337 @@
+use crate::renderer::ToneMapping;
+use crate::ui::sidebar::OutputContainerSettings;
+use crate::video::metadata::{
+    AudioCodec, BitDepth, ChromaSubsampling, ContainerFormat, VideoCodec, VideoContainerInfo,
+    AUDIO_BITRATE_DEFAULT,
+};
+use anyhow::{anyhow, Context, Error};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "kebab-case")]
+pub enum RateControl {
+    MatchSource,
+    Bitrate {
+        kbps: u32,
+    },
+    /// Decimal megabytes, the video bitrate is worked out from the trimmed duration.
+    TargetSize {
+        megabytes: f32,
+    },
+}
+
+impl RateControl {
+    pub fn target_size(&self) -> Option<u64> {
+        match self {
+            RateControl::TargetSize { megabytes } => Some((*megabytes as f64 * 1e6) as u64),
+            _ => None,
+        }
+    }
+}
+
+/// Named bundle of export settings, stored as toml in the user's config directory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportPreset {
+    pub name: String,
+    pub container: ContainerFormat,
+    pub video_codec: VideoCodec,
+    #[serde(default)]
+    pub video_profile: Option<String>,
+    #[serde(default)]
+    pub video_level: Option<String>,
+    pub chroma_subsampling: ChromaSubsampling,
+    pub bit_depth: BitDepth,
+    /// Output is scaled down to this height, keeping aspect ratio.
+    #[serde(default)]
+    pub max_height: Option<u32>,
+    pub rate_control: RateControl,
+    pub audio_codec: AudioCodec,
+    /// None keeps the source stream's bitrate.
+    #[serde(default)]
+    pub audio_kbps: Option<u32>,
+}
+
+impl ExportPreset {
+    pub fn builtin() -> Vec<ExportPreset> {
+        vec![
+            ExportPreset {
+                name: "Web 1080p H.264".to_string(),
+                container: ContainerFormat::MP4,
+                video_codec: VideoCodec::X264,
+                video_profile: Some("high".to_string()),
+                video_level: None,
+                chroma_subsampling: ChromaSubsampling::YUV420,
+                bit_depth: BitDepth::Eight,
+                max_height: Some(1080),
+                rate_control: RateControl::Bitrate { kbps: 8000 },
+                audio_codec: AudioCodec::AAC,
+                audio_kbps: Some(192),
+            },
+            ExportPreset {
+                name: "Archive MKV lossless".to_string(),
+                container: ContainerFormat::MKV,
+                video_codec: VideoCodec::FFV1,
+                video_profile: None,
+                video_level: None,
+                chroma_subsampling: ChromaSubsampling::YUV422,
+                bit_depth: BitDepth::Ten,
+                max_height: None,
+                rate_control: RateControl::MatchSource,
+                audio_codec: AudioCodec::FLAC,
+                audio_kbps: None,
+            },
+            ExportPreset {
+                name: "Discord under 10MB".to_string(),
+                container: ContainerFormat::MP4,
+                video_codec: VideoCodec::X264,
+                video_profile: Some("high".to_string()),
+                video_level: None,
+                chroma_subsampling: ChromaSubsampling::YUV420,
+                bit_depth: BitDepth::Eight,
+                max_height: Some(720),
+                rate_control: RateControl::TargetSize { megabytes: 10.0 },
+                audio_codec: AudioCodec::AAC,
+                audio_kbps: Some(96),
+            },
+        ]
+    }
+
+    pub fn container_settings(
+        &self,
+        info: &VideoContainerInfo,
+        audio_stream_idx: u32,
+    ) -> OutputContainerSettings {
+        let audio_stream = info.audio_streams.get(audio_stream_idx as usize);
+
+        OutputContainerSettings {
+            no_audio: audio_stream.is_none() || self.audio_codec == AudioCodec::NoAudio,
+            audio_stream_idx,
+            audio_codec: self.audio_codec,
+            audio_bitrate: match (self.audio_kbps, audio_stream) {
+                (Some(kbps), _) => kbps * 1000,
+                (None, Some(stream)) => stream.bitrate,
+                (None, None) => AUDIO_BITRATE_DEFAULT,
+            },
+            container: self.container,
+            video_codec: self.video_codec,
+            video_bitrate: match self.rate_control {
+                RateControl::MatchSource | RateControl::TargetSize { .. } => info.video_bitrate,
+                RateControl::Bitrate { kbps } => kbps * 1000,
+            },
+            video_profile: static_option(self.video_codec.profiles(), &self.video_profile),
+            video_level: static_option(self.video_codec.levels(), &self.video_level),
+            chroma_subsampling: self.chroma_subsampling,
+            bit_depth: self.bit_depth,
+            max_height: self.max_height,
+            target_size: self.rate_control.target_size(),
+            keep_anamorphic: false,
+            tone_mapping: ToneMapping::default(),
+        }
+    }
+
+    pub fn from_container_settings(
+        name: String,
+        settings: &OutputContainerSettings,
+        rate_control: RateControl,
+    ) -> Self {
+        Self {
+            name,
+            container: settings.container,
+            video_codec: settings.video_codec,
+            video_profile: settings.video_profile.map(str::to_string),
+            video_level: settings.video_level.map(str::to_string),
+            chroma_subsampling: settings.chroma_subsampling,
+            bit_depth: settings.bit_depth,
+            max_height: settings.max_height,
+            rate_control,
+            audio_codec: if settings.no_audio {
+                AudioCodec::NoAudio
+            } else {
+                settings.audio_codec
+            },
+            audio_kbps: Some(settings.audio_bitrate / 1000).filter(|kbps| *kbps != 0),
+        }
+    }
+
+    fn file_name(&self) -> String {
+        format!("{}.toml", self.slug())
+    }
+
+    fn slug(&self) -> String {
+        self.name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '-'
+                }
+            })
+            .collect()
+    }
+
+    /// Names can share a slug, those get the name's hash appended so saving never replaces
+    /// another preset.
+    fn path_in(&self, dir: &Path) -> Result<PathBuf, Error> {
+        let candidates = [
+            dir.join(self.file_name()),
+            dir.join(format!(
+                "{}-{:08x}.toml",
+                self.slug(),
+                name_hash(&self.name)
+            )),
+        ];
+
+        let saved_name = |path: &Path| {
+            let contents = std::fs::read_to_string(path).ok()?;
+            toml::from_str::<ExportPreset>(&contents)
+                .ok()
+                .map(|preset| preset.name)
+        };
+
+        if let Some(path) = candidates
+            .iter()
+            .find(|path| saved_name(path).as_deref() == Some(self.name.as_str()))
+        {
+            return Ok(path.clone());
+        }
+
+        candidates
+            .into_iter()
+            .find(|path| !path.exists())
+            .ok_or_else(|| {
+                anyhow!(
+                    "another preset is already saved as {}",
+                    dir.join(self.file_name()).display()
+                )
+            })
+    }
+
+    pub fn save(&self) -> Result<PathBuf, Error> {
+        self.save_in(&presets_dir())
+    }
+
+    fn save_in(&self, dir: &Path) -> Result<PathBuf, Error> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("could not create {}", dir.display()))?;
+
+        let path = self.path_in(dir)?;
+        std::fs::write(&path, toml::to_string_pretty(self)?)
+            .with_context(|| format!("could not write {}", path.display()))?;
+
+        Ok(path)
+    }
+}
+
+// fnv-1a, file names have to stay the same between builds
+fn name_hash(name: &str) -> u32 {
+    name.bytes().fold(0x811c9dc5, |hash, byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+// profiles and levels are kept as static strs in the settings
+fn static_option(options: &'static [&'static str], value: &Option<String>) -> Option<&'static str> {
+    let value = value.as_deref()?;
+    options.iter().find(|option| **option == value).copied()
+}
+
+pub fn presets_dir() -> PathBuf {
+    gst::glib::user_config_dir().join("moviola").join("presets")
+}
+
+/// Built in presets followed by the user's, a user preset replaces a built in one of the same name.
+pub fn load_presets() -> Vec<ExportPreset> {
+    let mut presets = ExportPreset::builtin();
+
+    let Ok(entries) = std::fs::read_dir(presets_dir()) else {
+        return presets;
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .collect();
+    paths.sort();
+
+    for path in paths {
+        let preset = std::fs::read_to_string(&path)
+            .map_err(Error::from)
+            .and_then(|contents| toml::from_str::<ExportPreset>(&contents).map_err(Error::from));
+
+        match preset {
+            Ok(preset) => {
+                presets.retain(|existing| existing.name != preset.name);
+                presets.push(preset);
+            }
+            Err(err) => tracing::warn!("skipping preset {}: {err}", path.display()),
+        }
+    }
+
+    presets
+}
+
+pub fn find_preset(name: &str) -> Result<ExportPreset, Error> {
+    load_presets()
+        .into_iter()
+        .find(|preset| preset.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| anyhow!("no preset named \"{name}\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preset_toml_round_trip() {
+        for preset in ExportPreset::builtin() {
+            let toml = toml::to_string_pretty(&preset).unwrap();
+            assert_eq!(toml::from_str::<ExportPreset>(&toml).unwrap(), preset);
+        }
+    }
+
+    #[test]
+    fn preset_profile_must_be_known() {
+        let mut preset = ExportPreset::builtin().remove(0);
+        preset.video_profile = Some("not-a-profile".to_string());
+
+        let settings = preset.container_settings(&VideoContainerInfo::default(), 0);
+        assert_eq!(settings.video_profile, None);
+        assert_eq!(settings.video_bitrate, 8_000_000);
+        assert!(settings.no_audio);
+        assert_eq!(settings.target_size, None);
+    }
+
+    #[test]
+    fn target_size_in_bytes() {
+        let rate_control = RateControl::TargetSize { megabytes: 8.0 };
+        assert_eq!(rate_control.target_size(), Some(8_000_000));
+        assert_eq!(RateControl::MatchSource.target_size(), None);
+    }
+
+    #[test]
+    fn presets_sharing_a_file_name_are_kept_apart() {
+        let dir = std::env::temp_dir().join(format!("moviola-presets-{}", std::process::id()));
+        let mut preset = ExportPreset::builtin().remove(0);
+
+        preset.name = "Web 1080p".to_string();
+        let first = preset.save_in(&dir).unwrap();
+        preset.name = "Web-1080p".to_string();
+        let second = preset.save_in(&dir).unwrap();
+        // saving again finds the preset's own file
+        let second_again = preset.save_in(&dir).unwrap();
+        preset.name = "Web_1080p".to_string();
+        let third = preset.save_in(&dir).unwrap();
+        let saved = std::fs::read_dir(&dir).unwrap().count();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(first, dir.join("web-1080p.toml"));
+        assert_ne!(first, second);
+        assert_eq!(second, second_again);
+        assert!(third != first && third != second);
+        assert_eq!(saved, 3);
+    }
+}
@@ -0,0 +1,63 @@
+use crate::video::metadata::ContainerFormat;
+use relm4::gtk;
+
+/// Options picked from a combo row, the row entries and their indices come from `OPTIONS`.
+pub trait SelectableOption: Copy + PartialEq + Sized + 'static {
+    /// Listed in row order, values left out (unknown, no audio) have no row.
+    const OPTIONS: &'static [Self];
+    /// Returned for indices outside the list, e.g. a row without a selection.
+    const UNLISTED: Self;
+
+    fn display(&self) -> &'static str;
+
+    /// Whether the element needed for this option (encoder, muxer) is installed.
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    /// Whether the option can be stored in `container`.
+    fn is_compatible_with(&self, _container: ContainerFormat) -> bool {
+        true
+    }
+
+    fn string_list() -> gtk::StringList {
+        let names: Vec<_> = Self::OPTIONS
+            .iter()
+            .map(|option| option.display())
+            .collect();
+        gtk::StringList::new(&names)
+    }
+
+    fn from_string_list_index(idx: u32) -> Self {
+        Self::OPTIONS
+            .get(idx as usize)
+            .copied()
+            .unwrap_or(Self::UNLISTED)
+    }
+
+    /// `gtk::INVALID_LIST_POSITION` clears the row's selection for unlisted values.
+    fn to_string_list_index(&self) -> u32 {
+        Self::OPTIONS
+            .iter()
+            .position(|option| option == self)
+            .map_or(gtk::INVALID_LIST_POSITION, |idx| idx as u32)
+    }
+}
+
+/// Checks an installed element of `factory_type` can output `caps`.
+pub fn has_element_for_caps(caps: &gst::Caps, factory_type: gst::ElementFactoryType) -> bool {
+    let factories = gst::ElementFactory::factories_with_type(factory_type, gst::Rank::MARGINAL);
+    !gst::ElementFactory::list_filter(&factories, caps, gst::PadDirection::Src, false).is_empty()
+}
+
+/// Checks an installed muxer writing `container_caps` accepts a `stream_caps` stream.
+pub fn has_muxer_accepting(container_caps: &gst::Caps, stream_caps: &gst::Caps) -> bool {
+    let muxers = gst::ElementFactory::factories_with_type(
+        gst::ElementFactoryType::MUXER,
+        gst::Rank::MARGINAL,
+    );
+    let muxers =
+        gst::ElementFactory::list_filter(&muxers, container_caps, gst::PadDirection::Src, false);
+    !gst::ElementFactory::list_filter(&muxers, stream_caps, gst::PadDirection::Sink, false)
+        .is_empty()
+}
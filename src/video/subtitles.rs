@@ -0,0 +1,498 @@
+use crate::renderer::{FrameSize, RenderCmd};
+use crate::ui::sidebar::SubtitleExportSettings;
+use crate::video::metadata::{ContainerFormat, VideoInfo};
+use anyhow::{anyhow, Error};
+use gst::prelude::{Cast, ElementExt, ObjectExt, PadExt};
+use gst::ClockTime;
+use gst_app::{AppSink, AppSrc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex};
+use std::time::Duration;
+
+// uridecodebin defaults plus ass/ssa, so styled subtitles are not parsed into pango markup
+pub static DECODE_CAPS: &str = "video/x-raw(ANY); audio/x-raw(ANY); text/x-raw(ANY); \
+    application/x-ass; application/x-ssa; subpicture/x-dvd; subpicture/x-dvb; subpicture/x-pgs";
+
+pub enum SubtitleData {
+    Sample(gst::Sample),
+    Gap(ClockTime, Option<ClockTime>),
+    Eos,
+}
+
+// sparse streams without gap events never catch up, frames stop waiting on them after this
+const BURN_IN_WAIT: Duration = Duration::from_millis(50);
+
+/// How far the burn-in cues have been sent to the renderer, frames wait for the cues covering them
+/// as the subtitle and video branches of the decode pipeline run independently.
+#[derive(Clone)]
+pub struct BurnInProgress(Arc<(Mutex<ClockTime>, Condvar)>);
+
+impl Default for BurnInProgress {
+    /// Nothing to wait for until burn-in is routed.
+    fn default() -> Self {
+        Self(Arc::new((Mutex::new(ClockTime::MAX), Condvar::new())))
+    }
+}
+
+impl BurnInProgress {
+    fn start(&self) {
+        *self.0 .0.lock().unwrap() = ClockTime::ZERO;
+    }
+
+    /// Every cue starting before `time` has been queued.
+    fn advance(&self, time: ClockTime) {
+        let (lock, cvar) = &*self.0;
+        let mut position = lock.lock().unwrap();
+        if time > *position {
+            *position = time;
+            cvar.notify_all();
+        }
+    }
+
+    fn finish(&self) {
+        self.advance(ClockTime::MAX);
+    }
+
+    /// Blocks until the cues up to `pts` are queued, or the stream has gone quiet.
+    pub fn wait_for(&self, pts: ClockTime) {
+        let (lock, cvar) = &*self.0;
+        let position = lock.lock().unwrap();
+        let _ = cvar
+            .wait_timeout_while(position, BURN_IN_WAIT, |position| *position < pts)
+            .unwrap();
+    }
+}
+
+/// Where each subtitle stream of the decode pipeline is sent, streams not listed are ignored.
+#[derive(Clone)]
+pub struct SubtitleRouting {
+    container: ContainerFormat,
+    /// Stream ids in discoverer order, decoded pads are matched to stream indices by them.
+    stream_ids: Vec<String>,
+    passthrough: Vec<(u32, mpsc::Sender<SubtitleData>)>,
+    burn_in: Option<(u32, FrameSize, mpsc::Sender<RenderCmd>)>,
+    burn_in_progress: BurnInProgress,
+    burn_in_linked: Arc<AtomicBool>,
+}
+
+impl SubtitleRouting {
+    /// Returns the routing and the receivers the encode pipeline reads passthrough streams from.
+    pub fn new(
+        settings: &SubtitleExportSettings,
+        info: &VideoInfo,
+        container: ContainerFormat,
+        output_size: FrameSize,
+        overlay_sender: mpsc::Sender<RenderCmd>,
+        burn_in_progress: BurnInProgress,
+    ) -> (Self, Vec<(u32, mpsc::Receiver<SubtitleData>)>) {
+        let streams = &info.container_info.subtitle_streams;
+        let is_text_stream = |idx: u32| {
+            streams
+                .get(idx as usize)
+                .is_some_and(|stream| stream.codec.is_text())
+        };
+
+        let burn_in = settings
+            .burn_in_stream
+            .filter(|idx| is_text_stream(*idx))
+            .map(|idx| (idx, output_size, overlay_sender));
+        if burn_in.is_some() {
+            burn_in_progress.start();
+        }
+
+        let mut passthrough = vec![];
+        let mut receivers = vec![];
+        for idx in settings.passthrough_streams.iter().copied() {
+            if !is_text_stream(idx) || settings.burn_in_stream == Some(idx) {
+                continue;
+            }
+
+            let (sender, recv) = mpsc::channel();
+            passthrough.push((idx, sender));
+            receivers.push((idx, recv));
+        }
+
+        let routing = Self {
+            container,
+            stream_ids: streams.iter().map(|s| s.stream_id.clone()).collect(),
+            passthrough,
+            burn_in,
+            burn_in_progress,
+            burn_in_linked: Arc::new(AtomicBool::new(false)),
+        };
+
+        (routing, receivers)
+    }
+
+    /// Index of the stream a decoded pad carries, uridecodebin doesn't expose pads in stream order.
+    pub fn stream_idx(&self, pad: &gst::Pad) -> Option<u32> {
+        let stream_id = pad.stream_id()?;
+        self.stream_ids
+            .iter()
+            .position(|id| !id.is_empty() && id.as_str() == stream_id.as_str())
+            .map(|idx| idx as u32)
+    }
+
+    /// Stops frames waiting on the burn-in stream when it couldn't be linked.
+    pub fn link_failed(&self, stream_idx: u32) {
+        if self
+            .burn_in
+            .as_ref()
+            .is_some_and(|(idx, _, _)| *idx == stream_idx)
+        {
+            self.burn_in_progress.finish();
+        }
+    }
+
+    /// Called once every pad is exposed, a burn-in stream without a pad has nothing to wait for.
+    pub fn no_more_pads(&self) {
+        if !self.burn_in_linked.load(Ordering::Relaxed) {
+            self.burn_in_progress.finish();
+        }
+    }
+
+    pub fn elements_for_stream(
+        &self,
+        stream_idx: u32,
+        caps_name: &str,
+    ) -> Result<Option<Vec<gst::Element>>, Error> {
+        if let Some((_, output_size, overlay_sender)) = self
+            .burn_in
+            .as_ref()
+            .filter(|(idx, _, _)| *idx == stream_idx)
+        {
+            self.burn_in_linked.store(true, Ordering::Relaxed);
+            return burn_in_elements(
+                caps_name,
+                *output_size,
+                overlay_sender.clone(),
+                self.burn_in_progress.clone(),
+            )
+            .map(Some);
+        }
+
+        match self.passthrough.iter().find(|(idx, _)| *idx == stream_idx) {
+            Some((_, sender)) => {
+                passthrough_elements(caps_name, self.container, sender.clone()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+pub fn is_subtitle_caps(name: &str) -> bool {
+    name.starts_with("text/") || is_styled_subtitle_caps(name)
+}
+
+fn is_styled_subtitle_caps(name: &str) -> bool {
+    name == "application/x-ass" || name == "application/x-ssa"
+}
+
+/// Decode side of passthrough, samples are forwarded untouched to the encode pipeline.
+fn passthrough_elements(
+    caps_name: &str,
+    container: ContainerFormat,
+    sender: mpsc::Sender<SubtitleData>,
+) -> Result<Vec<gst::Element>, Error> {
+    let mut elements = vec![gst::ElementFactory::make("queue").build()?];
+
+    if is_styled_subtitle_caps(caps_name) && !container.supports_styled_subtitles() {
+        elements.push(gst::ElementFactory::make("ssaparse").build()?);
+    }
+
+    let sample_sender = sender.clone();
+    let gap_sender = sender.clone();
+    let app_sink = AppSink::builder()
+        .sync(false)
+        .callbacks(
+            gst_app::AppSinkCallbacks::builder()
+                .new_sample(move |appsink| {
+                    let sample = appsink.pull_sample().unwrap();
+                    let _ = sample_sender.send(SubtitleData::Sample(sample));
+                    Ok(gst::FlowSuccess::Ok)
+                })
+                .eos(move |_| {
+                    let _ = sender.send(SubtitleData::Eos);
+                })
+                .build(),
+        )
+        .build();
+
+    // subtitles are sparse, gaps let the muxer carry on without waiting for the next cue
+    app_sink
+        .static_pad("sink")
+        .expect("appsink has no sinkpad")
+        .add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+            if let Some(gst::EventView::Gap(gap)) = info.event().map(|event| event.view()) {
+                let (timestamp, duration) = gap.get();
+                let _ = gap_sender.send(SubtitleData::Gap(timestamp, duration));
+            }
+            gst::PadProbeReturn::Ok
+        });
+
+    elements.push(app_sink.upcast());
+    Ok(elements)
+}
+
+/// Decode side of burn-in, cues are rendered to transparent frames and overlaid by the renderer.
+fn burn_in_elements(
+    caps_name: &str,
+    output_size: FrameSize,
+    overlay_sender: mpsc::Sender<RenderCmd>,
+    progress: BurnInProgress,
+) -> Result<Vec<gst::Element>, Error> {
+    let mut elements = vec![gst::ElementFactory::make("queue").build()?];
+
+    if is_styled_subtitle_caps(caps_name) {
+        elements.push(gst::ElementFactory::make("ssaparse").build()?);
+    }
+
+    let text_render = gst::ElementFactory::make("textrender").build()?;
+    text_render.set_property_from_str("valignment", "bottom");
+    text_render.set_property_from_str("halignment", "center");
+    // keep text the same relative size whatever the output resolution
+    let font_size = (output_size.height as f32 * 0.04).max(12.0);
+    text_render.set_property("font-desc", format!("Sans {font_size:.0}"));
+
+    let convert = gst::ElementFactory::make("videoconvert").build()?;
+
+    let sample_progress = progress.clone();
+    let eos_progress = progress.clone();
+    let app_sink = AppSink::builder()
+        .sync(false)
+        .caps(
+            &gst_video::VideoCapsBuilder::new()
+                .format(gst_video::VideoFormat::Rgba)
+                .width(output_size.width as i32)
+                .height(output_size.height as i32)
+                .build(),
+        )
+        .callbacks(
+            gst_app::AppSinkCallbacks::builder()
+                .new_sample(move |appsink| {
+                    let sample = appsink.pull_sample().unwrap();
+                    let pts = sample.buffer().and_then(|buffer| buffer.pts());
+                    let _ = overlay_sender.send(RenderCmd::QueueOverlay(sample));
+                    // sent on the same channel as frames, so queued before any frame it covers
+                    if let Some(pts) = pts {
+                        sample_progress.advance(pts);
+                    }
+                    Ok(gst::FlowSuccess::Ok)
+                })
+                .eos(move |_| eos_progress.finish())
+                .build(),
+        )
+        .build();
+
+    // gaps tell frames there is no cue to wait for
+    app_sink
+        .static_pad("sink")
+        .expect("appsink has no sinkpad")
+        .add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |_, info| {
+            if let Some(gst::EventView::Gap(gap)) = info.event().map(|event| event.view()) {
+                let (timestamp, duration) = gap.get();
+                progress.advance(timestamp + duration.unwrap_or(ClockTime::ZERO));
+            }
+            gst::PadProbeReturn::Ok
+        });
+
+    elements.extend([text_render, convert, app_sink.upcast()]);
+    Ok(elements)
+}
+
+/// Encode side of passthrough, retimes cues to the trim and converts markup to plain text.
+pub fn subtitle_appsrc(
+    name: &str,
+    subtitle_recv: mpsc::Receiver<SubtitleData>,
+    start: ClockTime,
+) -> AppSrc {
+    AppSrc::builder()
+        .name(name)
+        .format(gst::Format::Time)
+        .callbacks(
+            gst_app::AppSrcCallbacks::builder()
+                .need_data(move |appsrc, _| loop {
+                    match subtitle_recv.recv() {
+                        Ok(SubtitleData::Sample(sample)) => {
+                            if let Some(sample) = retime_subtitle_sample(&sample, start) {
+                                let _ = appsrc.push_sample(&sample);
+                                return;
+                            }
+                        }
+                        Ok(SubtitleData::Gap(timestamp, duration)) => {
+                            if timestamp >= start {
+                                let gap = gst::event::Gap::builder(timestamp - start)
+                                    .duration(duration)
+                                    .build();
+                                appsrc.send_event(gap);
+                            }
+                        }
+                        Ok(SubtitleData::Eos) | Err(_) => {
+                            let _ = appsrc.end_of_stream();
+                            return;
+                        }
+                    }
+                })
+                .build(),
+        )
+        .build()
+}
+
+/// Encodebin has no subtitle profiles, so the muxer pad is exposed on encodebin directly.
+pub fn link_to_muxer(
+    encode_bin: &gst::Element,
+    muxer: &gst::Element,
+    appsrc: &AppSrc,
+) -> Result<(), Error> {
+    let muxer_pad = muxer
+        .request_pad_simple("subtitle_%u")
+        .ok_or_else(|| anyhow!("muxer does not accept subtitles"))?;
+
+    let ghost_pad = gst::GhostPad::with_target(&muxer_pad)?;
+    ghost_pad.set_active(true)?;
+    encode_bin.add_pad(&ghost_pad)?;
+
+    let src_pad = appsrc.static_pad("src").expect("appsrc has no srcpad");
+    src_pad.link(&ghost_pad)?;
+
+    Ok(())
+}
+
+fn retime_subtitle_sample(sample: &gst::Sample, start: ClockTime) -> Option<gst::Sample> {
+    let caps = sample.caps()?;
+    let is_markup = caps
+        .structure(0)
+        .is_some_and(|s| s.get::<&str>("format").is_ok_and(|f| f == "pango-markup"));
+
+    let mut buffer = if is_markup {
+        let map = sample.buffer()?.map_readable().ok()?;
+        let text = String::from_utf8_lossy(map.as_slice());
+        let mut buffer = gst::Buffer::from_mut_slice(markup_to_plain_text(&text).into_bytes());
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(sample.buffer()?.pts());
+            buffer.set_duration(sample.buffer()?.duration());
+        }
+        buffer
+    } else {
+        sample.buffer_owned()?
+    };
+
+    if !shift_subtitle_buffer(buffer.make_mut(), start) {
+        return None;
+    }
+
+    let caps = if is_markup {
+        gst::Caps::builder("text/x-raw")
+            .field("format", "utf8")
+            .build()
+    } else {
+        caps.to_owned()
+    };
+
+    Some(gst::Sample::builder().buffer(&buffer).caps(&caps).build())
+}
+
+/// Makes the cue relative to `start`, clipping cues that began before it.
+/// Returns false when the cue ends before `start`.
+fn shift_subtitle_buffer(buffer: &mut gst::BufferRef, start: ClockTime) -> bool {
+    let Some(pts) = buffer.pts() else {
+        return false;
+    };
+    let duration = buffer.duration().unwrap_or(ClockTime::ZERO);
+
+    if pts >= start {
+        buffer.set_pts(pts - start);
+        return true;
+    }
+
+    let end = pts + duration;
+    if end <= start {
+        return false;
+    }
+
+    buffer.set_pts(ClockTime::ZERO);
+    buffer.set_duration(end - start);
+    true
+}
+
+fn markup_to_plain_text(markup: &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut in_tag = false;
+
+    for c in markup.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markup_stripped() {
+        assert_eq!(
+            markup_to_plain_text("<i>Hello</i> &amp; <b>goodbye</b> &lt;3"),
+            "Hello & goodbye <3"
+        );
+    }
+
+    #[test]
+    fn cue_shifted_by_trim() {
+        gst::init().unwrap();
+
+        let mut buffer = gst::Buffer::new();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(ClockTime::from_seconds(4));
+            buffer.set_duration(ClockTime::from_seconds(3));
+        }
+
+        let buffer = buffer.get_mut().unwrap();
+        assert!(shift_subtitle_buffer(buffer, ClockTime::from_seconds(5)));
+        assert_eq!(buffer.pts(), Some(ClockTime::ZERO));
+        assert_eq!(buffer.duration(), Some(ClockTime::from_seconds(2)));
+
+        let mut late = gst::Buffer::new();
+        {
+            let late = late.get_mut().unwrap();
+            late.set_pts(ClockTime::from_seconds(1));
+            late.set_duration(ClockTime::from_seconds(1));
+        }
+        assert!(!shift_subtitle_buffer(
+            late.get_mut().unwrap(),
+            ClockTime::from_seconds(5)
+        ));
+    }
+
+    #[test]
+    fn frames_wait_for_burn_in_cues() {
+        let progress = BurnInProgress::default();
+        // nothing routed, nothing to wait for
+        progress.wait_for(ClockTime::from_seconds(10));
+
+        progress.start();
+        let sender = progress.clone();
+        let cue = std::thread::spawn(move || sender.advance(ClockTime::from_seconds(2)));
+
+        let now = std::time::Instant::now();
+        progress.wait_for(ClockTime::from_seconds(1));
+        cue.join().unwrap();
+        assert_eq!(*progress.0 .0.lock().unwrap(), ClockTime::from_seconds(2));
+
+        // a quiet stream only holds frames up for a moment
+        progress.wait_for(ClockTime::from_seconds(3));
+        assert!(now.elapsed() >= BURN_IN_WAIT);
+    }
+}
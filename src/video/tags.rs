@@ -0,0 +1,266 @@
+use crate::ui::sidebar::MetadataExportSettings;
+use crate::video::export::TimelineExportSettings;
+use gst::prelude::{Cast, ElementExt, GstBinExt, TagSetterExt, TocSetterExt};
+use gst::ClockTime;
+use std::sync::{Arc, Mutex};
+
+// describe how the source was encoded rather than its content, so they are not carried over.
+// image-orientation is dropped as the renderer already bakes the orientation into the frames.
+static ENCODING_TAGS: [&str; 14] = [
+    "audio-codec",
+    "video-codec",
+    "subtitle-codec",
+    "codec",
+    "container-format",
+    "bitrate",
+    "nominal-bitrate",
+    "minimum-bitrate",
+    "maximum-bitrate",
+    "encoder",
+    "encoder-version",
+    "image-orientation",
+    "language-code",
+    "duration",
+];
+
+static LOCATION_TAG_PREFIX: &str = "geo-location";
+
+pub type SharedSourceMetadata = Arc<Mutex<SourceMetadata>>;
+
+/// Tags and chapters seen on the decode pipeline, applied to the muxer on export.
+#[derive(Debug, Default)]
+pub struct SourceMetadata {
+    tags: Option<gst::TagList>,
+    toc: Option<gst::Toc>,
+}
+
+impl SourceMetadata {
+    pub fn collect_event(&mut self, event: &gst::EventRef) {
+        match event.view() {
+            gst::EventView::Tag(tag_event) => {
+                let tags = tag_event.tag();
+                self.tags = Some(match self.tags.take() {
+                    Some(existing) => existing.merge(tags, gst::TagMergeMode::Keep),
+                    None => tags.to_owned(),
+                });
+            }
+            gst::EventView::Toc(toc_event) => {
+                let (toc, _updated) = toc_event.toc();
+                self.toc = Some(toc.to_owned());
+            }
+            _ => {}
+        }
+    }
+
+    pub fn export_tags(&self, settings: &MetadataExportSettings) -> gst::TagList {
+        let mut tags = self.tags.clone().unwrap_or_else(gst::TagList::new);
+
+        {
+            let tags = tags.make_mut();
+
+            let to_remove: Vec<String> = tags
+                .iter()
+                .map(|(name, _)| name.to_string())
+                .filter(|name| {
+                    ENCODING_TAGS.contains(&name.as_str())
+                        || (settings.strip_location && name.starts_with(LOCATION_TAG_PREFIX))
+                })
+                .collect();
+
+            for name in to_remove {
+                tags.remove_generic(name.as_str());
+            }
+
+            if let Some(title) = settings.title.as_ref() {
+                tags.add::<gst::tags::Title>(&title.as_str(), gst::TagMergeMode::Replace);
+            }
+
+            if let Some(comment) = settings.comment.as_ref() {
+                tags.add::<gst::tags::Comment>(&comment.as_str(), gst::TagMergeMode::Replace);
+            }
+        }
+
+        tags
+    }
+
+    pub fn export_toc(&self, timeline: TimelineExportSettings) -> Option<gst::Toc> {
+        self.toc
+            .as_ref()
+            .map(|toc| trim_toc(toc, timeline.start, timeline.end))
+    }
+
+    pub fn apply_to_muxer(
+        &self,
+        muxer: &gst::Element,
+        settings: &MetadataExportSettings,
+        timeline: TimelineExportSettings,
+    ) {
+        if let Some(tag_setter) = muxer.dynamic_cast_ref::<gst::TagSetter>() {
+            tag_setter.merge_tags(&self.export_tags(settings), gst::TagMergeMode::Replace);
+        }
+
+        if let Some(toc_setter) = muxer.dynamic_cast_ref::<gst::TocSetter>() {
+            if let Some(toc) = self.export_toc(timeline) {
+                toc_setter.set_toc(Some(&toc));
+            }
+        }
+    }
+}
+
+pub fn find_muxer(encode_bin: &gst::Element) -> Option<gst::Element> {
+    let bin = encode_bin.downcast_ref::<gst::Bin>()?;
+
+    bin.iterate_recurse()
+        .into_iter()
+        .filter_map(Result::ok)
+        .find(|element| {
+            element
+                .factory()
+                .is_some_and(|factory| factory.has_type(gst::ElementFactoryType::MUXER))
+        })
+}
+
+/// Shifts chapters so they are relative to `start`, dropping those outside the trim.
+pub fn trim_toc(toc: &gst::TocRef, start: ClockTime, end: ClockTime) -> gst::Toc {
+    let mut trimmed = gst::Toc::new(toc.scope());
+
+    {
+        let trimmed = trimmed.get_mut().unwrap();
+        if let Some(tags) = toc.tags() {
+            trimmed.set_tags(tags);
+        }
+
+        for entry in toc.entries() {
+            if let Some(entry) = trim_toc_entry(&entry, start, end) {
+                trimmed.append_entry(entry);
+            }
+        }
+    }
+
+    trimmed
+}
+
+fn trim_toc_entry(
+    entry: &gst::TocEntryRef,
+    start: ClockTime,
+    end: ClockTime,
+) -> Option<gst::TocEntry> {
+    let start_ns = start.nseconds() as i64;
+    let end_ns = end.nseconds() as i64;
+
+    let times = match entry.start_stop_times() {
+        Some((entry_start, entry_stop)) => {
+            // stop of -1 means the entry runs to the end of the media
+            let entry_stop = if entry_stop < 0 { end_ns } else { entry_stop };
+            if entry_stop <= start_ns || end_ns <= entry_start {
+                return None;
+            }
+
+            Some((
+                entry_start.max(start_ns) - start_ns,
+                entry_stop.min(end_ns) - start_ns,
+            ))
+        }
+        None => None,
+    };
+
+    let mut trimmed = gst::TocEntry::new(entry.entry_type(), entry.uid());
+
+    {
+        let trimmed = trimmed.get_mut().unwrap();
+        if let Some((entry_start, entry_stop)) = times {
+            trimmed.set_start_stop_times(entry_start, entry_stop);
+        }
+
+        if let Some(tags) = entry.tags() {
+            trimmed.set_tags(tags);
+        }
+
+        for sub_entry in entry.sub_entries() {
+            if let Some(sub_entry) = trim_toc_entry(&sub_entry, start, end) {
+                trimmed.append_sub_entry(sub_entry);
+            }
+        }
+    }
+
+    Some(trimmed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(uid: &str, start: u64, stop: u64) -> gst::TocEntry {
+        let mut entry = gst::TocEntry::new(gst::TocEntryType::Chapter, uid);
+        entry.get_mut().unwrap().set_start_stop_times(
+            ClockTime::from_seconds(start).nseconds() as i64,
+            ClockTime::from_seconds(stop).nseconds() as i64,
+        );
+        entry
+    }
+
+    #[test]
+    fn chapters_shifted_by_trim() {
+        gst::init().unwrap();
+
+        let mut toc = gst::Toc::new(gst::TocScope::Global);
+        let mut edition = gst::TocEntry::new(gst::TocEntryType::Edition, "edition");
+        {
+            let edition = edition.get_mut().unwrap();
+            edition.append_sub_entry(chapter("intro", 0, 10));
+            edition.append_sub_entry(chapter("middle", 10, 20));
+            edition.append_sub_entry(chapter("end", 20, 30));
+        }
+        toc.get_mut().unwrap().append_entry(edition);
+
+        let trimmed = trim_toc(
+            &toc,
+            ClockTime::from_seconds(12),
+            ClockTime::from_seconds(25),
+        );
+        let chapters = trimmed.entries()[0].sub_entries();
+
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[0].uid(), "middle");
+        assert_eq!(
+            chapters[0].start_stop_times(),
+            Some((0, ClockTime::from_seconds(8).nseconds() as i64))
+        );
+        assert_eq!(chapters[1].uid(), "end");
+        assert_eq!(
+            chapters[1].start_stop_times(),
+            Some((
+                ClockTime::from_seconds(8).nseconds() as i64,
+                ClockTime::from_seconds(13).nseconds() as i64
+            ))
+        );
+    }
+
+    #[test]
+    fn location_stripped_and_title_overridden() {
+        gst::init().unwrap();
+
+        let mut tags = gst::TagList::new();
+        {
+            let tags = tags.get_mut().unwrap();
+            tags.add::<gst::tags::Title>(&"original", gst::TagMergeMode::Append);
+            tags.add::<gst::tags::GeoLocationLatitude>(&43.6, gst::TagMergeMode::Append);
+            tags.add::<gst::tags::VideoCodec>(&"H.264", gst::TagMergeMode::Append);
+        }
+
+        let source = SourceMetadata {
+            tags: Some(tags),
+            toc: None,
+        };
+        let settings = MetadataExportSettings {
+            strip_location: true,
+            title: Some("renamed".to_string()),
+            comment: None,
+        };
+
+        let exported = source.export_tags(&settings);
+        assert_eq!(exported.get::<gst::tags::Title>().unwrap().get(), "renamed");
+        assert!(exported.get::<gst::tags::GeoLocationLatitude>().is_none());
+        assert!(exported.get::<gst::tags::VideoCodec>().is_none());
+    }
+}